@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- Accounts whose emails only differ by their case would break the index, the oldest one keeps the
+-- address and the others are soft deleted, so they can still be restored within the retention period
+UPDATE users SET deleted_at = CURRENT_TIMESTAMP
+WHERE deleted_at IS NULL AND EXISTS (
+    SELECT 1 FROM users AS other
+    WHERE other.deleted_at IS NULL
+    AND other.id < users.id
+    AND lower(trim(other.email)) = lower(trim(users.email))
+);
+
+UPDATE users SET email = lower(trim(email));
+
+-- A deleted account does not hold its email, it can be registered again
+CREATE UNIQUE INDEX users_email_unique ON users (email COLLATE NOCASE) WHERE deleted_at IS NULL;
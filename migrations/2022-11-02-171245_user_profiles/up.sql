@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE users ADD COLUMN display_name VARCHAR(255);
+ALTER TABLE users ADD COLUMN locale VARCHAR(35);
+ALTER TABLE users ADD COLUMN timezone VARCHAR(64);
+ALTER TABLE users ADD COLUMN avatar VARCHAR(255);
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE exports (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL,
+  status VARCHAR(255) CHECK (status IN ('pending', 'done', 'failed')) NOT NULL DEFAULT 'pending',
+  file VARCHAR(255),
+  created_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP,
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE invitations (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  email VARCHAR(255) NOT NULL,
+  role VARCHAR(255) CHECK (role IN ('admin', 'user')) NOT NULL DEFAULT 'user',
+  invited_by INTEGER NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP NOT NULL,
+  accepted_at TIMESTAMP,
+  revoked_at TIMESTAMP,
+  FOREIGN KEY (invited_by) REFERENCES users(id)
+);
@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+CREATE TABLE contacts_old (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL,
+  lastname VARCHAR(255) NOT NULL,
+  firstname VARCHAR(255) NOT NULL,
+  email VARCHAR(255) NOT NULL,
+  phone VARCHAR(255) NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+INSERT INTO contacts_old (id, user_id, lastname, firstname, email, phone)
+SELECT id, user_id, lastname, firstname, email, phone FROM contacts WHERE user_id IS NOT NULL;
+
+DROP TABLE contacts;
+
+ALTER TABLE contacts_old RENAME TO contacts;
+
+DROP TABLE IF EXISTS memberships;
+
+DROP TABLE IF EXISTS organizations;
@@ -0,0 +1,36 @@
+-- Your SQL goes here
+CREATE TABLE organizations (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  created_at TIMESTAMP NOT NULL
+);
+
+CREATE TABLE memberships (
+  organization_id INTEGER NOT NULL,
+  user_id INTEGER NOT NULL,
+  role VARCHAR(255) CHECK (role IN ('owner', 'admin', 'member')) NOT NULL DEFAULT 'member',
+  PRIMARY KEY (organization_id, user_id),
+  FOREIGN KEY (organization_id) REFERENCES organizations(id),
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+-- A contact belongs either to a user or to an organization
+CREATE TABLE contacts_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER,
+  organization_id INTEGER,
+  lastname VARCHAR(255) NOT NULL,
+  firstname VARCHAR(255) NOT NULL,
+  email VARCHAR(255) NOT NULL,
+  phone VARCHAR(255) NOT NULL,
+  CHECK ((user_id IS NULL) <> (organization_id IS NULL)),
+  FOREIGN KEY (user_id) REFERENCES users(id),
+  FOREIGN KEY (organization_id) REFERENCES organizations(id)
+);
+
+INSERT INTO contacts_new (id, user_id, lastname, firstname, email, phone)
+SELECT id, user_id, lastname, firstname, email, phone FROM contacts;
+
+DROP TABLE contacts;
+
+ALTER TABLE contacts_new RENAME TO contacts;
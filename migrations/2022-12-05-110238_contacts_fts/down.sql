@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS contacts_fts_update;
+
+DROP TRIGGER IF EXISTS contacts_fts_delete;
+
+DROP TRIGGER IF EXISTS contacts_fts_insert;
+
+DROP TABLE IF EXISTS contacts_fts;
@@ -0,0 +1,29 @@
+-- Your SQL goes here
+CREATE VIRTUAL TABLE contacts_fts USING fts5(
+  lastname,
+  firstname,
+  email,
+  phone,
+  content = 'contacts',
+  content_rowid = 'id',
+  tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO contacts_fts (contacts_fts) VALUES ('rebuild');
+
+CREATE TRIGGER contacts_fts_insert AFTER INSERT ON contacts BEGIN
+  INSERT INTO contacts_fts (rowid, lastname, firstname, email, phone)
+  VALUES (new.id, new.lastname, new.firstname, new.email, new.phone);
+END;
+
+CREATE TRIGGER contacts_fts_delete AFTER DELETE ON contacts BEGIN
+  INSERT INTO contacts_fts (contacts_fts, rowid, lastname, firstname, email, phone)
+  VALUES ('delete', old.id, old.lastname, old.firstname, old.email, old.phone);
+END;
+
+CREATE TRIGGER contacts_fts_update AFTER UPDATE ON contacts BEGIN
+  INSERT INTO contacts_fts (contacts_fts, rowid, lastname, firstname, email, phone)
+  VALUES ('delete', old.id, old.lastname, old.firstname, old.email, old.phone);
+  INSERT INTO contacts_fts (rowid, lastname, firstname, email, phone)
+  VALUES (new.id, new.lastname, new.firstname, new.email, new.phone);
+END;
@@ -0,0 +1,27 @@
+-- Your SQL goes here
+-- A tag belongs either to a user or to an organization, like the contacts it is applied to
+CREATE TABLE tags (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER,
+  organization_id INTEGER,
+  name VARCHAR(255) NOT NULL,
+  CHECK ((user_id IS NULL) <> (organization_id IS NULL)),
+  FOREIGN KEY (user_id) REFERENCES users(id),
+  FOREIGN KEY (organization_id) REFERENCES organizations(id)
+);
+
+CREATE UNIQUE INDEX tags_user_name_unique ON tags (user_id, name COLLATE NOCASE)
+WHERE user_id IS NOT NULL;
+
+CREATE UNIQUE INDEX tags_organization_name_unique ON tags (organization_id, name COLLATE NOCASE)
+WHERE organization_id IS NOT NULL;
+
+CREATE TABLE contact_tags (
+  contact_id INTEGER NOT NULL,
+  tag_id INTEGER NOT NULL,
+  PRIMARY KEY (contact_id, tag_id),
+  FOREIGN KEY (contact_id) REFERENCES contacts(id),
+  FOREIGN KEY (tag_id) REFERENCES tags(id)
+);
+
+CREATE INDEX contact_tags_tag_id ON contact_tags (tag_id);
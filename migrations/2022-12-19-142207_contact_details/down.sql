@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS contact_addresses;
+
+DROP TABLE IF EXISTS contact_phones;
+
+DROP TABLE IF EXISTS contact_emails;
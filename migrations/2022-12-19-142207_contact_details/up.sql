@@ -0,0 +1,44 @@
+-- Your SQL goes here
+-- The email and phone columns of the contacts are kept as a copy of the primary records
+CREATE TABLE contact_emails (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  contact_id INTEGER NOT NULL,
+  label VARCHAR(16) NOT NULL DEFAULT 'other' CHECK (label IN ('work', 'home', 'other')),
+  value VARCHAR(255) NOT NULL,
+  is_primary BOOLEAN NOT NULL DEFAULT 0,
+  FOREIGN KEY (contact_id) REFERENCES contacts(id)
+);
+
+CREATE INDEX contact_emails_contact_id ON contact_emails (contact_id);
+
+CREATE TABLE contact_phones (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  contact_id INTEGER NOT NULL,
+  label VARCHAR(16) NOT NULL DEFAULT 'other' CHECK (label IN ('work', 'home', 'mobile', 'other')),
+  value VARCHAR(255) NOT NULL,
+  is_primary BOOLEAN NOT NULL DEFAULT 0,
+  FOREIGN KEY (contact_id) REFERENCES contacts(id)
+);
+
+CREATE INDEX contact_phones_contact_id ON contact_phones (contact_id);
+
+CREATE TABLE contact_addresses (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  contact_id INTEGER NOT NULL,
+  label VARCHAR(16) NOT NULL DEFAULT 'other' CHECK (label IN ('work', 'home', 'other')),
+  street VARCHAR(255) NOT NULL DEFAULT '',
+  locality VARCHAR(255) NOT NULL DEFAULT '',
+  region VARCHAR(255) NOT NULL DEFAULT '',
+  postal_code VARCHAR(32) NOT NULL DEFAULT '',
+  country VARCHAR(255) NOT NULL DEFAULT '',
+  is_primary BOOLEAN NOT NULL DEFAULT 0,
+  FOREIGN KEY (contact_id) REFERENCES contacts(id)
+);
+
+CREATE INDEX contact_addresses_contact_id ON contact_addresses (contact_id);
+
+INSERT INTO contact_emails (contact_id, label, value, is_primary)
+SELECT id, 'other', email, 1 FROM contacts WHERE email <> '';
+
+INSERT INTO contact_phones (contact_id, label, value, is_primary)
+SELECT id, 'other', phone, 1 FROM contacts WHERE phone <> '';
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- The merged contact is deleted, a snapshot of it is kept with the merge
+CREATE TABLE contact_merges (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  winner_id INTEGER NOT NULL,
+  loser_id INTEGER NOT NULL,
+  merged_by INTEGER NOT NULL,
+  snapshot TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  FOREIGN KEY (winner_id) REFERENCES contacts(id),
+  FOREIGN KEY (merged_by) REFERENCES users(id)
+);
+
+CREATE INDEX contact_merges_winner_id ON contact_merges (winner_id);
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE contact_shares (
+  contact_id INTEGER NOT NULL,
+  user_id INTEGER NOT NULL,
+  permission VARCHAR(16) CHECK (permission IN ('read', 'write')) NOT NULL DEFAULT 'read',
+  shared_by INTEGER NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  PRIMARY KEY (contact_id, user_id),
+  FOREIGN KEY (contact_id) REFERENCES contacts(id),
+  FOREIGN KEY (user_id) REFERENCES users(id),
+  FOREIGN KEY (shared_by) REFERENCES users(id)
+);
+
+CREATE INDEX contact_shares_user_id ON contact_shares (user_id);
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+-- The owner is copied from the contact so that the history of a deleted contact stays scoped
+CREATE TABLE contact_versions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  contact_id INTEGER NOT NULL,
+  version INTEGER NOT NULL,
+  action VARCHAR(16) CHECK (action IN ('create', 'update', 'delete', 'restore')) NOT NULL,
+  user_id INTEGER,
+  organization_id INTEGER,
+  changed_by INTEGER NOT NULL,
+  snapshot TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  CHECK ((user_id IS NULL) <> (organization_id IS NULL)),
+  FOREIGN KEY (changed_by) REFERENCES users(id)
+);
+
+CREATE UNIQUE INDEX contact_versions_contact_version ON contact_versions (contact_id, version);
+CREATE INDEX contact_versions_user_id ON contact_versions (user_id);
+CREATE INDEX contact_versions_organization_id ON contact_versions (organization_id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS contact_field_values;
+
+DROP TABLE IF EXISTS custom_fields;
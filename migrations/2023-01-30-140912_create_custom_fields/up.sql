@@ -0,0 +1,30 @@
+-- Your SQL goes here
+-- The values are stored as text, `options` holds the JSON array of the choices of an enum field
+CREATE TABLE custom_fields (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER,
+  organization_id INTEGER,
+  name VARCHAR(64) NOT NULL,
+  kind VARCHAR(16) CHECK (kind IN ('text', 'number', 'date', 'enum', 'url')) NOT NULL,
+  options TEXT,
+  CHECK ((user_id IS NULL) <> (organization_id IS NULL)),
+  FOREIGN KEY (user_id) REFERENCES users(id),
+  FOREIGN KEY (organization_id) REFERENCES organizations(id)
+);
+
+CREATE UNIQUE INDEX custom_fields_user_name_unique ON custom_fields (user_id, name COLLATE NOCASE)
+WHERE user_id IS NOT NULL;
+
+CREATE UNIQUE INDEX custom_fields_organization_name_unique ON custom_fields (organization_id, name COLLATE NOCASE)
+WHERE organization_id IS NOT NULL;
+
+CREATE TABLE contact_field_values (
+  contact_id INTEGER NOT NULL,
+  field_id INTEGER NOT NULL,
+  value TEXT NOT NULL,
+  PRIMARY KEY (contact_id, field_id),
+  FOREIGN KEY (contact_id) REFERENCES contacts(id),
+  FOREIGN KEY (field_id) REFERENCES custom_fields(id)
+);
+
+CREATE INDEX contact_field_values_field_id ON contact_field_values (field_id, value);
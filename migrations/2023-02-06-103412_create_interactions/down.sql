@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE contacts DROP COLUMN last_contacted_at;
+DROP TABLE IF EXISTS interactions;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE interactions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  contact_id INTEGER NOT NULL,
+  kind VARCHAR(16) CHECK (kind IN ('call', 'meeting', 'note')) NOT NULL,
+  body TEXT NOT NULL,
+  occurred_at TIMESTAMP NOT NULL,
+  created_by INTEGER NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP NOT NULL,
+  FOREIGN KEY (contact_id) REFERENCES contacts(id),
+  FOREIGN KEY (created_by) REFERENCES users(id)
+);
+
+CREATE INDEX interactions_contact_occurred_at ON interactions (contact_id, occurred_at);
+
+-- The date of the last call or meeting, kept in line with the timeline
+ALTER TABLE contacts ADD COLUMN last_contacted_at TIMESTAMP;
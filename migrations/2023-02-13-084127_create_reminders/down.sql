@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users DROP COLUMN reminder_time;
+DROP TABLE IF EXISTS notifications;
+DROP TABLE IF EXISTS contact_dates;
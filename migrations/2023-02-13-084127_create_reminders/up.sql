@@ -0,0 +1,32 @@
+-- Your SQL goes here
+-- Birthdays and anniversaries come back every year, a follow-up happens once
+CREATE TABLE contact_dates (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  contact_id INTEGER NOT NULL,
+  kind VARCHAR(16) CHECK (kind IN ('birthday', 'anniversary', 'follow_up')) NOT NULL,
+  date DATE NOT NULL,
+  label VARCHAR(255) NOT NULL DEFAULT '',
+  remind_days_before INTEGER NOT NULL DEFAULT 0,
+  FOREIGN KEY (contact_id) REFERENCES contacts(id)
+);
+
+CREATE INDEX contact_dates_contact_id ON contact_dates (contact_id);
+
+-- A reminder is generated once for each occurrence of a date
+CREATE TABLE notifications (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL,
+  contact_id INTEGER NOT NULL,
+  kind VARCHAR(16) NOT NULL,
+  due_on DATE NOT NULL,
+  message TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  read_at TIMESTAMP,
+  FOREIGN KEY (user_id) REFERENCES users(id),
+  FOREIGN KEY (contact_id) REFERENCES contacts(id)
+);
+
+CREATE UNIQUE INDEX notifications_occurrence ON notifications (user_id, contact_id, kind, due_on);
+
+-- The local time, in the timezone of the profile, at which the reminders are sent
+ALTER TABLE users ADD COLUMN reminder_time VARCHAR(5);
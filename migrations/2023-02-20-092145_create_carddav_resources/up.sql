@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- The name and UID chosen by a CardDAV client for a contact it created, kept after the contact is
+-- deleted so that the deletion can be reported to the clients which synchronize the address book
+CREATE TABLE carddav_resources (
+  contact_id INTEGER PRIMARY KEY NOT NULL,
+  user_id INTEGER NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  uid VARCHAR(255) NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE UNIQUE INDEX carddav_resources_name ON carddav_resources (user_id, name);
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE contacts ADD COLUMN photo VARCHAR(255);
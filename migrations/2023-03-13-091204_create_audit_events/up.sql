@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE audit_events (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL,
+  actor_id INTEGER,
+  action VARCHAR(255) NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users(id),
+  FOREIGN KEY (actor_id) REFERENCES users(id)
+);
+
+CREATE INDEX audit_events_user_id ON audit_events (user_id);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX notifications_occurrence;
+DELETE FROM notifications WHERE id NOT IN (
+  SELECT min(id) FROM notifications GROUP BY user_id, contact_id, kind, due_on
+);
+ALTER TABLE notifications DROP COLUMN label;
+CREATE UNIQUE INDEX notifications_occurrence ON notifications (user_id, contact_id, kind, due_on);
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- The follow-ups of a contact due on the same day are told apart by their label
+DROP INDEX notifications_occurrence;
+ALTER TABLE notifications ADD COLUMN label VARCHAR(255) NOT NULL DEFAULT '';
+CREATE UNIQUE INDEX notifications_occurrence ON notifications (user_id, contact_id, kind, due_on, label);
@@ -0,0 +1,73 @@
+use crate::{
+    user::models::user::{Register, Role, User},
+    utils::db::establish_connection,
+};
+use std::{env::var, io::stdin};
+use validator::Validate;
+
+/// Create an admin account, failing if the account is not valid
+fn create_admin(name: String, email: String, password: String) -> Result<User, String> {
+    let register = Register {
+        name,
+        email,
+        password,
+    };
+
+    if register.password.is_empty() {
+        return Err("the password is empty".to_string());
+    }
+    register.validate().map_err(|err| err.to_string())?;
+
+    let connection = &mut establish_connection();
+
+    User::create(connection, register, Role::Admin).map_err(|err| err.to_string())
+}
+
+/// Create the first admin from the `ADMIN_NAME`, `ADMIN_EMAIL` and `ADMIN_PASSWORD` variables
+/// when no admin exists yet
+pub fn from_env() {
+    let (Ok(name), Ok(email), Ok(password)) =
+        (var("ADMIN_NAME"), var("ADMIN_EMAIL"), var("ADMIN_PASSWORD"))
+    else {
+        return;
+    };
+
+    let connection = &mut establish_connection();
+
+    match User::admin_exists(connection) {
+        Ok(false) => {}
+        Ok(true) => return,
+        Err(err) => {
+            tracing::error!("failed to look for an admin: {}", err);
+            return;
+        }
+    }
+
+    match create_admin(name, email, password) {
+        Ok(user) => tracing::info!("created the admin {}", user.email),
+        Err(err) => tracing::error!("failed to create the admin: {}", err),
+    }
+}
+
+/// Create an admin from the command line: `fer-server create-admin <name> <email>`,
+/// the password is read from the standard input
+pub fn from_args(args: &[String]) -> Result<(), String> {
+    let [name, email] = args else {
+        return Err("usage: fer-server create-admin <name> <email>".to_string());
+    };
+
+    let mut password = String::new();
+    stdin()
+        .read_line(&mut password)
+        .map_err(|err| err.to_string())?;
+
+    let user = create_admin(
+        name.clone(),
+        email.clone(),
+        password.trim_end_matches(['\r', '\n']).to_string(),
+    )?;
+
+    println!("created the admin {} ({})", user.email, user.id);
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use crate::{
+    auth::models::{
+        audit::{AuditAction, AuditEvent},
+        claims::Claims,
+    },
+    route,
+    user::models::user::{NewAccount, Register, Role, RoleUpdate, User},
+    utils::{db::establish_connection, error::ApiError},
+};
+use axum::{extract::Path, Json, Router};
+use validator::Validate;
+
+/// Create a user with any role
+async fn create_user(
+    claims: Claims,
+    Json(payload): Json<NewAccount>,
+) -> Result<Json<User>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    if payload.password.is_empty() {
+        return Err(ApiError::MissingCredentials);
+    }
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+    let role = Role::parse(&payload.role).ok_or(ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    let register = Register {
+        name: payload.name,
+        email: payload.email,
+        password: payload.password,
+    };
+    let user = User::create(connection, register, role).map_err(ApiError::from)?;
+    AuditEvent::record(
+        connection,
+        user.id,
+        Some(claims.id()),
+        AuditAction::Register,
+    )
+    .map_err(ApiError::from)?;
+
+    Ok(Json(user))
+}
+
+/// Change the role of a user
+async fn change_role(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Json(payload): Json<RoleUpdate>,
+) -> Result<Json<User>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    // An admin can not demote itself, and the last admin can not be demoted either
+    if claims.is_user(id) {
+        return Err(ApiError::NotValid);
+    }
+
+    let role = Role::parse(&payload.role).ok_or(ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    let user = connection.immediate_transaction(|connection| {
+        if role != Role::Admin && User::is_last_admin(connection, id).map_err(ApiError::from)? {
+            return Err(ApiError::Conflict);
+        }
+
+        let user = User::set_role(connection, id, role).map_err(|_| ApiError::NotFound)?;
+        AuditEvent::record(connection, id, Some(claims.id()), AuditAction::RoleChange)
+            .map_err(ApiError::from)?;
+
+        Ok::<_, ApiError>(user)
+    })?;
+
+    Ok(Json(user))
+}
+
+/// Suspend a user
+async fn suspend(claims: Claims, Path(id): Path<i32>) -> Result<Json<User>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    if claims.is_user(id) {
+        return Err(ApiError::NotValid);
+    }
+
+    let connection = &mut establish_connection();
+
+    let user = connection.immediate_transaction(|connection| {
+        if User::is_last_admin(connection, id).map_err(ApiError::from)? {
+            return Err(ApiError::Conflict);
+        }
+
+        let user = User::suspend(connection, id).map_err(|_| ApiError::NotFound)?;
+        AuditEvent::record(connection, id, Some(claims.id()), AuditAction::Suspend)
+            .map_err(ApiError::from)?;
+
+        Ok::<_, ApiError>(user)
+    })?;
+
+    Ok(Json(user))
+}
+
+/// Reactivate a suspended user
+async fn reactivate(claims: Claims, Path(id): Path<i32>) -> Result<Json<User>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    let connection = &mut establish_connection();
+
+    let user = User::reactivate(connection, id).map_err(|_| ApiError::NotFound)?;
+    AuditEvent::record(connection, id, Some(claims.id()), AuditAction::Reactivate)
+        .map_err(ApiError::from)?;
+
+    Ok(Json(user))
+}
+
+/// Admin routes
+pub fn controller(router: &Router) -> Router {
+    router
+        .clone()
+        .route(
+            route("/admin/users".to_string()).as_str(),
+            axum::routing::post(create_user),
+        )
+        .route(
+            route("/admin/users/:id/role".to_string()).as_str(),
+            axum::routing::put(change_role),
+        )
+        .route(
+            route("/admin/users/:id/suspend".to_string()).as_str(),
+            axum::routing::post(suspend),
+        )
+        .route(
+            route("/admin/users/:id/reactivate".to_string()).as_str(),
+            axum::routing::post(reactivate),
+        )
+}
@@ -1,11 +1,12 @@
 use super::models::{
+    audit::{AuditAction, AuditEvent},
     auth::{Auth, AuthBody, AuthPayload},
     claims::Claims,
     keys::KEYS,
 };
 use crate::{
     route,
-    user::models::user::{Register, User},
+    user::models::user::{Register, Role, User},
     utils::{db::establish_connection, error::ApiError},
 };
 use axum::{routing::post, Json, Router};
@@ -25,9 +26,15 @@ async fn login(Json(payload): Json<AuthPayload>) -> Result<Json<AuthBody>, ApiEr
     let auth = Auth::find_by_user_id(connection, user.id).map_err(|_| ApiError::NotFound)?;
 
     if !auth.is_valid(payload.password) {
+        AuditEvent::record(connection, user.id, None, AuditAction::LoginFailed)
+            .map_err(ApiError::from)?;
         return Err(ApiError::NotValid);
     }
 
+    if user.is_suspended() {
+        return Err(ApiError::AccountSuspended);
+    }
+
     let expiration = chrono::Utc::now()
         .checked_add_signed(chrono::Duration::hours(1))
         .expect("valid timestamp")
@@ -44,6 +51,9 @@ async fn login(Json(payload): Json<AuthPayload>) -> Result<Json<AuthBody>, ApiEr
     let token = encode(&Header::default(), &claims, &KEYS.encoding)
         .map_err(|_| ApiError::InternalServerError)?;
 
+    AuditEvent::record(connection, user.id, Some(user.id), AuditAction::Login)
+        .map_err(ApiError::from)?;
+
     // Send the authorized token
     Ok(Json(AuthBody {
         access_token: token,
@@ -59,7 +69,9 @@ async fn register(Json(payload): Json<Register>) -> Result<Json<String>, ApiErro
 
     let connection = &mut establish_connection();
 
-    let user = User::create(connection, payload).map_err(|_| ApiError::NotValid)?;
+    let user = User::create(connection, payload, Role::User).map_err(ApiError::from)?;
+    AuditEvent::record(connection, user.id, Some(user.id), AuditAction::Register)
+        .map_err(ApiError::from)?;
 
     Ok(Json(user.id.to_string()))
 }
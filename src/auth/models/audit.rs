@@ -0,0 +1,103 @@
+use crate::schema::audit_events;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::Serialize;
+
+/// Something which happened to an account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Register,
+    Login,
+    LoginFailed,
+    Update,
+    RoleChange,
+    Suspend,
+    Reactivate,
+    Delete,
+    Restore,
+    ExportRequest,
+    ExportDownload,
+}
+
+/// An event of the audit history of a user: who did what, and when
+#[derive(Debug, Serialize, Queryable)]
+pub struct AuditEvent {
+    pub id: i32,
+    pub user_id: i32,
+    pub actor_id: Option<i32>,
+    pub action: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_events)]
+struct NewAuditEvent<'a> {
+    user_id: i32,
+    actor_id: Option<i32>,
+    action: &'a str,
+    created_at: NaiveDateTime,
+}
+
+impl AuditAction {
+    /// Get the action as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Register => "register",
+            Self::Login => "login",
+            Self::LoginFailed => "login_failed",
+            Self::Update => "update",
+            Self::RoleChange => "role_change",
+            Self::Suspend => "suspend",
+            Self::Reactivate => "reactivate",
+            Self::Delete => "delete",
+            Self::Restore => "restore",
+            Self::ExportRequest => "export_request",
+            Self::ExportDownload => "export_download",
+        }
+    }
+}
+
+impl AuditEvent {
+    /// Record an action on the account of a user, the actor is unknown when nobody is logged in
+    pub fn record(
+        connection: &mut SqliteConnection,
+        user_id_param: i32,
+        actor_id_param: Option<i32>,
+        action_param: AuditAction,
+    ) -> Result<usize, Error> {
+        let new_event = NewAuditEvent {
+            user_id: user_id_param,
+            actor_id: actor_id_param,
+            action: action_param.as_str(),
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+        diesel::insert_into(audit_events::table)
+            .values(&new_event)
+            .execute(connection)
+    }
+
+    /// Find the audit history of a user, the oldest event first
+    pub fn all(connection: &mut SqliteConnection, user_id_param: i32) -> Result<Vec<Self>, Error> {
+        use crate::schema::audit_events::dsl::*;
+
+        audit_events
+            .filter(user_id.eq(user_id_param))
+            .order(id.asc())
+            .load::<AuditEvent>(connection)
+    }
+
+    /// Delete the audit history of users, and forget them as actors of the other histories
+    pub fn delete_for_users(
+        connection: &mut SqliteConnection,
+        ids: &[i32],
+    ) -> Result<usize, Error> {
+        use crate::schema::audit_events::dsl::*;
+
+        diesel::update(audit_events.filter(actor_id.eq_any(ids)))
+            .set(actor_id.eq(None::<i32>))
+            .execute(connection)?;
+        diesel::delete(audit_events.filter(user_id.eq_any(ids))).execute(connection)
+    }
+}
@@ -7,7 +7,10 @@ use headers::{authorization::Bearer, Authorization};
 use jsonwebtoken::{decode, Validation};
 use serde::{Deserialize, Serialize};
 
-use crate::utils::error::ApiError;
+use crate::{
+    user::models::user::User,
+    utils::{db::establish_connection, error::ApiError},
+};
 
 use super::keys::KEYS;
 
@@ -37,7 +40,12 @@ impl Claims {
 
     /// Check if the id is the same as the user id
     pub fn is_user(&self, id: i32) -> bool {
-        self.sub.parse::<i32>().unwrap() == id
+        self.id() == id
+    }
+
+    /// Get the id of the user
+    pub fn id(&self) -> i32 {
+        self.sub.parse::<i32>().unwrap()
     }
 }
 
@@ -58,6 +66,16 @@ where
         let data = decode::<Claims>(bearer.token(), &KEYS.decoding, &Validation::default())
             .map_err(|_| ApiError::InvalidToken)?;
 
-        Ok(data.claims)
+        // A token outlives its user, it must stop working once the account is deleted or suspended
+        // and follow the role changes
+        let connection = &mut establish_connection();
+        let mut claims = data.claims;
+        let user = User::find(connection, claims.id()).map_err(|_| ApiError::InvalidToken)?;
+        if user.is_suspended() {
+            return Err(ApiError::AccountSuspended);
+        }
+        claims.role = user.role;
+
+        Ok(claims)
     }
 }
@@ -0,0 +1,41 @@
+use super::keys::KEYS;
+use crate::utils::error::ApiError;
+use jsonwebtoken::{decode, encode, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+/// The claims of a signed link, valid for one purpose until it expires
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkClaims {
+    pub sub: i32,
+    pub purpose: String,
+    pub exp: i64,
+}
+
+impl LinkClaims {
+    /// Create the claims of a link
+    pub fn new(sub: i32, purpose: &str, exp: i64) -> Self {
+        Self {
+            sub,
+            purpose: purpose.to_string(),
+            exp,
+        }
+    }
+
+    /// Sign the claims into a token
+    pub fn encode(&self) -> Result<String, ApiError> {
+        encode(&Header::default(), self, &KEYS.encoding).map_err(|_| ApiError::TokenCreation)
+    }
+
+    /// Verify a token signed for the given purpose and return its claims
+    pub fn decode(token: &str, purpose: &str) -> Result<Self, ApiError> {
+        let claims = decode::<LinkClaims>(token, &KEYS.decoding, &Validation::default())
+            .map_err(|_| ApiError::InvalidToken)?
+            .claims;
+
+        if claims.purpose != purpose {
+            return Err(ApiError::InvalidToken);
+        }
+
+        Ok(claims)
+    }
+}
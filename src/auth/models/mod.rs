@@ -1,3 +1,5 @@
+pub mod audit;
 pub mod auth;
 pub mod claims;
 pub mod keys;
+pub mod link;
@@ -0,0 +1,65 @@
+use crate::{auth::models::auth::Auth, user::models::user::User, utils::db::establish_connection};
+use axum::{
+    async_trait,
+    extract::{FromRequest, RequestParts},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    TypedHeader,
+};
+use headers::{authorization::Basic, Authorization};
+
+/// The user of a CardDAV request, authenticated with the Basic scheme as the clients cannot get
+/// a token: the username is the email of the account and the password is its password
+pub struct DavUser(pub User);
+
+/// Ask the client for the credentials of the account
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [
+            (
+                header::WWW_AUTHENTICATE,
+                "Basic realm=\"fer\", charset=\"UTF-8\"",
+            ),
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+        ],
+        "Wrong credentials",
+    )
+        .into_response()
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for DavUser
+where
+    B: Send,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(basic)) =
+            TypedHeader::<Authorization<Basic>>::from_request(req)
+                .await
+                .map_err(|_| unauthorized())?;
+
+        let connection = &mut establish_connection();
+
+        let user = User::find_by_email(connection, basic.username().to_string())
+            .map_err(|_| unauthorized())?;
+        let auth = Auth::find_by_user_id(connection, user.id).map_err(|_| unauthorized())?;
+
+        if !auth.is_valid(basic.password().to_string()) {
+            return Err(unauthorized());
+        }
+
+        if user.is_suspended() {
+            return Err((
+                StatusCode::FORBIDDEN,
+                [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+                "Account suspended",
+            )
+                .into_response());
+        }
+
+        Ok(DavUser(user))
+    }
+}
@@ -0,0 +1,876 @@
+use super::{
+    auth::DavUser,
+    error::DavError,
+    models::resource::CardResource,
+    xml::{self, Multistatus, PropFind, PropName, Report, CALENDARSERVER, CARDDAV, DAV},
+};
+use crate::{
+    contact::{
+        models::{
+            contact::{Contact, Owner},
+            date::{DateKind, DatePayload},
+            details::ContactDetails,
+            version::ContactVersion,
+        },
+        photo,
+        vcard::{self, ParsedCard, Version},
+    },
+    route,
+    user::models::user::User,
+    utils::{db::establish_connection, etag::matches as etag_matches, image},
+};
+use axum::{
+    body::Bytes,
+    extract::Path,
+    http::{header, HeaderMap, HeaderName, Method, StatusCode},
+    response::{IntoResponse, Response},
+    routing::any,
+    Router,
+};
+use diesel::{result::Error, OptionalExtension, SqliteConnection};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use quick_xml::escape::escape;
+use std::collections::HashSet;
+use validator::Validate;
+
+/// The characters of a card name which are escaped in its path
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'@');
+
+/// The prefix of the sync tokens, which end with the id of the last version of the address book
+const SYNC_TOKEN_PREFIX: &str = "urn:fer:sync:";
+
+/// The methods of the CardDAV resources
+const ALLOW: &str = "OPTIONS, PROPFIND, REPORT, GET, HEAD, PUT, DELETE";
+
+/// Get the path of the principal of a user
+fn principal_path(user_id: i32) -> String {
+    route(format!("/dav/principals/{}/", user_id))
+}
+
+/// Get the path of the collection holding the address book of a user
+fn home_path(user_id: i32) -> String {
+    route(format!("/dav/addressbooks/{}/", user_id))
+}
+
+/// Get the path of the address book of a user
+fn book_path(user_id: i32) -> String {
+    format!("{}contacts/", home_path(user_id))
+}
+
+/// Get the path of a card in the address book of a user
+fn card_path(user_id: i32, name: &str) -> String {
+    format!(
+        "{}{}",
+        book_path(user_id),
+        utf8_percent_encode(name, SEGMENT)
+    )
+}
+
+/// Get the name of a card from its href, which may be a full URL
+fn card_name(user_id: i32, href: &str) -> Option<String> {
+    let path = match href.split_once("://") {
+        Some((_, rest)) => &rest[rest.find('/')?..],
+        None => href,
+    };
+    let name = percent_decode_str(path.strip_prefix(&book_path(user_id))?)
+        .decode_utf8()
+        .ok()?;
+
+    (!name.is_empty() && !name.contains('/')).then(|| name.into_owned())
+}
+
+/// Get the id of a contact from its default card name, `<id>.vcf`
+fn default_id(name: &str) -> Option<i32> {
+    name.strip_suffix(".vcf")?.parse().ok()
+}
+
+/// Write the sync token of an address book from the id of its last version
+fn sync_token(last: i32) -> String {
+    format!("{}{}", SYNC_TOKEN_PREFIX, last)
+}
+
+/// Write the ETag of a card from the version of its contact
+fn etag(contact_id: i32, version: i32) -> String {
+    format!("\"{}-{}\"", contact_id, version)
+}
+
+/// A contact published in an address book, its card is only written when it is asked as its
+/// photo is read from the storage
+struct Card {
+    name: String,
+    etag: String,
+    uid: Option<String>,
+    details: ContactDetails,
+}
+
+impl Card {
+    /// Write the card as vCard 3.0, the version every client supports
+    fn data(&self) -> String {
+        match &self.uid {
+            Some(uid) => vcard::to_vcard_with_uid(&self.details, Version::V3, uid),
+            None => vcard::to_vcard(&self.details, Version::V3),
+        }
+    }
+}
+
+/// Publish contacts of a user as cards
+fn cards(
+    connection: &mut SqliteConnection,
+    user_id: i32,
+    contacts: Vec<Contact>,
+) -> Result<Vec<Card>, Error> {
+    let resources = CardResource::all(connection, user_id)?;
+
+    Ok(ContactDetails::load(connection, contacts)?
+        .into_iter()
+        .map(|details| {
+            let id = details.contact.id;
+            let version = details.contact.version;
+
+            match resources.get(&id) {
+                Some(resource) => Card {
+                    name: resource.name.clone(),
+                    etag: etag(id, version),
+                    uid: Some(resource.uid.clone()),
+                    details,
+                },
+                None => Card {
+                    name: format!("{}.vcf", id),
+                    etag: etag(id, version),
+                    uid: None,
+                    details,
+                },
+            }
+        })
+        .collect())
+}
+
+/// Find the contact published under a name in the address book of a user
+fn find_contact(
+    connection: &mut SqliteConnection,
+    user_id: i32,
+    name: &str,
+) -> Result<Option<Contact>, Error> {
+    let id = match CardResource::find_by_name(connection, user_id, name).optional()? {
+        Some(resource) => resource.contact_id,
+        None => match default_id(name) {
+            Some(id) if !CardResource::exists(connection, id)? => id,
+            _ => return Ok(None),
+        },
+    };
+
+    Contact::find_owned(connection, Owner::User(user_id), id).optional()
+}
+
+/// Send a response without body, with a content type so that it is not formatted as JSON
+fn status(code: StatusCode) -> Response {
+    (code, [(header::CONTENT_TYPE, "text/plain; charset=utf-8")]).into_response()
+}
+
+/// Send an XML body
+fn xml_response(code: StatusCode, body: String) -> Response {
+    (
+        code,
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+/// Answer an OPTIONS request with the supported methods and DAV classes
+fn options() -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::ALLOW, ALLOW),
+            (HeaderName::from_static("dav"), "1, 3, addressbook"),
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+        ],
+    )
+        .into_response()
+}
+
+/// Refuse a method which the resource does not support
+fn not_allowed() -> Response {
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        [
+            (header::ALLOW, ALLOW),
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+        ],
+    )
+        .into_response()
+}
+
+/// Check that a user only reaches their own address book
+fn check_user(user: &User, user_id: i32) -> Result<(), DavError> {
+    if user.id == user_id {
+        Ok(())
+    } else {
+        Err(DavError::Status(StatusCode::FORBIDDEN))
+    }
+}
+
+/// Check if a PROPFIND asks for the children of a collection, an infinite depth being served
+/// as one level
+fn with_children(headers: &HeaderMap) -> bool {
+    headers
+        .get("depth")
+        .and_then(|depth| depth.to_str().ok())
+        .is_none_or(|depth| depth.trim() != "0")
+}
+
+/// Check the `If-Match` and `If-None-Match` headers of a change against the ETag of the card,
+/// none when the card does not exist
+fn check_preconditions(headers: &HeaderMap, etag: Option<&str>) -> Result<(), DavError> {
+    let get = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(expected) = get(header::IF_MATCH) {
+        if !etag.is_some_and(|etag| etag_matches(expected, etag)) {
+            return Err(DavError::Status(StatusCode::PRECONDITION_FAILED));
+        }
+    }
+    if let Some(expected) = get(header::IF_NONE_MATCH) {
+        if etag.is_some_and(|etag| etag_matches(expected, etag)) {
+            return Err(DavError::Status(StatusCode::PRECONDITION_FAILED));
+        }
+    }
+
+    Ok(())
+}
+
+/// The resources of the CardDAV server
+enum Resource<'a> {
+    Root,
+    Principal,
+    Home,
+    Book { token: &'a str },
+    Card(&'a Card),
+}
+
+impl Resource<'_> {
+    /// Get the names of the properties of the resource
+    fn names(&self) -> Vec<PropName> {
+        let mut names = vec![
+            (DAV, "resourcetype"),
+            (DAV, "current-user-principal"),
+            (DAV, "current-user-privilege-set"),
+        ];
+        match self {
+            Resource::Root | Resource::Home => {}
+            Resource::Principal => names.extend([
+                (DAV, "displayname"),
+                (DAV, "principal-URL"),
+                (CARDDAV, "addressbook-home-set"),
+            ]),
+            Resource::Book { .. } => names.extend([
+                (DAV, "displayname"),
+                (DAV, "sync-token"),
+                (DAV, "supported-report-set"),
+                (CARDDAV, "supported-address-data"),
+                (CALENDARSERVER, "getctag"),
+            ]),
+            Resource::Card(_) => names.extend([(DAV, "getetag"), (DAV, "getcontenttype")]),
+        }
+
+        names
+            .into_iter()
+            .map(|(namespace, name)| PropName::new(namespace, name))
+            .collect()
+    }
+
+    /// Get the escaped value of a property, none when the resource does not have it
+    fn property(&self, user: &User, name: &PropName) -> Option<String> {
+        let value = match (name.namespace.as_str(), name.name.as_str(), self) {
+            (DAV, "resourcetype", Resource::Root | Resource::Home) => "<d:collection/>".to_string(),
+            (DAV, "resourcetype", Resource::Principal) => "<d:principal/>".to_string(),
+            (DAV, "resourcetype", Resource::Book { .. }) => {
+                "<d:collection/><card:addressbook/>".to_string()
+            }
+            (DAV, "resourcetype", Resource::Card(_)) => String::new(),
+            (DAV, "current-user-principal", _) | (DAV, "principal-URL", Resource::Principal) => {
+                xml::href(&principal_path(user.id))
+            }
+            (DAV, "current-user-privilege-set", Resource::Book { .. } | Resource::Card(_)) => {
+                ["read", "write", "write-content", "bind", "unbind"]
+                    .iter()
+                    .map(|privilege| format!("<d:privilege><d:{}/></d:privilege>", privilege))
+                    .collect()
+            }
+            (DAV, "current-user-privilege-set", _) => {
+                "<d:privilege><d:read/></d:privilege>".to_string()
+            }
+            (DAV, "displayname", Resource::Principal) => {
+                escape(user.display_name.as_deref().unwrap_or(&user.name)).into_owned()
+            }
+            (DAV, "displayname", Resource::Book { .. }) => "Contacts".to_string(),
+            (CARDDAV, "addressbook-home-set", Resource::Principal) => {
+                xml::href(&home_path(user.id))
+            }
+            (DAV, "sync-token", Resource::Book { token })
+            | (CALENDARSERVER, "getctag", Resource::Book { token }) => escape(*token).into_owned(),
+            (DAV, "supported-report-set", Resource::Book { .. }) => [
+                "<card:addressbook-query/>",
+                "<card:addressbook-multiget/>",
+                "<d:sync-collection/>",
+            ]
+            .iter()
+            .map(|report| {
+                format!(
+                    "<d:supported-report><d:report>{}</d:report></d:supported-report>",
+                    report
+                )
+            })
+            .collect(),
+            (CARDDAV, "supported-address-data", Resource::Book { .. }) => {
+                "<card:address-data-type content-type=\"text/vcard\" version=\"3.0\"/>".to_string()
+            }
+            (DAV, "getetag", Resource::Card(card)) => escape(card.etag.as_str()).into_owned(),
+            (DAV, "getcontenttype", Resource::Card(_)) => "text/vcard; charset=utf-8".to_string(),
+            (CARDDAV, "address-data", Resource::Card(card)) => {
+                // The line breaks of the card are kept as they are by the XML parsers
+                escape(card.data().as_str()).replace('\r', "&#13;")
+            }
+            _ => return None,
+        };
+
+        Some(value)
+    }
+}
+
+/// Add a resource to a multistatus response with the properties which are asked
+fn describe(
+    multistatus: &mut Multistatus,
+    path: &str,
+    resource: &Resource,
+    user: &User,
+    request: &PropFind,
+) {
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+
+    match request {
+        PropFind::Names => {
+            found.extend(
+                resource
+                    .names()
+                    .into_iter()
+                    .map(|name| (name, String::new())),
+            );
+        }
+        PropFind::All => {
+            for name in resource.names() {
+                if let Some(value) = resource.property(user, &name) {
+                    found.push((name, value));
+                }
+            }
+        }
+        PropFind::Props(names) => {
+            for name in names {
+                match resource.property(user, name) {
+                    Some(value) => found.push((name.clone(), value)),
+                    None => missing.push(name.clone()),
+                }
+            }
+        }
+    }
+
+    multistatus.resource(path, &found, &missing);
+}
+
+/// Get the properties asked by a report, all of them when none is listed
+fn asked(props: Vec<PropName>) -> PropFind {
+    if props.is_empty() {
+        PropFind::All
+    } else {
+        PropFind::Props(props)
+    }
+}
+
+/// Answer a PROPFIND request on resources
+fn propfind(
+    user: &User,
+    body: &[u8],
+    resources: &[(String, Resource)],
+) -> Result<Response, DavError> {
+    let request = xml::propfind(&String::from_utf8_lossy(body))
+        .ok_or(DavError::Status(StatusCode::BAD_REQUEST))?;
+
+    let mut multistatus = Multistatus::default();
+    for (path, resource) in resources {
+        describe(&mut multistatus, path, resource, user, &request);
+    }
+
+    Ok(xml_response(
+        StatusCode::MULTI_STATUS,
+        multistatus.finish(None),
+    ))
+}
+
+/// Answer a REPORT request on the address book of a user
+fn report(
+    connection: &mut SqliteConnection,
+    user: &User,
+    body: &[u8],
+) -> Result<Response, DavError> {
+    let report = xml::report(&String::from_utf8_lossy(body))
+        .ok_or(DavError::Precondition(DAV, "supported-report"))?;
+    let owner = Owner::User(user.id);
+    let mut multistatus = Multistatus::default();
+
+    match report {
+        Report::Query {
+            props,
+            filter,
+            limit,
+        } => {
+            let contacts = Contact::all(connection, user.id)?;
+            let cards = cards(connection, user.id, contacts)?;
+            let request = asked(props);
+
+            let mut matched = cards
+                .iter()
+                .filter(|card| filter.matches(&vcard::properties(&card.data())));
+            for card in matched.by_ref().take(limit.unwrap_or(usize::MAX)) {
+                let path = card_path(user.id, &card.name);
+                describe(
+                    &mut multistatus,
+                    &path,
+                    &Resource::Card(card),
+                    user,
+                    &request,
+                );
+            }
+            if matched.next().is_some() {
+                multistatus.status(&book_path(user.id), "507 Insufficient Storage");
+            }
+
+            Ok(xml_response(
+                StatusCode::MULTI_STATUS,
+                multistatus.finish(None),
+            ))
+        }
+        Report::Multiget { props, hrefs } => {
+            let request = asked(props);
+
+            for href in hrefs {
+                let contact = match card_name(user.id, &href) {
+                    Some(name) => find_contact(connection, user.id, &name)?,
+                    None => None,
+                };
+                match contact {
+                    Some(contact) => {
+                        let card = cards(connection, user.id, vec![contact])?.remove(0);
+                        describe(
+                            &mut multistatus,
+                            &href,
+                            &Resource::Card(&card),
+                            user,
+                            &request,
+                        );
+                    }
+                    None => multistatus.status(&href, "404 Not Found"),
+                }
+            }
+
+            Ok(xml_response(
+                StatusCode::MULTI_STATUS,
+                multistatus.finish(None),
+            ))
+        }
+        Report::Sync { props, token } => {
+            let last = ContactVersion::last_id(connection, owner)?;
+            let since = match token.as_str() {
+                "" => None,
+                token => Some(
+                    token
+                        .strip_prefix(SYNC_TOKEN_PREFIX)
+                        .and_then(|since| since.parse::<i32>().ok())
+                        .filter(|since| (0..=last).contains(since))
+                        .ok_or(DavError::Precondition(DAV, "valid-sync-token"))?,
+                ),
+            };
+            let request = asked(props);
+
+            let mut contacts = Contact::all(connection, user.id)?;
+            let mut deleted = Vec::new();
+            if let Some(since) = since {
+                let changed = ContactVersion::changed_since(connection, owner, since)?
+                    .into_iter()
+                    .collect::<HashSet<_>>();
+                contacts.retain(|contact| changed.contains(&contact.id));
+
+                let present = contacts
+                    .iter()
+                    .map(|contact| contact.id)
+                    .collect::<HashSet<_>>();
+                let resources = CardResource::all(connection, user.id)?;
+                deleted.extend(changed.difference(&present).map(|id| {
+                    resources
+                        .get(id)
+                        .map(|resource| resource.name.clone())
+                        .unwrap_or_else(|| format!("{}.vcf", id))
+                }));
+            }
+
+            for card in cards(connection, user.id, contacts)? {
+                let path = card_path(user.id, &card.name);
+                describe(
+                    &mut multistatus,
+                    &path,
+                    &Resource::Card(&card),
+                    user,
+                    &request,
+                );
+            }
+            for name in deleted {
+                multistatus.status(&card_path(user.id, &name), "404 Not Found");
+            }
+
+            Ok(xml_response(
+                StatusCode::MULTI_STATUS,
+                multistatus.finish(Some(&sync_token(last))),
+            ))
+        }
+    }
+}
+
+/// Send a card, or only its headers for a HEAD request
+fn get_card(
+    connection: &mut SqliteConnection,
+    user: &User,
+    name: &str,
+    headers: &HeaderMap,
+    head: bool,
+) -> Result<Response, DavError> {
+    let contact =
+        find_contact(connection, user.id, name)?.ok_or(DavError::Status(StatusCode::NOT_FOUND))?;
+    let card = cards(connection, user.id, vec![contact])?.remove(0);
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|expected| etag_matches(expected, &card.etag));
+    let (code, body) = match (not_modified, head) {
+        (true, _) => (StatusCode::NOT_MODIFIED, String::new()),
+        (false, true) => (StatusCode::OK, String::new()),
+        (false, false) => (StatusCode::OK, card.data()),
+    };
+
+    Ok((
+        code,
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/vcard; charset=utf-8".to_string(),
+            ),
+            (header::ETAG, card.etag),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Create or replace a card, the contact being stored as the application understands it the
+/// response has no ETag so that the client fetches the card again
+fn put_card(
+    connection: &mut SqliteConnection,
+    user: &User,
+    name: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Response, DavError> {
+    let input = String::from_utf8_lossy(body);
+    let invalid = DavError::Precondition(CARDDAV, "valid-address-data");
+
+    let mut parsed = vcard::parse(&input);
+    if parsed.len() != 1 {
+        return Err(invalid);
+    }
+    let ParsedCard {
+        contact: mut payload,
+        photo: card_photo,
+    } = parsed.remove(0).map_err(|_| invalid)?;
+    // A photo which cannot be read is left out, as a card without photo keeps the stored one
+    let processed = card_photo.and_then(|bytes| image::process(&bytes).ok());
+
+    // The preconditions are checked in the transaction of the change, so that no other change
+    // comes in between
+    let (code, contact) = connection.immediate_transaction(|connection| {
+        let current = find_contact(connection, user.id, name)?;
+        let current_etag = current
+            .as_ref()
+            .map(|contact| etag(contact.id, contact.version));
+        check_preconditions(headers, current_etag.as_deref())?;
+
+        match current {
+            Some(contact) => {
+                let details = ContactDetails::load_one(connection, contact)?;
+
+                // The follow-ups are not written in the cards, they are kept
+                if let Some(dates) = payload.dates.as_mut() {
+                    dates.extend(
+                        details
+                            .dates
+                            .iter()
+                            .filter(|date| date.kind() == DateKind::FollowUp)
+                            .map(DatePayload::from),
+                    );
+                }
+                payload.normalize();
+                payload.validate().map_err(|_| invalid)?;
+
+                let details = payload.update(connection, details.contact.id, user.id)?;
+
+                Ok((StatusCode::NO_CONTENT, details.contact))
+            }
+            None => {
+                // The default names belong to the contacts created by the application
+                if default_id(name).is_some() {
+                    return Err(DavError::Status(StatusCode::CONFLICT));
+                }
+                payload.normalize();
+                payload.validate().map_err(|_| invalid)?;
+
+                let uid = vcard::properties(&input)
+                    .into_iter()
+                    .find(|(property, _)| property == "UID")
+                    .map(|(_, uid)| uid.trim().to_string())
+                    .filter(|uid| !uid.is_empty())
+                    .unwrap_or_else(|| name.trim_end_matches(".vcf").to_string());
+
+                let details = payload.create(connection, Owner::User(user.id), user.id)?;
+                CardResource {
+                    contact_id: details.contact.id,
+                    user_id: user.id,
+                    name: name.to_string(),
+                    uid,
+                }
+                .create(connection)?;
+
+                Ok((StatusCode::CREATED, details.contact))
+            }
+        }
+    })?;
+
+    // The files of the photo are only written once the contact is, a rolled back change leaves
+    // none behind
+    if let Some(processed) = &processed {
+        photo::store(connection, contact, processed, user.id)
+            .map_err(|_| DavError::Status(StatusCode::INTERNAL_SERVER_ERROR))?;
+    }
+
+    Ok(status(code))
+}
+
+/// Delete the contact of a card
+fn delete_card(
+    connection: &mut SqliteConnection,
+    user: &User,
+    name: &str,
+    headers: &HeaderMap,
+) -> Result<Response, DavError> {
+    connection.immediate_transaction(|connection| {
+        let contact = find_contact(connection, user.id, name)?
+            .ok_or(DavError::Status(StatusCode::NOT_FOUND))?;
+        check_preconditions(headers, Some(&etag(contact.id, contact.version)))?;
+
+        ContactDetails::load_one(connection, contact)
+            .and_then(|details| details.delete(connection, user.id))?;
+
+        Ok::<_, DavError>(())
+    })?;
+
+    Ok(status(StatusCode::NO_CONTENT))
+}
+
+/// Send the clients looking for the address books to the root of the server
+pub async fn well_known() -> Response {
+    (
+        StatusCode::MOVED_PERMANENTLY,
+        [
+            (header::LOCATION, route("/dav/".to_string())),
+            (
+                header::CONTENT_TYPE,
+                "text/plain; charset=utf-8".to_string(),
+            ),
+        ],
+    )
+        .into_response()
+}
+
+/// The root of the server, which gives the principal of the user
+pub async fn root(
+    method: Method,
+    DavUser(user): DavUser,
+    body: Bytes,
+) -> Result<Response, DavError> {
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => propfind(
+            &user,
+            &body,
+            &[(route("/dav/".to_string()), Resource::Root)],
+        ),
+        _ => Ok(not_allowed()),
+    }
+}
+
+/// The principal of a user, which gives their address book collection
+pub async fn principal(
+    method: Method,
+    DavUser(user): DavUser,
+    Path(user_id): Path<i32>,
+    body: Bytes,
+) -> Result<Response, DavError> {
+    check_user(&user, user_id)?;
+
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => propfind(
+            &user,
+            &body,
+            &[(principal_path(user.id), Resource::Principal)],
+        ),
+        _ => Ok(not_allowed()),
+    }
+}
+
+/// The collection holding the address book of a user
+pub async fn home(
+    method: Method,
+    DavUser(user): DavUser,
+    Path(user_id): Path<i32>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, DavError> {
+    check_user(&user, user_id)?;
+
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let connection = &mut establish_connection();
+            let token = sync_token(ContactVersion::last_id(connection, Owner::User(user.id))?);
+
+            let mut resources = vec![(home_path(user.id), Resource::Home)];
+            if with_children(&headers) {
+                resources.push((book_path(user.id), Resource::Book { token: &token }));
+            }
+
+            propfind(&user, &body, &resources)
+        }
+        _ => Ok(not_allowed()),
+    }
+}
+
+/// The address book of a user
+pub async fn book(
+    method: Method,
+    DavUser(user): DavUser,
+    Path(user_id): Path<i32>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, DavError> {
+    check_user(&user, user_id)?;
+
+    let connection = &mut establish_connection();
+
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let token = sync_token(ContactVersion::last_id(connection, Owner::User(user.id))?);
+            let cards = if with_children(&headers) {
+                let contacts = Contact::all(connection, user.id)?;
+                cards(connection, user.id, contacts)?
+            } else {
+                Vec::new()
+            };
+
+            let mut resources = vec![(book_path(user.id), Resource::Book { token: &token })];
+            resources.extend(
+                cards
+                    .iter()
+                    .map(|card| (card_path(user.id, &card.name), Resource::Card(card))),
+            );
+
+            propfind(&user, &body, &resources)
+        }
+        "REPORT" => report(connection, &user, &body),
+        _ => Ok(not_allowed()),
+    }
+}
+
+/// A card of the address book of a user
+pub async fn card(
+    method: Method,
+    DavUser(user): DavUser,
+    Path((user_id, name)): Path<(i32, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, DavError> {
+    check_user(&user, user_id)?;
+
+    let connection = &mut establish_connection();
+
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "GET" => get_card(connection, &user, &name, &headers, false),
+        "HEAD" => get_card(connection, &user, &name, &headers, true),
+        "PUT" => put_card(connection, &user, &name, &headers, &body),
+        "DELETE" => delete_card(connection, &user, &name, &headers),
+        "PROPFIND" => {
+            let contact = find_contact(connection, user.id, &name)?
+                .ok_or(DavError::Status(StatusCode::NOT_FOUND))?;
+            let card = cards(connection, user.id, vec![contact])?.remove(0);
+
+            propfind(
+                &user,
+                &body,
+                &[(card_path(user.id, &card.name), Resource::Card(&card))],
+            )
+        }
+        _ => Ok(not_allowed()),
+    }
+}
+
+/// CardDAV routes, the collections answering with and without their trailing slash
+pub fn controller(router: &Router) -> Router {
+    router
+        .clone()
+        .route("/.well-known/carddav", any(well_known))
+        .route(route("/dav".to_string()).as_str(), any(root))
+        .route(route("/dav/".to_string()).as_str(), any(root))
+        .route(
+            route("/dav/principals/:id".to_string()).as_str(),
+            any(principal),
+        )
+        .route(
+            route("/dav/principals/:id/".to_string()).as_str(),
+            any(principal),
+        )
+        .route(
+            route("/dav/addressbooks/:id".to_string()).as_str(),
+            any(home),
+        )
+        .route(
+            route("/dav/addressbooks/:id/".to_string()).as_str(),
+            any(home),
+        )
+        .route(
+            route("/dav/addressbooks/:id/contacts".to_string()).as_str(),
+            any(book),
+        )
+        .route(
+            route("/dav/addressbooks/:id/contacts/".to_string()).as_str(),
+            any(book),
+        )
+        .route(
+            route("/dav/addressbooks/:id/contacts/:name".to_string()).as_str(),
+            any(card),
+        )
+}
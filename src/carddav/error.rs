@@ -0,0 +1,43 @@
+use super::xml::{self, PropName};
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// The errors of the CardDAV requests, which are not sent in the JSON format of the API
+#[derive(Debug, Clone, Copy)]
+pub enum DavError {
+    Status(StatusCode),
+    /// A precondition of the request failed, given by its namespace and name
+    Precondition(&'static str, &'static str),
+}
+
+impl From<diesel::result::Error> for DavError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => Self::Status(StatusCode::NOT_FOUND),
+            err => {
+                tracing::error!("carddav query failed: {}", err);
+                Self::Status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+impl IntoResponse for DavError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Status(status) => (
+                status,
+                [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            )
+                .into_response(),
+            Self::Precondition(namespace, name) => (
+                StatusCode::FORBIDDEN,
+                [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+                xml::error(&PropName::new(namespace, name)),
+            )
+                .into_response(),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod auth;
+pub mod controllers;
+pub mod error;
+pub mod models;
+pub mod xml;
@@ -0,0 +1,73 @@
+use crate::schema::carddav_resources;
+use diesel::prelude::*;
+use diesel::result::Error;
+use std::collections::HashMap;
+
+/// The name and UID a CardDAV client gave to a contact it created, the other contacts are
+/// published as `<id>.vcf` with the UID of their vCard export
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = carddav_resources)]
+pub struct CardResource {
+    pub contact_id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub uid: String,
+}
+
+impl CardResource {
+    /// Get the resources of a user by contact
+    pub fn all(
+        connection: &mut SqliteConnection,
+        user_id_param: i32,
+    ) -> Result<HashMap<i32, Self>, Error> {
+        use crate::schema::carddav_resources::dsl::*;
+
+        Ok(carddav_resources
+            .filter(user_id.eq(user_id_param))
+            .load::<CardResource>(connection)?
+            .into_iter()
+            .map(|resource| (resource.contact_id, resource))
+            .collect())
+    }
+
+    /// Find a resource of a user by name
+    pub fn find_by_name(
+        connection: &mut SqliteConnection,
+        user_id_param: i32,
+        name_param: &str,
+    ) -> Result<Self, Error> {
+        use crate::schema::carddav_resources::dsl::*;
+
+        carddav_resources
+            .filter(user_id.eq(user_id_param))
+            .filter(name.eq(name_param))
+            .first::<CardResource>(connection)
+    }
+
+    /// Check if a contact was created by a client under another name
+    pub fn exists(connection: &mut SqliteConnection, contact_id_param: i32) -> Result<bool, Error> {
+        use crate::schema::carddav_resources::dsl::*;
+
+        diesel::select(diesel::dsl::exists(
+            carddav_resources.filter(contact_id.eq(contact_id_param)),
+        ))
+        .get_result::<bool>(connection)
+    }
+
+    /// Record the name and UID of a contact created by a client
+    pub fn create(&self, connection: &mut SqliteConnection) -> Result<usize, Error> {
+        diesel::insert_into(carddav_resources::table)
+            .values(self)
+            .execute(connection)
+    }
+
+    /// Delete the resources of users
+    pub fn delete_for_users(
+        connection: &mut SqliteConnection,
+        ids: &[i32],
+    ) -> Result<usize, Error> {
+        use crate::schema::carddav_resources::dsl::*;
+
+        diesel::delete(carddav_resources.filter(user_id.eq_any(ids))).execute(connection)
+    }
+}
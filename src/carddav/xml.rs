@@ -0,0 +1,515 @@
+use quick_xml::{
+    escape::{escape, resolve_predefined_entity},
+    events::{BytesStart, Event},
+    name::ResolveResult,
+    NsReader, XmlVersion,
+};
+
+/// The namespace of WebDAV
+pub const DAV: &str = "DAV:";
+/// The namespace of CardDAV
+pub const CARDDAV: &str = "urn:ietf:params:xml:ns:carddav";
+/// The namespace of the Apple extensions, for the collection tag used by the older clients
+pub const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+/// An element of a request body
+#[derive(Debug, Default)]
+pub struct Element {
+    pub namespace: String,
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    /// Check the namespace and the name of the element
+    pub fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+
+    /// Get the first child with a name
+    pub fn child(&self, namespace: &str, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.is(namespace, name))
+    }
+
+    /// Get the children with a name
+    pub fn children<'a>(
+        &'a self,
+        namespace: &'a str,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a Element> {
+        self.children
+            .iter()
+            .filter(move |child| child.is(namespace, name))
+    }
+
+    /// Get the value of an attribute
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Read an opening tag, without its namespace declarations
+fn open(namespace: String, start: &BytesStart) -> Option<Element> {
+    let attributes = start
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute.ok()?;
+            let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+            let value = attribute
+                .normalized_value(XmlVersion::default())
+                .ok()?
+                .into_owned();
+            Some((key, value))
+        })
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .filter(|(key, _)| key != "xmlns" && !key.starts_with("xmlns:"))
+        .collect();
+
+    Some(Element {
+        namespace,
+        name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+        attributes,
+        ..Element::default()
+    })
+}
+
+/// Parse an XML document into its root element, none when it is not well formed
+pub fn parse(input: &str) -> Option<Element> {
+    let mut reader = NsReader::from_str(input);
+    let mut stack: Vec<Element> = Vec::new();
+
+    loop {
+        let (namespace, event) = reader.read_resolved_event().ok()?;
+        let namespace = match namespace {
+            ResolveResult::Bound(namespace) => {
+                String::from_utf8_lossy(namespace.as_ref()).into_owned()
+            }
+            _ => String::new(),
+        };
+
+        let closed = match event {
+            Event::Start(start) => {
+                stack.push(open(namespace, &start)?);
+                None
+            }
+            Event::Empty(start) => Some(open(namespace, &start)?),
+            Event::End(_) => Some(stack.pop()?),
+            Event::Text(text) => {
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&text.decode().ok()?);
+                }
+                None
+            }
+            Event::CData(data) => {
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&data.decode().ok()?);
+                }
+                None
+            }
+            Event::GeneralRef(reference) => {
+                if let Some(current) = stack.last_mut() {
+                    match reference.resolve_char_ref().ok()? {
+                        Some(c) => current.text.push(c),
+                        None => current
+                            .text
+                            .push_str(resolve_predefined_entity(&reference.decode().ok()?)?),
+                    }
+                }
+                None
+            }
+            Event::Eof => return None,
+            _ => None,
+        };
+
+        if let Some(element) = closed {
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None => return Some(element),
+            }
+        }
+    }
+}
+
+/// The name of a property
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropName {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PropName {
+    /// Create the name of a property
+    pub fn new(namespace: &str, name: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    /// Check the namespace and the name of the property
+    pub fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+}
+
+/// Get the names of the properties listed in the `prop` child of an element
+fn props(element: &Element) -> Vec<PropName> {
+    element
+        .child(DAV, "prop")
+        .map(|prop| {
+            prop.children
+                .iter()
+                .map(|child| PropName::new(&child.namespace, &child.name))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The properties asked by a PROPFIND request
+#[derive(Debug)]
+pub enum PropFind {
+    All,
+    Names,
+    Props(Vec<PropName>),
+}
+
+/// Parse the body of a PROPFIND request, an empty body asking for all the properties
+pub fn propfind(body: &str) -> Option<PropFind> {
+    if body.trim().is_empty() {
+        return Some(PropFind::All);
+    }
+
+    let root = parse(body)?;
+    if !root.is(DAV, "propfind") {
+        return None;
+    }
+
+    if root.child(DAV, "propname").is_some() {
+        Some(PropFind::Names)
+    } else if root.child(DAV, "prop").is_some() {
+        Some(PropFind::Props(props(&root)))
+    } else {
+        Some(PropFind::All)
+    }
+}
+
+/// How a text is compared to the values of a property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchType {
+    Equals,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+/// A condition on the values of a property
+#[derive(Debug)]
+pub struct TextMatch {
+    pub text: String,
+    pub match_type: MatchType,
+    pub case_sensitive: bool,
+    pub negate: bool,
+}
+
+impl TextMatch {
+    /// Check a value of a property
+    fn matches(&self, value: &str) -> bool {
+        let (text, value) = if self.case_sensitive {
+            (self.text.clone(), value.to_string())
+        } else {
+            (self.text.to_lowercase(), value.to_lowercase())
+        };
+
+        let matched = match self.match_type {
+            MatchType::Equals => value == text,
+            MatchType::Contains => value.contains(&text),
+            MatchType::StartsWith => value.starts_with(&text),
+            MatchType::EndsWith => value.ends_with(&text),
+        };
+
+        matched != self.negate
+    }
+}
+
+/// A condition on a property of the cards, the conditions on its parameters are not supported
+/// and ignored
+#[derive(Debug)]
+pub struct PropFilter {
+    pub name: String,
+    pub all_of: bool,
+    pub is_not_defined: bool,
+    pub matches: Vec<TextMatch>,
+}
+
+impl PropFilter {
+    /// Check the properties of a card
+    fn matches(&self, properties: &[(String, String)]) -> bool {
+        let values = properties
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case(&self.name))
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>();
+
+        if self.is_not_defined {
+            return values.is_empty();
+        }
+        if self.matches.is_empty() {
+            return !values.is_empty();
+        }
+
+        let mut results = self
+            .matches
+            .iter()
+            .map(|text_match| values.iter().any(|value| text_match.matches(value)));
+        if self.all_of {
+            results.all(|matched| matched)
+        } else {
+            results.any(|matched| matched)
+        }
+    }
+}
+
+/// The filter of an addressbook-query report
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub all_of: bool,
+    pub props: Vec<PropFilter>,
+}
+
+impl Filter {
+    /// Check the properties of a card, as names and values, a filter without conditions
+    /// matching all the cards
+    pub fn matches(&self, properties: &[(String, String)]) -> bool {
+        if self.props.is_empty() {
+            return true;
+        }
+
+        let mut results = self.props.iter().map(|prop| prop.matches(properties));
+        if self.all_of {
+            results.all(|matched| matched)
+        } else {
+            results.any(|matched| matched)
+        }
+    }
+}
+
+/// Read the `test` attribute of a filter, `anyof` by default
+fn all_of(element: &Element) -> bool {
+    element.attribute("test") == Some("allof")
+}
+
+/// Read the filter of an addressbook-query report
+fn filter(element: &Element) -> Option<Filter> {
+    let props = element
+        .children(CARDDAV, "prop-filter")
+        .map(|prop| {
+            let matches = prop
+                .children(CARDDAV, "text-match")
+                .map(|text_match| {
+                    let match_type = match text_match.attribute("match-type") {
+                        None | Some("contains") => MatchType::Contains,
+                        Some("equals") => MatchType::Equals,
+                        Some("starts-with") => MatchType::StartsWith,
+                        Some("ends-with") => MatchType::EndsWith,
+                        Some(_) => return None,
+                    };
+
+                    Some(TextMatch {
+                        text: text_match.text.trim().to_string(),
+                        match_type,
+                        case_sensitive: text_match.attribute("collation") == Some("i;octet"),
+                        negate: text_match.attribute("negate-condition") == Some("yes"),
+                    })
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(PropFilter {
+                name: prop.attribute("name")?.to_string(),
+                all_of: all_of(prop),
+                is_not_defined: prop.child(CARDDAV, "is-not-defined").is_some(),
+                matches,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Filter {
+        all_of: all_of(element),
+        props,
+    })
+}
+
+/// The reports on an address book
+#[derive(Debug)]
+pub enum Report {
+    Query {
+        props: Vec<PropName>,
+        filter: Filter,
+        limit: Option<usize>,
+    },
+    Multiget {
+        props: Vec<PropName>,
+        hrefs: Vec<String>,
+    },
+    Sync {
+        props: Vec<PropName>,
+        token: String,
+    },
+}
+
+/// Parse the body of a REPORT request, none when the report is not supported
+pub fn report(body: &str) -> Option<Report> {
+    let root = parse(body)?;
+
+    if root.is(CARDDAV, "addressbook-query") {
+        let filter = match root.child(CARDDAV, "filter") {
+            Some(element) => filter(element)?,
+            None => Filter::default(),
+        };
+        let limit = root
+            .child(CARDDAV, "limit")
+            .and_then(|limit| limit.child(CARDDAV, "nresults"))
+            .and_then(|nresults| nresults.text.trim().parse().ok());
+
+        Some(Report::Query {
+            props: props(&root),
+            filter,
+            limit,
+        })
+    } else if root.is(CARDDAV, "addressbook-multiget") {
+        Some(Report::Multiget {
+            props: props(&root),
+            hrefs: root
+                .children(DAV, "href")
+                .map(|href| href.text.trim().to_string())
+                .collect(),
+        })
+    } else if root.is(DAV, "sync-collection") {
+        Some(Report::Sync {
+            props: props(&root),
+            token: root
+                .child(DAV, "sync-token")
+                .map(|token| token.text.trim().to_string())
+                .unwrap_or_default(),
+        })
+    } else {
+        None
+    }
+}
+
+/// Get the prefix of a namespace in the responses
+fn prefix(namespace: &str) -> Option<&'static str> {
+    match namespace {
+        DAV => Some("d"),
+        CARDDAV => Some("card"),
+        CALENDARSERVER => Some("cs"),
+        _ => None,
+    }
+}
+
+/// Write an element with its content, which is already escaped
+pub fn element(name: &PropName, content: &str) -> String {
+    let (tag, declaration) = match prefix(&name.namespace) {
+        Some(prefix) => (format!("{}:{}", prefix, name.name), String::new()),
+        None if name.namespace.is_empty() => (name.name.clone(), String::new()),
+        None => (
+            format!("x:{}", name.name),
+            format!(" xmlns:x=\"{}\"", escape(name.namespace.as_str())),
+        ),
+    };
+
+    if content.is_empty() {
+        format!("<{}{}/>", tag, declaration)
+    } else {
+        format!("<{}{}>{}</{}>", tag, declaration, content, tag)
+    }
+}
+
+/// Write a `href` element
+pub fn href(path: &str) -> String {
+    format!("<d:href>{}</d:href>", escape(path))
+}
+
+/// The opening of a document, with the namespaces of its elements
+fn root(name: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<{} xmlns:d=\"{}\" xmlns:card=\"{}\" xmlns:cs=\"{}\">",
+        name, DAV, CARDDAV, CALENDARSERVER
+    )
+}
+
+/// Write the body of an error response with the precondition which failed
+pub fn error(precondition: &PropName) -> String {
+    format!("{}{}</d:error>", root("d:error"), element(precondition, ""))
+}
+
+/// A multistatus response, listing resources with their properties or their status
+pub struct Multistatus {
+    body: String,
+}
+
+impl Default for Multistatus {
+    fn default() -> Self {
+        Self {
+            body: root("d:multistatus"),
+        }
+    }
+}
+
+impl Multistatus {
+    /// Add a resource with the values of its properties, which are already escaped, and the
+    /// properties it does not have
+    pub fn resource(&mut self, path: &str, found: &[(PropName, String)], missing: &[PropName]) {
+        self.body.push_str("<d:response>");
+        self.body.push_str(&href(path));
+        for (props, status) in [
+            (
+                found
+                    .iter()
+                    .map(|(name, value)| element(name, value))
+                    .collect::<String>(),
+                "200 OK",
+            ),
+            (
+                missing
+                    .iter()
+                    .map(|name| element(name, ""))
+                    .collect::<String>(),
+                "404 Not Found",
+            ),
+        ] {
+            if !props.is_empty() {
+                self.body.push_str(&format!(
+                    "<d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 {}</d:status></d:propstat>",
+                    props, status
+                ));
+            }
+        }
+        self.body.push_str("</d:response>");
+    }
+
+    /// Add a resource with a status, such as a deleted or unknown one
+    pub fn status(&mut self, path: &str, status: &str) {
+        self.body.push_str(&format!(
+            "<d:response>{}<d:status>HTTP/1.1 {}</d:status></d:response>",
+            href(path),
+            status
+        ));
+    }
+
+    /// Write the response, with the new token of a synchronization
+    pub fn finish(mut self, sync_token: Option<&str>) -> String {
+        if let Some(token) = sync_token {
+            self.body
+                .push_str(&format!("<d:sync-token>{}</d:sync-token>", escape(token)));
+        }
+        self.body.push_str("</d:multistatus>");
+
+        self.body
+    }
+}
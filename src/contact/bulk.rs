@@ -0,0 +1,264 @@
+use super::models::{
+    contact::{Contact, Owner},
+    details::{ContactDetails, ContactPayload},
+};
+use crate::{field::models::field::CustomField, tag::models::tag::Tag};
+use diesel::{
+    result::{DatabaseErrorKind, Error},
+    Connection, SqliteConnection,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Largest number of operations in one request
+pub const MAX_BULK_OPERATIONS: usize = 1000;
+
+/// How the operations of a request are applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkMode {
+    /// All the operations are applied, or none when one fails
+    #[default]
+    Atomic,
+    /// Each operation is applied on its own, the failures are reported
+    BestEffort,
+}
+
+/// An operation on a contact of the owner
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Operation {
+    Create { contact: ContactPayload },
+    Update { id: i32, contact: ContactPayload },
+    Delete { id: i32 },
+    Tag { id: i32, tag_id: i32 },
+    Untag { id: i32, tag_id: i32 },
+}
+
+#[derive(Deserialize)]
+pub struct BulkPayload {
+    #[serde(default)]
+    pub mode: BulkMode,
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkStatus {
+    Done,
+    Failed,
+}
+
+/// The result of one operation of the request
+#[derive(Debug, Serialize)]
+pub struct BulkEntry {
+    pub index: usize,
+    pub action: &'static str,
+    pub status: BulkStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct BulkReport {
+    pub committed: bool,
+    pub done: usize,
+    pub failed: usize,
+    pub entries: Vec<BulkEntry>,
+}
+
+impl BulkReport {
+    /// Record the result of an operation
+    fn push(&mut self, entry: BulkEntry) {
+        match entry.status {
+            BulkStatus::Done => self.done += 1,
+            BulkStatus::Failed => self.failed += 1,
+        }
+        self.entries.push(entry);
+    }
+}
+
+impl Operation {
+    /// Get the name of the action
+    fn action(&self) -> &'static str {
+        match self {
+            Self::Create { .. } => "create",
+            Self::Update { .. } => "update",
+            Self::Delete { .. } => "delete",
+            Self::Tag { .. } => "tag",
+            Self::Untag { .. } => "untag",
+        }
+    }
+}
+
+/// Describe why an operation failed, the details of an unexpected error are only logged
+fn reason(err: Error) -> String {
+    match err {
+        Error::NotFound => "contact or tag not found".to_string(),
+        Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => "conflict".to_string(),
+        err => {
+            tracing::error!("bulk operation failed: {}", err);
+            "internal server error".to_string()
+        }
+    }
+}
+
+/// Normalize and check a contact, with its custom field values
+fn prepare(
+    connection: &mut SqliteConnection,
+    owner: Owner,
+    contact: &mut ContactPayload,
+) -> Result<(), String> {
+    contact.normalize();
+    contact.validate().map_err(|errors| errors.to_string())?;
+
+    if let Some(fields) = &contact.fields {
+        let invalid = CustomField::invalid_values(connection, owner, fields).map_err(reason)?;
+        if !invalid.is_empty() {
+            return Err(format!("invalid custom fields: {}", invalid.join(", ")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply an operation on the contacts of an owner and return the contact it changed
+fn apply(
+    connection: &mut SqliteConnection,
+    owner: Owner,
+    operation: Operation,
+    changed_by: i32,
+) -> Result<i32, String> {
+    match operation {
+        Operation::Create { mut contact } => {
+            if !contact.has_name() {
+                return Err("the contact has no name".to_string());
+            }
+            prepare(connection, owner, &mut contact)?;
+            let details = contact
+                .create(connection, owner, changed_by)
+                .map_err(reason)?;
+
+            Ok(details.contact.id)
+        }
+        Operation::Update { id, mut contact } => {
+            Contact::find_owned(connection, owner, id).map_err(reason)?;
+            if !contact.has_changes() {
+                return Err("nothing to update".to_string());
+            }
+            prepare(connection, owner, &mut contact)?;
+            contact.update(connection, id, changed_by).map_err(reason)?;
+
+            Ok(id)
+        }
+        Operation::Delete { id } => {
+            Contact::find_owned(connection, owner, id)
+                .and_then(|contact| ContactDetails::load_one(connection, contact))
+                .and_then(|details| details.delete(connection, changed_by))
+                .map_err(reason)?;
+
+            Ok(id)
+        }
+        Operation::Tag { id, tag_id } => {
+            Contact::find_owned(connection, owner, id).map_err(reason)?;
+            Tag::find_owned(connection, owner, tag_id)
+                .and_then(|tag| tag.tag(connection, &[id]))
+                .map_err(reason)?;
+
+            Ok(id)
+        }
+        Operation::Untag { id, tag_id } => {
+            Contact::find_owned(connection, owner, id).map_err(reason)?;
+            Tag::find_owned(connection, owner, tag_id)
+                .and_then(|tag| tag.untag(connection, &[id]))
+                .map_err(reason)?;
+
+            Ok(id)
+        }
+    }
+}
+
+/// Apply each operation in its own transaction, so that a failed one leaves no change
+fn apply_all(
+    connection: &mut SqliteConnection,
+    owner: Owner,
+    operations: Vec<Operation>,
+    changed_by: i32,
+) -> BulkReport {
+    let mut report = BulkReport::default();
+
+    for (index, operation) in operations.into_iter().enumerate() {
+        let action = operation.action();
+        let mut failure = None;
+        let outcome = connection.transaction(|connection| {
+            apply(connection, owner, operation, changed_by).map_err(|err| {
+                failure = Some(err);
+                Error::RollbackTransaction
+            })
+        });
+
+        report.push(match outcome {
+            Ok(contact_id) => BulkEntry {
+                index,
+                action,
+                status: BulkStatus::Done,
+                contact_id: Some(contact_id),
+                reason: None,
+            },
+            Err(err) => BulkEntry {
+                index,
+                action,
+                status: BulkStatus::Failed,
+                contact_id: None,
+                reason: Some(failure.unwrap_or_else(|| reason(err))),
+            },
+        });
+    }
+
+    report
+}
+
+/// Apply operations on the contacts of an owner, in atomic mode nothing is changed when an
+/// operation fails
+pub fn run(
+    connection: &mut SqliteConnection,
+    owner: Owner,
+    payload: BulkPayload,
+    changed_by: i32,
+) -> Result<BulkReport, Error> {
+    if payload.mode == BulkMode::BestEffort {
+        let mut report = apply_all(connection, owner, payload.operations, changed_by);
+        report.committed = report.done > 0;
+        return Ok(report);
+    }
+
+    let mut result = None;
+    let outcome = connection.transaction(|connection| {
+        let report = apply_all(connection, owner, payload.operations, changed_by);
+        let rollback = report.failed > 0;
+        result = Some(report);
+
+        if rollback {
+            Err(Error::RollbackTransaction)
+        } else {
+            Ok(())
+        }
+    });
+
+    match (outcome, result) {
+        (Ok(()), Some(mut report)) => {
+            report.committed = true;
+            Ok(report)
+        }
+        (Err(Error::RollbackTransaction), Some(mut report)) => {
+            for entry in &mut report.entries {
+                entry.contact_id = None;
+            }
+            Ok(report)
+        }
+        (Err(err), _) => Err(err),
+        (Ok(()), None) => unreachable!("the report is set when the transaction succeeds"),
+    }
+}
@@ -1,85 +1,1350 @@
-use super::models::contact::{Contact, NewUpdateContact};
+use super::{
+    bulk::{self, BulkPayload, BulkReport, MAX_BULK_OPERATIONS},
+    csv::{self, ColumnMapping, Preset},
+    duplicates::{self, DEFAULT_THRESHOLD},
+    import::{import, import_all, ImportReport, MAX_IMPORT_SIZE},
+    models::{
+        contact::{Contact, Owner},
+        details::{ContactDetails, ContactPayload},
+        interaction::{Interaction, InteractionPayload, InteractionUpdate},
+        merge::{ContactMerge, MergePayload},
+        search::search,
+        share::{ContactShare, Permission, SharePayload, SharedContact},
+        version::ContactVersion,
+    },
+    phone::Format,
+    photo,
+    query::{ContactQuery, Fields},
+    vcard::{self, Version},
+};
 use crate::{
     auth::models::claims::Claims,
+    field::models::field::CustomField,
+    organization::controllers::role_of,
     route,
-    utils::{db::establish_connection, error::ApiError},
+    user::models::user::User,
+    utils::{
+        db::establish_connection,
+        error::ApiError,
+        etag::{check_if_match, etag, not_modified},
+        image::{self, image_key, thumbnail_key, MAX_UPLOAD_SIZE},
+        storage::STORAGE,
+        upload::read_fields,
+    },
+};
+use axum::{
+    extract::{Multipart, Path, Query},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json, Router,
 };
-use axum::{extract::Path, Json, Router};
+use diesel::{Connection, SqliteConnection};
+use serde::Deserialize;
 use serde_json::{json, Value};
+use validator::Validate;
+
+#[derive(Debug, Deserialize)]
+pub struct VcardQuery {
+    version: Option<String>,
+}
+
+impl VcardQuery {
+    /// Get the requested vCard version, 3.0 by default
+    fn version(&self) -> Result<Version, ApiError> {
+        match &self.version {
+            Some(version) => Version::parse(version).ok_or(ApiError::NotValid),
+            None => Ok(Version::V3),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PhotoQuery {
+    thumbnail: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PhoneQuery {
+    phone_format: Option<String>,
+}
+
+/// Parse the requested format of the phone numbers, they are rendered as stored by default
+fn phone_format(format: &Option<String>) -> Result<Option<Format>, ApiError> {
+    format
+        .as_deref()
+        .map(|format| Format::parse(format).ok_or(ApiError::NotValid))
+        .transpose()
+}
+
+/// Check that the caller can manage the contacts of an owner: the user itself, a member of the
+/// organization or an admin
+pub fn authorize_owner(
+    connection: &mut SqliteConnection,
+    claims: &Claims,
+    owner: Owner,
+) -> Result<(), ApiError> {
+    match owner {
+        Owner::User(user_id) if claims.is_admin() || claims.is_user(user_id) => Ok(()),
+        Owner::User(_) => Err(ApiError::Forbidden),
+        Owner::Organization(organization_id) => {
+            role_of(connection, claims, organization_id).map(|_| ())
+        }
+    }
+}
 
-/// Get all contacts
-pub async fn get_all(claims: Claims, Path(id): Path<i32>) -> Result<Json<Value>, ApiError> {
-    if !claims.is_admin() && !claims.is_user(id) {
-        return Err(ApiError::InvalidToken);
+/// Check that the caller can access a stored contact, either through its owner or through a
+/// share granting at least the permission
+fn authorize(
+    connection: &mut SqliteConnection,
+    claims: &Claims,
+    contact: &Contact,
+    permission: Permission,
+) -> Result<(), ApiError> {
+    let denied = match authorize_owner(connection, claims, contact.owner()) {
+        Ok(()) => return Ok(()),
+        Err(err) => err,
+    };
+
+    match ContactShare::permission_of(connection, contact.id, claims.id()) {
+        Ok(Some(granted)) if granted >= permission => Ok(()),
+        Ok(Some(_)) => Err(ApiError::Forbidden),
+        Ok(None) => Err(denied),
+        Err(err) => Err(ApiError::from(err)),
     }
+}
+
+/// Get the contacts of an owner, only those with a tag when `?tag=` is given and those with a
+/// custom field value when `?field=name:value` is given
+///
+/// `?filter=` takes conditions separated by `,` such as `lastname:starts_with:Do` or
+/// `last_contacted_at:gte:2023-01-01`, `?sort=` takes columns such as `lastname,firstname:desc`
+/// and `?fields=` the keys to return. The contacts are paged when `?limit=` or `?cursor=` is
+/// given, the response then carries the cursor of the next page.
+///
+/// The filters and the sort keys only apply to the columns of the contact: `email` and `phone`
+/// are the primary ones, the other emails, phones, addresses and dates cannot be filtered on.
+fn list(claims: &Claims, owner: Owner, query: &ListQuery) -> Result<Json<Value>, ApiError> {
+    let format = phone_format(&query.phone_format)?;
+    let field = match query.field.as_deref() {
+        Some(field) => Some(field.split_once(':').ok_or(ApiError::NotValid)?),
+        None => None,
+    };
+    let fields = query
+        .fields
+        .as_deref()
+        .map(|fields| Fields::parse(fields).ok_or(ApiError::NotValid))
+        .transpose()?;
+    let mut contact_query = ContactQuery::parse(
+        query.filter.as_deref(),
+        query.sort.as_deref(),
+        query.cursor.as_deref(),
+        query.limit,
+    )
+    .ok_or(ApiError::NotValid)?;
+    contact_query.tag = query.tag;
 
     let connection = &mut establish_connection();
 
-    let contacts = Contact::all(connection, id).map_err(|_| ApiError::InternalServerError)?;
+    authorize_owner(connection, claims, owner)?;
+
+    if let Some((name, value)) = field {
+        let ids = CustomField::find_by_name(connection, owner, name)
+            .map_err(|_| ApiError::NotValid)?
+            .contact_ids_with(connection, value)
+            .map_err(ApiError::from)?;
+        contact_query.ids = Some(ids);
+    }
+    let page = contact_query
+        .run(connection, owner)
+        .map_err(ApiError::from)?;
+    let mut contacts = ContactDetails::load(connection, page.contacts).map_err(ApiError::from)?;
+    if let Some(format) = format {
+        contacts
+            .iter_mut()
+            .for_each(|contact| contact.format_phones(format));
+    }
+    let contacts = match fields {
+        Some(fields) => contacts
+            .iter()
+            .map(|contact| fields.select(contact))
+            .collect::<Vec<_>>(),
+        None => contacts
+            .iter()
+            .map(|contact| json!(contact))
+            .collect::<Vec<_>>(),
+    };
 
-    Ok(Json(json!({ "contacts": contacts })))
+    Ok(Json(
+        json!({ "contacts": contacts, "next_cursor": page.next_cursor }),
+    ))
 }
 
-/// Get a contact by id
-pub async fn find(claims: Claims, Path(id): Path<i32>) -> Result<Json<Contact>, ApiError> {
-    if !claims.is_admin() && !claims.is_user(id) {
-        return Err(ApiError::InvalidToken);
+/// Get a contact of an owner, with its version as ETag
+fn show(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    query: &PhoneQuery,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let format = phone_format(&query.phone_format)?;
+
+    let connection = &mut establish_connection();
+
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Read)?;
+
+    let tag = etag(contact.version);
+    if let Some(response) = not_modified(headers, &tag) {
+        return Ok(response);
+    }
+
+    let mut contact = ContactDetails::load_one(connection, contact).map_err(ApiError::from)?;
+    if let Some(format) = format {
+        contact.format_phones(format);
     }
 
+    Ok(([(header::ETAG, tag)], Json(contact)).into_response())
+}
+
+/// Check that the custom field values of a contact are defined for its owner and valid
+fn check_fields(
+    connection: &mut SqliteConnection,
+    owner: Owner,
+    contact: &ContactPayload,
+) -> Result<(), ApiError> {
+    let Some(fields) = &contact.fields else {
+        return Ok(());
+    };
+
+    let invalid = CustomField::invalid_values(connection, owner, fields).map_err(ApiError::from)?;
+    if !invalid.is_empty() {
+        return Err(ApiError::NotValid);
+    }
+
+    Ok(())
+}
+
+/// Create a contact for an owner
+fn store(
+    claims: &Claims,
+    owner: Owner,
+    mut new_contact: ContactPayload,
+) -> Result<Json<ContactDetails>, ApiError> {
+    if !new_contact.has_name() {
+        return Err(ApiError::NotValid);
+    }
+    new_contact.normalize();
+    new_contact.validate().map_err(|_| ApiError::NotValid)?;
+
     let connection = &mut establish_connection();
 
-    let contact = Contact::find(connection, id).map_err(|_| ApiError::InternalServerError)?;
+    authorize_owner(connection, claims, owner)?;
+    check_fields(connection, owner, &new_contact)?;
+
+    let contact = new_contact
+        .create(connection, owner, claims.id())
+        .map_err(ApiError::from)?;
 
     Ok(Json(contact))
 }
 
-/// Create a new contact
-pub async fn create(
-    claims: Claims,
-    Path(id): Path<i32>,
-    Json(new_contact): Json<NewUpdateContact>,
-) -> Result<Json<Contact>, ApiError> {
-    if !claims.is_admin() && !claims.is_user(id) {
-        return Err(ApiError::InvalidToken);
+/// Update a contact of an owner, only if it is still at the version of `If-Match` when given
+fn edit(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    mut contact: ContactPayload,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    contact.normalize();
+    contact.validate().map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    // The version is checked in the transaction of the change so that no other one comes between
+    let contact = connection.transaction(|connection| {
+        let current = Contact::find_owned(connection, owner, id)?;
+        authorize(connection, claims, &current, Permission::Write)?;
+        check_if_match(headers, &etag(current.version))?;
+        check_fields(connection, owner, &contact)?;
+
+        contact
+            .update(connection, id, claims.id())
+            .map_err(ApiError::from)
+    })?;
+
+    Ok((
+        [(header::ETAG, etag(contact.contact.version))],
+        Json(contact),
+    )
+        .into_response())
+}
+
+/// Delete a contact of an owner, only if it is still at the version of `If-Match` when given
+fn remove(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    headers: &HeaderMap,
+) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    // The photo is kept with the history, the contact can be restored with it
+    connection.transaction(|connection| {
+        let contact = Contact::find_owned(connection, owner, id)?;
+        authorize_owner(connection, claims, contact.owner())?;
+        check_if_match(headers, &etag(contact.version))?;
+
+        ContactDetails::load_one(connection, contact)
+            .and_then(|contact| contact.delete(connection, claims.id()))
+            .map_err(ApiError::from)
+    })?;
+
+    Ok(Json(json!({ "message": "Contact deleted" })))
+}
+
+/// Create, update, delete or tag many contacts of an owner in one request
+fn apply_bulk(
+    claims: &Claims,
+    owner: Owner,
+    payload: BulkPayload,
+) -> Result<Json<BulkReport>, ApiError> {
+    if payload.operations.is_empty() {
+        return Err(ApiError::NotValid);
+    }
+    if payload.operations.len() > MAX_BULK_OPERATIONS {
+        return Err(ApiError::PayloadTooLarge);
     }
 
     let connection = &mut establish_connection();
 
+    authorize_owner(connection, claims, owner)?;
+
+    let report = bulk::run(connection, owner, payload, claims.id()).map_err(ApiError::from)?;
+
+    Ok(Json(report))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    tag: Option<i32>,
+    field: Option<String>,
+    filter: Option<String>,
+    sort: Option<String>,
+    fields: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+    phone_format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicateQuery {
+    threshold: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    q: String,
+    limit: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CsvQuery {
+    dry_run: Option<bool>,
+}
+
+/// Get the pairs of contacts of an owner which are suspected duplicates
+fn list_duplicates(
+    claims: &Claims,
+    owner: Owner,
+    query: &DuplicateQuery,
+) -> Result<Json<Value>, ApiError> {
+    let threshold = query.threshold.unwrap_or(DEFAULT_THRESHOLD);
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(ApiError::NotValid);
+    }
+
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let contacts = Contact::all_for_owner(connection, owner).map_err(ApiError::from)?;
+    let contacts = ContactDetails::load(connection, contacts).map_err(ApiError::from)?;
+
+    Ok(Json(
+        json!({ "duplicates": duplicates::find(&contacts, threshold) }),
+    ))
+}
+
+/// Merge a duplicate into a contact of an owner
+fn merge(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    payload: MergePayload,
+) -> Result<Json<ContactDetails>, ApiError> {
+    if payload.duplicate_id == id {
+        return Err(ApiError::NotValid);
+    }
+
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let winner = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    let loser =
+        Contact::find_owned(connection, owner, payload.duplicate_id).map_err(ApiError::from)?;
+
+    let winner = ContactDetails::load_one(connection, winner).map_err(ApiError::from)?;
+    let loser = ContactDetails::load_one(connection, loser).map_err(ApiError::from)?;
+
     let contact =
-        Contact::create(connection, new_contact).map_err(|_| ApiError::InternalServerError)?;
+        ContactMerge::merge(connection, winner, loser, claims.id()).map_err(ApiError::from)?;
 
     Ok(Json(contact))
 }
 
-/// Update a contact
-pub async fn update(
-    claims: Claims,
-    Path(id): Path<i32>,
-    Json(contact): Json<NewUpdateContact>,
-) -> Result<Json<Contact>, ApiError> {
-    if !claims.is_admin() && !claims.is_user(id) {
-        return Err(ApiError::InvalidToken);
+/// Get the merges into a contact of an owner
+fn list_merges(claims: &Claims, owner: Owner, id: i32) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Read)?;
+
+    let merges = ContactMerge::all_for_contact(connection, id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "merges": merges })))
+}
+
+/// Get the users a contact of an owner is shared with
+fn list_shares(claims: &Claims, owner: Owner, id: i32) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    let shares = ContactShare::all_for_contact(connection, id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "shares": shares })))
+}
+
+/// Share a contact of an owner with a user
+fn share(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    payload: SharePayload,
+) -> Result<Json<ContactShare>, ApiError> {
+    let permission = Permission::parse(&payload.permission).ok_or(ApiError::NotValid)?;
+    if claims.is_user(payload.user_id) || owner == Owner::User(payload.user_id) {
+        return Err(ApiError::NotValid);
     }
 
     let connection = &mut establish_connection();
 
-    let contact =
-        Contact::update(connection, id, contact).map_err(|_| ApiError::InternalServerError)?;
+    authorize_owner(connection, claims, owner)?;
+
+    Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    User::find(connection, payload.user_id).map_err(ApiError::from)?;
+
+    let share = ContactShare::share(connection, id, payload.user_id, permission, claims.id())
+        .map_err(ApiError::from)?;
+
+    Ok(Json(share))
+}
+
+/// Stop sharing a contact of an owner with a user, which the user can also do
+fn unshare(claims: &Claims, owner: Owner, id: i32, user_id: i32) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    if !claims.is_user(user_id) {
+        authorize_owner(connection, claims, owner)?;
+    }
+
+    Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    ContactShare::unshare(connection, id, user_id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "message": "Contact unshared" })))
+}
+
+/// Get the contacts shared with the user
+pub async fn shared_with_me(claims: Claims) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    let shared = SharedContact::all(connection, claims.id()).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "contacts": shared })))
+}
+
+/// Get the history of a contact of an owner, which may have been deleted
+fn list_versions(claims: &Claims, owner: Owner, id: i32) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let versions =
+        ContactVersion::all_for_contact(connection, owner, id).map_err(ApiError::from)?;
+    if versions.is_empty() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(json!({ "versions": versions })))
+}
+
+/// Bring a contact of an owner back to a previous version, restoring it if it has been deleted
+fn restore_version(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    version: i32,
+) -> Result<Json<ContactDetails>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let version =
+        ContactVersion::find_owned(connection, owner, id, version).map_err(ApiError::from)?;
+    let contact = version
+        .restore(connection, claims.id())
+        .map_err(ApiError::from)?;
 
     Ok(Json(contact))
 }
 
-/// Delete a contact
-pub async fn delete(claims: Claims, Path(id): Path<i32>) -> Result<Json<Value>, ApiError> {
-    if !claims.is_admin() && !claims.is_user(id) {
-        return Err(ApiError::InvalidToken);
+/// Get the timeline of a contact of an owner
+fn list_interactions(claims: &Claims, owner: Owner, id: i32) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Read)?;
+
+    let interactions = Interaction::all_for_contact(connection, id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "interactions": interactions })))
+}
+
+/// Log a call, a meeting or a note on a contact of an owner
+fn store_interaction(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    payload: InteractionPayload,
+) -> Result<Json<Interaction>, ApiError> {
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Write)?;
+
+    let interaction =
+        Interaction::create(connection, id, claims.id(), payload).map_err(ApiError::from)?;
+
+    Ok(Json(interaction))
+}
+
+/// Update an entry of the timeline of a contact of an owner
+fn edit_interaction(
+    claims: &Claims,
+    owner: Owner,
+    (id, interaction_id): (i32, i32),
+    payload: InteractionUpdate,
+) -> Result<Json<Interaction>, ApiError> {
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Write)?;
+
+    let interaction = Interaction::find_for_contact(connection, id, interaction_id)
+        .and_then(|interaction| interaction.update(connection, payload))
+        .map_err(ApiError::from)?;
+
+    Ok(Json(interaction))
+}
+
+/// Delete an entry of the timeline of a contact of an owner
+fn remove_interaction(
+    claims: &Claims,
+    owner: Owner,
+    (id, interaction_id): (i32, i32),
+) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Write)?;
+
+    Interaction::find_for_contact(connection, id, interaction_id)
+        .and_then(|interaction| interaction.delete(connection))
+        .map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "message": "Interaction deleted" })))
+}
+
+/// Get the deleted contacts of an owner, with the last version of each
+fn list_deleted(claims: &Claims, owner: Owner) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let deleted = ContactVersion::all_deleted(connection, owner).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "contacts": deleted })))
+}
+
+/// Export all the contacts of an owner as a CSV file
+fn export_csv(claims: &Claims, owner: Owner) -> Result<impl IntoResponse, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let contacts = Contact::all_for_owner(connection, owner)
+        .and_then(|contacts| ContactDetails::load(connection, contacts))
+        .map_err(ApiError::from)?;
+    let fields = CustomField::all(connection, owner).map_err(ApiError::from)?;
+    let body = csv::to_csv(&contacts, &fields).map_err(|_| ApiError::InternalServerError)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"contacts.csv\"",
+            ),
+        ],
+        body,
+    ))
+}
+
+/// Import the rows of a CSV file sent as the `file` field of a multipart form, with an optional
+/// `preset` layout (`fer`, `google` or `outlook`) and a JSON `mapping` from fields to headers
+async fn import_csv(
+    claims: &Claims,
+    owner: Owner,
+    query: &CsvQuery,
+    mut multipart: Multipart,
+) -> Result<Json<ImportReport>, ApiError> {
+    let mut fields = read_fields(&mut multipart, MAX_IMPORT_SIZE).await?;
+    let file = fields.remove("file").ok_or(ApiError::NotValid)?;
+
+    let preset = match fields.get("preset") {
+        Some(preset) => {
+            Preset::parse(&String::from_utf8_lossy(preset)).ok_or(ApiError::NotValid)?
+        }
+        None => Preset::Fer,
+    };
+    let mapping = match fields.get("mapping") {
+        Some(mapping) => {
+            serde_json::from_slice::<ColumnMapping>(mapping).map_err(|_| ApiError::NotValid)?
+        }
+        None => ColumnMapping::default(),
+    };
+
+    let rows = csv::parse(&file, preset, &mapping).map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let report = import_all(
+        connection,
+        owner,
+        rows,
+        query.dry_run.unwrap_or(false),
+        claims.id(),
+    )
+    .map_err(ApiError::from)?;
+
+    Ok(Json(report))
+}
+
+/// Send a vCard file
+fn vcard_response(body: String, filename: String) -> impl IntoResponse {
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/vcard; charset=utf-8".to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+}
+
+/// Export all the contacts of an owner as a vCard file
+fn export_vcards(
+    claims: &Claims,
+    owner: Owner,
+    query: &VcardQuery,
+) -> Result<impl IntoResponse, ApiError> {
+    let version = query.version()?;
+
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let contacts = Contact::all_for_owner(connection, owner).map_err(ApiError::from)?;
+    let contacts = ContactDetails::load(connection, contacts).map_err(ApiError::from)?;
+
+    Ok(vcard_response(
+        vcard::to_vcards(&contacts, version),
+        "contacts.vcf".to_string(),
+    ))
+}
+
+/// Export a contact of an owner as a vCard file
+fn export_vcard(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    query: &VcardQuery,
+) -> Result<impl IntoResponse, ApiError> {
+    let version = query.version()?;
+
+    let connection = &mut establish_connection();
+
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Read)?;
+
+    let contact = ContactDetails::load_one(connection, contact).map_err(ApiError::from)?;
+
+    Ok(vcard_response(
+        vcard::to_vcard(&contact, version),
+        format!("contact-{}.vcf", contact.contact.id),
+    ))
+}
+
+/// Import the cards of a vCard file, sent as the `file` field of a multipart form
+async fn import_vcards(
+    claims: &Claims,
+    owner: Owner,
+    mut multipart: Multipart,
+) -> Result<Json<ImportReport>, ApiError> {
+    let mut fields = read_fields(&mut multipart, MAX_IMPORT_SIZE).await?;
+    let file = fields.remove("file").ok_or(ApiError::NotValid)?;
+    let input = String::from_utf8(file).map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let report =
+        import(connection, owner, vcard::parse(&input), claims.id()).map_err(ApiError::from)?;
+
+    Ok(Json(report))
+}
+
+/// Upload the photo of a contact of an owner, sent as the `photo` field of a multipart form
+async fn upload_photo(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    mut multipart: Multipart,
+) -> Result<Json<ContactDetails>, ApiError> {
+    let mut fields = read_fields(&mut multipart, MAX_UPLOAD_SIZE).await?;
+    let bytes = fields.remove("photo").ok_or(ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Write)?;
+
+    let processed = tokio::task::spawn_blocking(move || image::process(&bytes))
+        .await
+        .map_err(|_| ApiError::InternalServerError)??;
+
+    let details = photo::store(connection, contact, &processed, claims.id())?;
+
+    Ok(Json(details))
+}
+
+/// Get the photo of a contact of an owner, or its thumbnail with `?thumbnail=true`
+fn get_photo(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    query: &PhotoQuery,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let connection = &mut establish_connection();
+
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Read)?;
+    let photo = contact.photo.ok_or(ApiError::NotFound)?;
+
+    let key = if query.thumbnail.unwrap_or(false) {
+        thumbnail_key(&photo)
+    } else {
+        image_key(&photo)
+    };
+    // A new upload gets a new key, so the key identifies the content
+    let tag = format!("\"{}\"", key);
+    if let Some(response) = not_modified(headers, &tag) {
+        return Ok(response);
     }
+    let bytes = STORAGE.get(&key).map_err(|_| ApiError::NotFound)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/png".to_string()),
+            (header::CACHE_CONTROL, "private, max-age=86400".to_string()),
+            (header::ETAG, tag),
+        ],
+        bytes,
+    )
+        .into_response())
+}
 
+/// Delete the photo of a contact of an owner
+fn remove_photo(claims: &Claims, owner: Owner, id: i32) -> Result<Json<ContactDetails>, ApiError> {
     let connection = &mut establish_connection();
 
-    Contact::delete(connection, id).map_err(|_| ApiError::InternalServerError)?;
+    let contact = Contact::find_owned(connection, owner, id).map_err(ApiError::from)?;
+    authorize(connection, claims, &contact, Permission::Write)?;
+    if contact.photo.is_none() {
+        return Err(ApiError::NotFound);
+    }
 
-    Ok(Json(json!({ "message": "Contact deleted" })))
+    // The files stay for the previous versions, they are deleted with the history
+    let details =
+        ContactDetails::set_photo(connection, id, None, claims.id()).map_err(ApiError::from)?;
+
+    Ok(Json(details))
+}
+
+/// Search the contacts of the user, of its organizations and those shared with it
+pub async fn search_contacts(
+    claims: Claims,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Value>, ApiError> {
+    if query.q.trim().is_empty() {
+        return Err(ApiError::NotValid);
+    }
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    let connection = &mut establish_connection();
+
+    let results = search(connection, claims.id(), &query.q, limit).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "results": results })))
+}
+
+/// Get all contacts of a user
+pub async fn get_all(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Value>, ApiError> {
+    list(&claims, Owner::User(user_id), &query)
+}
+
+/// Get a contact of a user
+pub async fn find(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Query(query): Query<PhoneQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    show(&claims, Owner::User(user_id), id, &query, &headers)
+}
+
+/// Create a new contact for a user
+pub async fn create(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Json(new_contact): Json<ContactPayload>,
+) -> Result<Json<ContactDetails>, ApiError> {
+    store(&claims, Owner::User(user_id), new_contact)
+}
+
+/// Update a contact of a user
+pub async fn update(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    headers: HeaderMap,
+    Json(contact): Json<ContactPayload>,
+) -> Result<Response, ApiError> {
+    edit(&claims, Owner::User(user_id), id, contact, &headers)
+}
+
+/// Delete a contact of a user
+pub async fn delete(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, ApiError> {
+    remove(&claims, Owner::User(user_id), id, &headers)
+}
+
+/// Get all contacts of an organization
+pub async fn get_all_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Value>, ApiError> {
+    list(&claims, Owner::Organization(organization_id), &query)
+}
+
+/// Get a contact of an organization
+pub async fn find_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Query(query): Query<PhoneQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    show(
+        &claims,
+        Owner::Organization(organization_id),
+        id,
+        &query,
+        &headers,
+    )
+}
+
+/// Create a new contact in an organization
+pub async fn create_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+    Json(new_contact): Json<ContactPayload>,
+) -> Result<Json<ContactDetails>, ApiError> {
+    store(&claims, Owner::Organization(organization_id), new_contact)
+}
+
+/// Update a contact of an organization
+pub async fn update_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    headers: HeaderMap,
+    Json(contact): Json<ContactPayload>,
+) -> Result<Response, ApiError> {
+    edit(
+        &claims,
+        Owner::Organization(organization_id),
+        id,
+        contact,
+        &headers,
+    )
+}
+
+/// Delete a contact of an organization
+pub async fn delete_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, ApiError> {
+    remove(&claims, Owner::Organization(organization_id), id, &headers)
+}
+
+/// Get the suspected duplicates in the contacts of a user
+pub async fn duplicates_for_user(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Query(query): Query<DuplicateQuery>,
+) -> Result<Json<Value>, ApiError> {
+    list_duplicates(&claims, Owner::User(user_id), &query)
+}
+
+/// Merge a duplicate into a contact of a user
+pub async fn merge_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<MergePayload>,
+) -> Result<Json<ContactDetails>, ApiError> {
+    merge(&claims, Owner::User(user_id), id, payload)
+}
+
+/// Get the merges into a contact of a user
+pub async fn merges_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    list_merges(&claims, Owner::User(user_id), id)
+}
+
+/// Get the suspected duplicates in the contacts of an organization
+pub async fn duplicates_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+    Query(query): Query<DuplicateQuery>,
+) -> Result<Json<Value>, ApiError> {
+    list_duplicates(&claims, Owner::Organization(organization_id), &query)
+}
+
+/// Merge a duplicate into a contact of an organization
+pub async fn merge_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<MergePayload>,
+) -> Result<Json<ContactDetails>, ApiError> {
+    merge(&claims, Owner::Organization(organization_id), id, payload)
+}
+
+/// Get the merges into a contact of an organization
+pub async fn merges_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    list_merges(&claims, Owner::Organization(organization_id), id)
+}
+
+/// Get the shares of a contact of a user
+pub async fn shares_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    list_shares(&claims, Owner::User(user_id), id)
+}
+
+/// Share a contact of a user
+pub async fn share_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<SharePayload>,
+) -> Result<Json<ContactShare>, ApiError> {
+    share(&claims, Owner::User(user_id), id, payload)
+}
+
+/// Stop sharing a contact of a user
+pub async fn unshare_for_user(
+    claims: Claims,
+    Path((user_id, id, shared_with)): Path<(i32, i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    unshare(&claims, Owner::User(user_id), id, shared_with)
+}
+
+/// Get the shares of a contact of an organization
+pub async fn shares_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    list_shares(&claims, Owner::Organization(organization_id), id)
+}
+
+/// Share a contact of an organization
+pub async fn share_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<SharePayload>,
+) -> Result<Json<ContactShare>, ApiError> {
+    share(&claims, Owner::Organization(organization_id), id, payload)
+}
+
+/// Stop sharing a contact of an organization
+pub async fn unshare_for_organization(
+    claims: Claims,
+    Path((organization_id, id, shared_with)): Path<(i32, i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    unshare(
+        &claims,
+        Owner::Organization(organization_id),
+        id,
+        shared_with,
+    )
+}
+
+/// Get the history of a contact of a user
+pub async fn versions_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    list_versions(&claims, Owner::User(user_id), id)
+}
+
+/// Restore a version of a contact of a user
+pub async fn restore_for_user(
+    claims: Claims,
+    Path((user_id, id, version)): Path<(i32, i32, i32)>,
+) -> Result<Json<ContactDetails>, ApiError> {
+    restore_version(&claims, Owner::User(user_id), id, version)
+}
+
+/// Get the deleted contacts of a user
+pub async fn deleted_for_user(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+) -> Result<Json<Value>, ApiError> {
+    list_deleted(&claims, Owner::User(user_id))
+}
+
+/// Get the history of a contact of an organization
+pub async fn versions_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    list_versions(&claims, Owner::Organization(organization_id), id)
+}
+
+/// Restore a version of a contact of an organization
+pub async fn restore_for_organization(
+    claims: Claims,
+    Path((organization_id, id, version)): Path<(i32, i32, i32)>,
+) -> Result<Json<ContactDetails>, ApiError> {
+    restore_version(&claims, Owner::Organization(organization_id), id, version)
+}
+
+/// Get the deleted contacts of an organization
+pub async fn deleted_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+) -> Result<Json<Value>, ApiError> {
+    list_deleted(&claims, Owner::Organization(organization_id))
+}
+
+/// Get the timeline of a contact of a user
+pub async fn interactions_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    list_interactions(&claims, Owner::User(user_id), id)
+}
+
+/// Log an interaction with a contact of a user
+pub async fn create_interaction_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<InteractionPayload>,
+) -> Result<Json<Interaction>, ApiError> {
+    store_interaction(&claims, Owner::User(user_id), id, payload)
+}
+
+/// Update an interaction with a contact of a user
+pub async fn update_interaction_for_user(
+    claims: Claims,
+    Path((user_id, id, interaction_id)): Path<(i32, i32, i32)>,
+    Json(payload): Json<InteractionUpdate>,
+) -> Result<Json<Interaction>, ApiError> {
+    edit_interaction(&claims, Owner::User(user_id), (id, interaction_id), payload)
+}
+
+/// Delete an interaction with a contact of a user
+pub async fn delete_interaction_for_user(
+    claims: Claims,
+    Path((user_id, id, interaction_id)): Path<(i32, i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    remove_interaction(&claims, Owner::User(user_id), (id, interaction_id))
+}
+
+/// Get the timeline of a contact of an organization
+pub async fn interactions_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    list_interactions(&claims, Owner::Organization(organization_id), id)
+}
+
+/// Log an interaction with a contact of an organization
+pub async fn create_interaction_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<InteractionPayload>,
+) -> Result<Json<Interaction>, ApiError> {
+    store_interaction(&claims, Owner::Organization(organization_id), id, payload)
+}
+
+/// Update an interaction with a contact of an organization
+pub async fn update_interaction_for_organization(
+    claims: Claims,
+    Path((organization_id, id, interaction_id)): Path<(i32, i32, i32)>,
+    Json(payload): Json<InteractionUpdate>,
+) -> Result<Json<Interaction>, ApiError> {
+    edit_interaction(
+        &claims,
+        Owner::Organization(organization_id),
+        (id, interaction_id),
+        payload,
+    )
+}
+
+/// Delete an interaction with a contact of an organization
+pub async fn delete_interaction_for_organization(
+    claims: Claims,
+    Path((organization_id, id, interaction_id)): Path<(i32, i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    remove_interaction(
+        &claims,
+        Owner::Organization(organization_id),
+        (id, interaction_id),
+    )
+}
+
+/// Export all contacts of a user as vCards
+pub async fn export_vcards_for_user(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Query(query): Query<VcardQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    export_vcards(&claims, Owner::User(user_id), &query)
+}
+
+/// Export a contact of a user as a vCard
+pub async fn export_vcard_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Query(query): Query<VcardQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    export_vcard(&claims, Owner::User(user_id), id, &query)
+}
+
+/// Import vCards in the contacts of a user
+pub async fn import_vcards_for_user(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    multipart: Multipart,
+) -> Result<Json<ImportReport>, ApiError> {
+    import_vcards(&claims, Owner::User(user_id), multipart).await
+}
+
+/// Export all contacts of an organization as vCards
+pub async fn export_vcards_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+    Query(query): Query<VcardQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    export_vcards(&claims, Owner::Organization(organization_id), &query)
+}
+
+/// Export a contact of an organization as a vCard
+pub async fn export_vcard_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Query(query): Query<VcardQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    export_vcard(&claims, Owner::Organization(organization_id), id, &query)
+}
+
+/// Import vCards in the contacts of an organization
+pub async fn import_vcards_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+    multipart: Multipart,
+) -> Result<Json<ImportReport>, ApiError> {
+    import_vcards(&claims, Owner::Organization(organization_id), multipart).await
+}
+
+/// Export all contacts of a user as CSV
+pub async fn export_csv_for_user(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    export_csv(&claims, Owner::User(user_id))
+}
+
+/// Import a CSV file in the contacts of a user
+pub async fn import_csv_for_user(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Query(query): Query<CsvQuery>,
+    multipart: Multipart,
+) -> Result<Json<ImportReport>, ApiError> {
+    import_csv(&claims, Owner::User(user_id), &query, multipart).await
+}
+
+/// Export all contacts of an organization as CSV
+pub async fn export_csv_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    export_csv(&claims, Owner::Organization(organization_id))
+}
+
+/// Import a CSV file in the contacts of an organization
+pub async fn import_csv_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+    Query(query): Query<CsvQuery>,
+    multipart: Multipart,
+) -> Result<Json<ImportReport>, ApiError> {
+    import_csv(
+        &claims,
+        Owner::Organization(organization_id),
+        &query,
+        multipart,
+    )
+    .await
+}
+
+/// Apply bulk operations on the contacts of a user
+pub async fn bulk_for_user(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<BulkPayload>,
+) -> Result<Json<BulkReport>, ApiError> {
+    apply_bulk(&claims, Owner::User(user_id), payload)
+}
+
+/// Apply bulk operations on the contacts of an organization
+pub async fn bulk_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+    Json(payload): Json<BulkPayload>,
+) -> Result<Json<BulkReport>, ApiError> {
+    apply_bulk(&claims, Owner::Organization(organization_id), payload)
+}
+
+/// Upload the photo of a contact of a user
+pub async fn upload_photo_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    multipart: Multipart,
+) -> Result<Json<ContactDetails>, ApiError> {
+    upload_photo(&claims, Owner::User(user_id), id, multipart).await
+}
+
+/// Get the photo of a contact of a user
+pub async fn photo_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Query(query): Query<PhotoQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    get_photo(&claims, Owner::User(user_id), id, &query, &headers)
+}
+
+/// Delete the photo of a contact of a user
+pub async fn delete_photo_for_user(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+) -> Result<Json<ContactDetails>, ApiError> {
+    remove_photo(&claims, Owner::User(user_id), id)
+}
+
+/// Upload the photo of a contact of an organization
+pub async fn upload_photo_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    multipart: Multipart,
+) -> Result<Json<ContactDetails>, ApiError> {
+    upload_photo(&claims, Owner::Organization(organization_id), id, multipart).await
+}
+
+/// Get the photo of a contact of an organization
+pub async fn photo_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Query(query): Query<PhotoQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    get_photo(
+        &claims,
+        Owner::Organization(organization_id),
+        id,
+        &query,
+        &headers,
+    )
+}
+
+/// Delete the photo of a contact of an organization
+pub async fn delete_photo_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+) -> Result<Json<ContactDetails>, ApiError> {
+    remove_photo(&claims, Owner::Organization(organization_id), id)
 }
 
 /// Create a router for the contact routes
@@ -87,23 +1352,152 @@ pub fn controller(router: &Router) -> Router {
     router
         .clone()
         .route(
-            route("/contacts/:id/all".to_string()).as_str(),
-            axum::routing::get(get_all),
+            route("/contacts/search".to_string()).as_str(),
+            axum::routing::get(search_contacts),
+        )
+        .route(
+            route("/contacts/shared".to_string()).as_str(),
+            axum::routing::get(shared_with_me),
+        )
+        .route(
+            route("/users/:id/contacts".to_string()).as_str(),
+            axum::routing::get(get_all).post(create),
+        )
+        .route(
+            route("/users/:id/contacts/bulk".to_string()).as_str(),
+            axum::routing::post(bulk_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id".to_string()).as_str(),
+            axum::routing::get(find).put(update).delete(delete),
+        )
+        .route(
+            route("/users/:id/duplicates".to_string()).as_str(),
+            axum::routing::get(duplicates_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id/merges".to_string()).as_str(),
+            axum::routing::get(merges_for_user).post(merge_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id/shares".to_string()).as_str(),
+            axum::routing::get(shares_for_user).post(share_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id/shares/:user_id".to_string()).as_str(),
+            axum::routing::delete(unshare_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id/versions".to_string()).as_str(),
+            axum::routing::get(versions_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id/versions/:version/restore".to_string()).as_str(),
+            axum::routing::post(restore_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id/interactions".to_string()).as_str(),
+            axum::routing::get(interactions_for_user).post(create_interaction_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id/interactions/:interaction_id".to_string())
+                .as_str(),
+            axum::routing::put(update_interaction_for_user).delete(delete_interaction_for_user),
+        )
+        .route(
+            route("/users/:id/deleted-contacts".to_string()).as_str(),
+            axum::routing::get(deleted_for_user),
+        )
+        .route(
+            route("/users/:id/vcard".to_string()).as_str(),
+            axum::routing::get(export_vcards_for_user).post(import_vcards_for_user),
+        )
+        .route(
+            route("/users/:id/csv".to_string()).as_str(),
+            axum::routing::get(export_csv_for_user).post(import_csv_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id/vcard".to_string()).as_str(),
+            axum::routing::get(export_vcard_for_user),
+        )
+        .route(
+            route("/users/:id/contacts/:contact_id/photo".to_string()).as_str(),
+            axum::routing::get(photo_for_user)
+                .put(upload_photo_for_user)
+                .delete(delete_photo_for_user),
+        )
+        .route(
+            route("/organizations/:id/contacts".to_string()).as_str(),
+            axum::routing::get(get_all_for_organization).post(create_for_organization),
+        )
+        .route(
+            route("/organizations/:id/contacts/bulk".to_string()).as_str(),
+            axum::routing::post(bulk_for_organization),
+        )
+        .route(
+            route("/organizations/:id/contacts/:contact_id".to_string()).as_str(),
+            axum::routing::get(find_for_organization)
+                .put(update_for_organization)
+                .delete(delete_for_organization),
+        )
+        .route(
+            route("/organizations/:id/duplicates".to_string()).as_str(),
+            axum::routing::get(duplicates_for_organization),
+        )
+        .route(
+            route("/organizations/:id/contacts/:contact_id/merges".to_string()).as_str(),
+            axum::routing::get(merges_for_organization).post(merge_for_organization),
+        )
+        .route(
+            route("/organizations/:id/contacts/:contact_id/shares".to_string()).as_str(),
+            axum::routing::get(shares_for_organization).post(share_for_organization),
+        )
+        .route(
+            route("/organizations/:id/contacts/:contact_id/shares/:user_id".to_string()).as_str(),
+            axum::routing::delete(unshare_for_organization),
+        )
+        .route(
+            route("/organizations/:id/contacts/:contact_id/versions".to_string()).as_str(),
+            axum::routing::get(versions_for_organization),
+        )
+        .route(
+            route("/organizations/:id/contacts/:contact_id/versions/:version/restore".to_string())
+                .as_str(),
+            axum::routing::post(restore_for_organization),
+        )
+        .route(
+            route("/organizations/:id/contacts/:contact_id/interactions".to_string()).as_str(),
+            axum::routing::get(interactions_for_organization)
+                .post(create_interaction_for_organization),
+        )
+        .route(
+            route(
+                "/organizations/:id/contacts/:contact_id/interactions/:interaction_id".to_string(),
+            )
+            .as_str(),
+            axum::routing::put(update_interaction_for_organization)
+                .delete(delete_interaction_for_organization),
+        )
+        .route(
+            route("/organizations/:id/deleted-contacts".to_string()).as_str(),
+            axum::routing::get(deleted_for_organization),
         )
         .route(
-            route("/contacts/:id".to_string()).as_str(),
-            axum::routing::get(find),
+            route("/organizations/:id/vcard".to_string()).as_str(),
+            axum::routing::get(export_vcards_for_organization).post(import_vcards_for_organization),
         )
         .route(
-            route("/contacts/:id".to_string()).as_str(),
-            axum::routing::post(create),
+            route("/organizations/:id/csv".to_string()).as_str(),
+            axum::routing::get(export_csv_for_organization).post(import_csv_for_organization),
         )
         .route(
-            route("/contacts/:id".to_string()).as_str(),
-            axum::routing::put(update),
+            route("/organizations/:id/contacts/:contact_id/vcard".to_string()).as_str(),
+            axum::routing::get(export_vcard_for_organization),
         )
         .route(
-            route("/contacts/:id".to_string()).as_str(),
-            axum::routing::delete(delete),
+            route("/organizations/:id/contacts/:contact_id/photo".to_string()).as_str(),
+            axum::routing::get(photo_for_organization)
+                .put(upload_photo_for_organization)
+                .delete(delete_photo_for_organization),
         )
 }
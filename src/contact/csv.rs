@@ -0,0 +1,246 @@
+use super::models::{
+    contact::NewUpdateContact,
+    details::{ContactDetails, ContactPayload},
+};
+use crate::field::models::field::CustomField;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The columns of an exported file, which is also the default layout of an import
+const COLUMNS: [&str; 5] = ["id", "lastname", "firstname", "email", "phone"];
+
+/// The known spreadsheet layouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Fer,
+    Google,
+    Outlook,
+}
+
+/// The header of the CSV column read for each contact field
+#[derive(Debug, Default, Deserialize)]
+pub struct ColumnMapping {
+    pub lastname: Option<String>,
+    pub firstname: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+}
+
+impl Preset {
+    /// Parse a preset from its name
+    pub fn parse(preset: &str) -> Option<Self> {
+        match preset.trim() {
+            "fer" => Some(Self::Fer),
+            "google" => Some(Self::Google),
+            "outlook" => Some(Self::Outlook),
+            _ => None,
+        }
+    }
+
+    /// The candidate headers of each field, the first one present in the file is used
+    fn candidates(&self) -> [&'static [&'static str]; 4] {
+        match self {
+            Self::Fer => [&["lastname"], &["firstname"], &["email"], &["phone"]],
+            Self::Google => [
+                &["Last Name", "Family Name"],
+                &["First Name", "Given Name"],
+                &["E-mail 1 - Value"],
+                &["Phone 1 - Value"],
+            ],
+            Self::Outlook => [
+                &["Last Name"],
+                &["First Name"],
+                &["E-mail Address"],
+                &[
+                    "Mobile Phone",
+                    "Business Phone",
+                    "Home Phone",
+                    "Primary Phone",
+                ],
+            ],
+        }
+    }
+}
+
+/// Quote a cell which a spreadsheet would run as a formula (`=`, `+`, `-`, `@`, tab or carriage
+/// return first)
+fn protect(value: String) -> String {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value
+    }
+}
+
+/// Remove the quote added by `protect`, so that an exported file can be imported back
+fn unprotect(value: &str) -> &str {
+    match value.strip_prefix('\'') {
+        Some(formula) if formula.starts_with(['=', '+', '-', '@', '\t', '\r']) => formula,
+        _ => value,
+    }
+}
+
+/// Write contacts as CSV, with a column for each custom field of their owner
+pub fn to_csv(contacts: &[ContactDetails], fields: &[CustomField]) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record(
+        COLUMNS
+            .iter()
+            .map(|column| column.to_string())
+            .chain(fields.iter().map(|field| protect(field.name.clone()))),
+    )?;
+    for details in contacts {
+        let contact = &details.contact;
+        let values = fields
+            .iter()
+            .map(|field| match details.fields.get(&field.name) {
+                Some(Value::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+                None => String::new(),
+            });
+
+        writer.write_record(
+            [
+                contact.id.to_string(),
+                contact.lastname.clone(),
+                contact.firstname.clone(),
+                contact.email.clone(),
+                contact.phone.clone(),
+            ]
+            .into_iter()
+            .chain(values)
+            .map(protect),
+        )?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|err| csv::Error::from(err.into_error()))
+}
+
+/// Find the index of the column of a field, the explicit mapping taking precedence over the preset
+fn column(
+    headers: &HashMap<String, usize>,
+    mapped: &Option<String>,
+    candidates: &[&str],
+) -> Option<usize> {
+    match mapped {
+        Some(header) => headers.get(&header.trim().to_lowercase()).copied(),
+        None => candidates
+            .iter()
+            .find_map(|header| headers.get(&header.to_lowercase()).copied()),
+    }
+}
+
+/// Parse the rows of a CSV file, each row being converted or failing on its own
+pub fn parse(
+    input: &[u8],
+    preset: Preset,
+    mapping: &ColumnMapping,
+) -> Result<Vec<Result<ContactPayload, String>>, String> {
+    let input = input.strip_prefix(b"\xef\xbb\xbf").unwrap_or(input);
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(input);
+
+    let headers = reader
+        .headers()
+        .map_err(|err| err.to_string())?
+        .iter()
+        .enumerate()
+        .map(|(index, header)| (header.to_lowercase(), index))
+        .collect::<HashMap<_, _>>();
+
+    let [lastname, firstname, email, phone] = preset.candidates();
+    let columns = [
+        column(&headers, &mapping.lastname, lastname),
+        column(&headers, &mapping.firstname, firstname),
+        column(&headers, &mapping.email, email),
+        column(&headers, &mapping.phone, phone),
+    ];
+    if columns[0].is_none() && columns[1].is_none() {
+        return Err("no name column found".to_string());
+    }
+
+    let rows = reader
+        .records()
+        .map(|record| {
+            let record = record.map_err(|err| err.to_string())?;
+            let [lastname, firstname, email, phone] = columns.map(|column| {
+                let value = column
+                    .and_then(|index| record.get(index))
+                    .unwrap_or_default();
+                unprotect(value).to_string()
+            });
+
+            // A missing name is left out, an empty email or phone means that there is none
+            if lastname.is_empty() && firstname.is_empty() {
+                return Err("the row has no name".to_string());
+            }
+            let optional = |name: String| Some(name).filter(|name| !name.is_empty());
+
+            Ok(NewUpdateContact {
+                lastname: optional(lastname),
+                firstname: optional(firstname),
+                email: Some(email),
+                phone: Some(phone),
+            }
+            .into())
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn quotes_the_cells_read_as_formulas() {
+        assert_eq!(
+            protect("=HYPERLINK(\"x\")".to_string()),
+            "'=HYPERLINK(\"x\")"
+        );
+        assert_eq!(protect("+33612345678".to_string()), "'+33612345678");
+        assert_eq!(protect("@SUM(A1)".to_string()), "'@SUM(A1)");
+        assert_eq!(protect("Smith".to_string()), "Smith");
+        assert_eq!(protect(String::new()), "");
+    }
+
+    #[test]
+    fn reads_back_the_quoted_cells() {
+        for value in ["=1+1", "-2", "+33612345678", "'quoted", "Smith"] {
+            assert_eq!(unprotect(&protect(value.to_string())), value);
+        }
+    }
+
+    #[test]
+    fn imports_a_row_without_email() {
+        let input = b"lastname,firstname,email,phone\nSmith,Alice,,'+33612345678\n";
+
+        let mut rows = parse(input, Preset::Fer, &ColumnMapping::default()).unwrap();
+        let mut row = rows.remove(0).unwrap();
+        row.normalize();
+
+        assert_eq!(row.contact.email.as_deref(), Some(""));
+        assert_eq!(row.contact.phone.as_deref(), Some("+33612345678"));
+        assert!(row.validate().is_ok());
+    }
+
+    #[test]
+    fn leaves_out_a_missing_first_name() {
+        let input = b"lastname,firstname\nMadonna,\n,\n";
+
+        let rows = parse(input, Preset::Fer, &ColumnMapping::default()).unwrap();
+
+        let row = rows[0].as_ref().unwrap();
+        assert_eq!(row.contact.lastname.as_deref(), Some("Madonna"));
+        assert_eq!(row.contact.firstname, None);
+        assert!(rows[1].is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use super::models::details::ContactDetails;
+use serde::Serialize;
+use std::collections::HashSet;
+use strsim::jaro_winkler;
+
+/// Lowest name similarity which counts as a match
+const NAME_THRESHOLD: f64 = 0.88;
+
+/// Default score from which a pair is reported as a suspected duplicate
+pub const DEFAULT_THRESHOLD: f64 = 0.5;
+
+/// A pair of contacts which probably are the same person
+#[derive(Debug, Serialize)]
+pub struct Candidate {
+    pub contact_ids: [i32; 2],
+    pub score: f64,
+    pub reasons: Vec<&'static str>,
+}
+
+/// The normalized values of a contact which are compared
+struct Keys {
+    id: i32,
+    emails: HashSet<String>,
+    phones: HashSet<String>,
+    name: String,
+    reversed_name: String,
+}
+
+impl Keys {
+    fn new(details: &ContactDetails) -> Self {
+        let contact = &details.contact;
+        let firstname = contact.firstname.trim().to_lowercase();
+        let lastname = contact.lastname.trim().to_lowercase();
+
+        Keys {
+            id: contact.id,
+            emails: details
+                .emails
+                .iter()
+                .map(|email| email.value.trim().to_lowercase())
+                .filter(|email| !email.is_empty())
+                .collect(),
+            phones: details
+                .phones
+                .iter()
+                .map(|phone| phone.value.chars().filter(|c| c.is_ascii_digit()).collect())
+                .filter(|phone: &String| !phone.is_empty())
+                .collect(),
+            name: format!("{} {}", firstname, lastname),
+            reversed_name: format!("{} {}", lastname, firstname),
+        }
+    }
+
+    /// Score the likeliness that two contacts are the same person, between 0 and 1
+    fn score(&self, other: &Keys) -> (f64, Vec<&'static str>) {
+        let mut score = 0.0;
+        let mut reasons = Vec::new();
+
+        if !self.emails.is_disjoint(&other.emails) {
+            score += 0.6;
+            reasons.push("email");
+        }
+        if !self.phones.is_disjoint(&other.phones) {
+            score += 0.4;
+            reasons.push("phone");
+        }
+
+        // The first and last names are often swapped by the imports
+        let similarity = jaro_winkler(&self.name, &other.name)
+            .max(jaro_winkler(&self.name, &other.reversed_name));
+        if similarity >= NAME_THRESHOLD {
+            score += 0.55 * similarity;
+            reasons.push("name");
+        }
+
+        (f64::min(score, 1.0), reasons)
+    }
+}
+
+/// Find the pairs of contacts scoring at least the threshold, the most likely first
+pub fn find(contacts: &[ContactDetails], threshold: f64) -> Vec<Candidate> {
+    let keys = contacts.iter().map(Keys::new).collect::<Vec<_>>();
+    let mut candidates = Vec::new();
+
+    for (index, first) in keys.iter().enumerate() {
+        for second in &keys[index + 1..] {
+            let (score, reasons) = first.score(second);
+            if score >= threshold {
+                candidates.push(Candidate {
+                    contact_ids: [first.id, second.id],
+                    score: (score * 100.0).round() / 100.0,
+                    reasons,
+                });
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build the keys of a contact from its already normalized values
+    fn keys(id: i32, firstname: &str, lastname: &str, emails: &[&str], phones: &[&str]) -> Keys {
+        Keys {
+            id,
+            emails: emails.iter().map(|email| email.to_string()).collect(),
+            phones: phones.iter().map(|phone| phone.to_string()).collect(),
+            name: format!("{} {}", firstname, lastname),
+            reversed_name: format!("{} {}", lastname, firstname),
+        }
+    }
+
+    #[test]
+    fn reports_the_same_email_with_different_names() {
+        let first = keys(1, "alice", "smith", &["alice@example.com"], &[]);
+        let second = keys(2, "bob", "jones", &["alice@example.com"], &[]);
+
+        let (score, reasons) = first.score(&second);
+
+        assert_eq!(reasons, vec!["email"]);
+        assert!(score >= DEFAULT_THRESHOLD);
+    }
+
+    #[test]
+    fn reports_near_identical_names() {
+        let first = keys(1, "jonathan", "smith", &[], &[]);
+        let second = keys(2, "jonathon", "smith", &[], &[]);
+
+        let (score, reasons) = first.score(&second);
+
+        assert_eq!(reasons, vec!["name"]);
+        assert!(score >= DEFAULT_THRESHOLD);
+    }
+
+    #[test]
+    fn reports_swapped_names() {
+        let first = keys(1, "alice", "smith", &[], &[]);
+        let second = keys(2, "smith", "alice", &[], &[]);
+
+        let (score, reasons) = first.score(&second);
+
+        assert_eq!(reasons, vec!["name"]);
+        assert!(score > 0.5);
+    }
+
+    #[test]
+    fn caps_the_score_when_everything_matches() {
+        let first = keys(
+            1,
+            "alice",
+            "smith",
+            &["alice@example.com"],
+            &["33612345678"],
+        );
+        let second = keys(
+            2,
+            "alice",
+            "smith",
+            &["alice@example.com"],
+            &["33612345678"],
+        );
+
+        let (score, reasons) = first.score(&second);
+
+        assert_eq!(reasons, vec!["email", "phone", "name"]);
+        assert_eq!(score, 1.0);
+    }
+
+    #[test]
+    fn ignores_different_people() {
+        let first = keys(
+            1,
+            "alice",
+            "smith",
+            &["alice@example.com"],
+            &["33612345678"],
+        );
+        let second = keys(2, "bob", "jones", &["bob@example.com"], &["33698765432"]);
+
+        let (score, reasons) = first.score(&second);
+
+        assert!(reasons.is_empty());
+        assert_eq!(score, 0.0);
+    }
+
+    #[test]
+    fn does_not_report_a_shared_phone_alone() {
+        let first = keys(1, "alice", "smith", &[], &["33612345678"]);
+        let second = keys(2, "bob", "jones", &[], &["33612345678"]);
+
+        let (score, _) = first.score(&second);
+
+        assert!(score < DEFAULT_THRESHOLD);
+    }
+}
@@ -0,0 +1,193 @@
+use super::{
+    models::{
+        contact::{Contact, Owner},
+        details::ContactPayload,
+    },
+    photo,
+    vcard::ParsedCard,
+};
+use crate::utils::image;
+use diesel::{result::Error, Connection, SqliteConnection};
+use serde::Serialize;
+use std::collections::HashSet;
+use validator::Validate;
+
+/// Largest accepted import file, in bytes
+pub const MAX_IMPORT_SIZE: usize = 10 * 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Created,
+    Skipped,
+    Failed,
+}
+
+/// The result of the import of one entry of the file
+#[derive(Debug, Serialize)]
+pub struct ImportEntry {
+    pub index: usize,
+    pub status: ImportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: bool,
+    pub created: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub entries: Vec<ImportEntry>,
+}
+
+impl ImportReport {
+    /// Record the result of an entry
+    fn push(&mut self, entry: ImportEntry) {
+        match entry.status {
+            ImportStatus::Created => self.created += 1,
+            ImportStatus::Skipped => self.skipped += 1,
+            ImportStatus::Failed => self.failed += 1,
+        }
+        self.entries.push(entry);
+    }
+
+    /// Record a failed entry
+    fn fail(&mut self, index: usize, reason: String) {
+        self.push(ImportEntry {
+            index,
+            status: ImportStatus::Failed,
+            contact_id: None,
+            reason: Some(reason),
+        });
+    }
+}
+
+/// Create the parsed contacts of an owner with their photos, skipping those whose email is
+/// already known
+pub fn import(
+    connection: &mut SqliteConnection,
+    owner: Owner,
+    entries: Vec<Result<ParsedCard, String>>,
+    changed_by: i32,
+) -> Result<ImportReport, Error> {
+    let mut report = ImportReport {
+        imported: true,
+        ..ImportReport::default()
+    };
+
+    let mut emails = Contact::all_for_owner(connection, owner)?
+        .into_iter()
+        .map(|contact| contact.email.to_lowercase())
+        .filter(|email| !email.is_empty())
+        .collect::<HashSet<_>>();
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        let ParsedCard {
+            contact: mut new_contact,
+            photo,
+        } = match entry {
+            Ok(card) => card,
+            Err(reason) => {
+                report.fail(index, reason);
+                continue;
+            }
+        };
+
+        new_contact.normalize();
+        if let Err(errors) = new_contact.validate() {
+            report.fail(index, errors.to_string());
+            continue;
+        }
+
+        let email = new_contact
+            .contact
+            .email
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase();
+        if !email.is_empty() && emails.contains(&email) {
+            report.push(ImportEntry {
+                index,
+                status: ImportStatus::Skipped,
+                contact_id: None,
+                reason: Some("a contact with this email already exists".to_string()),
+            });
+            continue;
+        }
+
+        match new_contact.create(connection, owner, changed_by) {
+            Ok(details) => {
+                let id = details.contact.id;
+                // A photo which cannot be read leaves the contact without photo
+                if let Some(photo) = photo {
+                    let stored = image::process(&photo).and_then(|processed| {
+                        photo::store(connection, details.contact, &processed, changed_by)
+                    });
+                    if let Err(err) = stored {
+                        tracing::error!("failed to import the photo of contact {}: {:?}", id, err);
+                    }
+                }
+                emails.insert(email);
+                report.push(ImportEntry {
+                    index,
+                    status: ImportStatus::Created,
+                    contact_id: Some(id),
+                    reason: None,
+                });
+            }
+            Err(err) => report.fail(index, err.to_string()),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Create the parsed contacts of an owner in one transaction, nothing is created when an entry
+/// fails or when it is a dry run
+pub fn import_all(
+    connection: &mut SqliteConnection,
+    owner: Owner,
+    entries: Vec<Result<ContactPayload, String>>,
+    dry_run: bool,
+    changed_by: i32,
+) -> Result<ImportReport, Error> {
+    let mut result = None;
+    // The rows of a file have no photo
+    let entries = entries
+        .into_iter()
+        .map(|entry| {
+            entry.map(|contact| ParsedCard {
+                contact,
+                photo: None,
+            })
+        })
+        .collect();
+
+    let outcome = connection.transaction(|connection| {
+        let report = import(connection, owner, entries, changed_by)?;
+        let rollback = dry_run || report.failed > 0;
+        result = Some(report);
+
+        if rollback {
+            Err(Error::RollbackTransaction)
+        } else {
+            Ok(())
+        }
+    });
+
+    match (outcome, result) {
+        (Ok(()), Some(report)) => Ok(report),
+        (Err(Error::RollbackTransaction), Some(mut report)) => {
+            report.imported = false;
+            for entry in &mut report.entries {
+                entry.contact_id = None;
+            }
+            Ok(report)
+        }
+        (Err(err), _) => Err(err),
+        (Ok(()), None) => unreachable!("the report is set when the transaction succeeds"),
+    }
+}
@@ -1,2 +1,10 @@
+pub mod bulk;
 pub mod controllers;
+pub mod csv;
+pub mod duplicates;
+pub mod import;
 pub mod models;
+pub mod phone;
+pub mod photo;
+pub mod query;
+pub mod vcard;
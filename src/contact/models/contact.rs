@@ -1,32 +1,91 @@
-use crate::schema::contacts;
+use super::{
+    details::ContactDetails, interaction::Interaction, merge::ContactMerge, share::ContactShare,
+    version::ContactVersion,
+};
+use crate::contact::phone::validate_phone;
+use crate::reminder::models::notification::Notification;
+use crate::schema::{contact_tags, contacts};
+use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use diesel::result::Error;
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use std::collections::HashSet;
+use validator::{validate_email, Validate, ValidationError};
 
-#[derive(Debug, Deserialize, Serialize, Queryable, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Queryable, QueryableByName, Identifiable, PartialEq)]
+#[diesel(table_name = contacts)]
 pub struct Contact {
-    id: i32,
-    user_id: i32,
-    lastname: String,
-    firstname: String,
-    email: String,
-    phone: String,
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub organization_id: Option<i32>,
+    pub lastname: String,
+    pub firstname: String,
+    pub email: String,
+    pub phone: String,
+    pub last_contacted_at: Option<NaiveDateTime>,
+    pub photo: Option<String>,
+    pub version: i32,
 }
 
-#[derive(Deserialize, Validate, Insertable, AsChangeset)]
+#[derive(Clone, Default, Deserialize, Validate, Insertable, AsChangeset)]
 #[diesel(table_name = contacts)]
 pub struct NewUpdateContact {
     #[validate(length(min = 4))]
-    lastname: Option<String>,
+    pub lastname: Option<String>,
     #[validate(length(min = 4))]
-    firstname: Option<String>,
-    #[validate(email)]
-    email: Option<String>,
-    phone: Option<String>,
+    pub firstname: Option<String>,
+    #[validate(custom = "validate_optional_email")]
+    pub email: Option<String>,
+    #[validate(custom = "validate_phone")]
+    pub phone: Option<String>,
+}
+
+/// Check that an email is valid, an empty one meaning no email
+fn validate_optional_email(email: &str) -> Result<(), ValidationError> {
+    if email.is_empty() || validate_email(email) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("email"))
+    }
+}
+
+impl NewUpdateContact {
+    /// Check that at least one field is set, an empty changeset cannot be saved
+    pub fn has_changes(&self) -> bool {
+        self.lastname.is_some()
+            || self.firstname.is_some()
+            || self.email.is_some()
+            || self.phone.is_some()
+    }
+}
+
+/// A contact with its owner, either a user or an organization
+#[derive(Insertable)]
+#[diesel(table_name = contacts)]
+struct NewContact {
+    user_id: Option<i32>,
+    organization_id: Option<i32>,
+    #[diesel(embed)]
+    fields: NewUpdateContact,
+}
+
+/// The owner of a contact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Owner {
+    User(i32),
+    Organization(i32),
 }
 
 impl Contact {
+    /// Get the owner of the contact
+    pub fn owner(&self) -> Owner {
+        match (self.user_id, self.organization_id) {
+            (_, Some(organization_id)) => Owner::Organization(organization_id),
+            (Some(user_id), None) => Owner::User(user_id),
+            (None, None) => unreachable!("a contact always has an owner"),
+        }
+    }
+
     /// Find all contacts for a user
     pub fn all(
         connection: &mut SqliteConnection,
@@ -41,6 +100,46 @@ impl Contact {
         Ok(results)
     }
 
+    /// Find all contacts of an organization
+    pub fn all_for_organization(
+        connection: &mut SqliteConnection,
+        organization_id_param: i32,
+    ) -> Result<Vec<Contact>, Error> {
+        use crate::schema::contacts::dsl::*;
+
+        contacts
+            .filter(organization_id.eq(organization_id_param))
+            .load::<Contact>(connection)
+    }
+
+    /// Find all contacts of an owner
+    pub fn all_for_owner(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+    ) -> Result<Vec<Contact>, Error> {
+        match owner {
+            Owner::User(user) => Contact::all(connection, user),
+            Owner::Organization(organization) => {
+                Contact::all_for_organization(connection, organization)
+            }
+        }
+    }
+
+    /// Find a contact by id, only if it belongs to the owner
+    pub fn find_owned(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        id_param: i32,
+    ) -> Result<Self, Error> {
+        let contact = Contact::find(connection, id_param)?;
+
+        if contact.owner() != owner {
+            return Err(Error::NotFound);
+        }
+
+        Ok(contact)
+    }
+
     /// Find a contact by id
     pub fn find(connection: &mut SqliteConnection, id_param: i32) -> Result<Self, Error> {
         use crate::schema::contacts::dsl::*;
@@ -53,17 +152,35 @@ impl Contact {
     /// Create a new contact
     pub fn create(
         connection: &mut SqliteConnection,
+        owner: Owner,
         new_contact: NewUpdateContact,
     ) -> Result<Self, Error> {
         use crate::schema::contacts::dsl::*;
 
-        diesel::insert_into(contacts)
-            .values(&new_contact)
-            .execute(connection)?;
+        let (user_id_param, organization_id_param) = match owner {
+            Owner::User(user) => (Some(user), None),
+            Owner::Organization(organization) => (None, Some(organization)),
+        };
 
-        let contact = contacts.order(id.desc()).first::<Contact>(connection)?;
+        // The callers require a name, the fields which are not given are stored empty
+        let fields = NewUpdateContact {
+            lastname: Some(new_contact.lastname.unwrap_or_default()),
+            firstname: Some(new_contact.firstname.unwrap_or_default()),
+            email: Some(new_contact.email.unwrap_or_default()),
+            phone: Some(new_contact.phone.unwrap_or_default()),
+        };
 
-        Ok(contact)
+        connection.transaction(|connection| {
+            diesel::insert_into(contacts)
+                .values(&NewContact {
+                    user_id: user_id_param,
+                    organization_id: organization_id_param,
+                    fields,
+                })
+                .execute(connection)?;
+
+            contacts.order(id.desc()).first::<Contact>(connection)
+        })
     }
 
     /// Update a contact
@@ -75,7 +192,7 @@ impl Contact {
         use crate::schema::contacts::dsl::*;
 
         diesel::update(contacts.find(id_param))
-            .set::<NewUpdateContact>(new_contact)
+            .set((new_contact, version.eq(version + 1)))
             .execute(connection)?;
 
         let contact = contacts.find(id_param).first::<Contact>(connection)?;
@@ -83,12 +200,72 @@ impl Contact {
         Ok(contact)
     }
 
+    /// Bump the version of a contact whose details changed
+    pub fn touch(connection: &mut SqliteConnection, id_param: i32) -> Result<Self, Error> {
+        use crate::schema::contacts::dsl::*;
+
+        diesel::update(contacts.find(id_param))
+            .set(version.eq(version + 1))
+            .execute(connection)?;
+
+        contacts.find(id_param).first::<Contact>(connection)
+    }
+
+    /// Set the storage key of the photo of a contact
+    pub fn set_photo(
+        connection: &mut SqliteConnection,
+        id_param: i32,
+        photo_param: Option<String>,
+    ) -> Result<Self, Error> {
+        use crate::schema::contacts::dsl::*;
+
+        diesel::update(contacts.find(id_param))
+            .set((photo.eq(photo_param), version.eq(version + 1)))
+            .execute(connection)?;
+
+        contacts.find(id_param).first::<Contact>(connection)
+    }
+
     /// Delete a contact
     pub fn delete(connection: &mut SqliteConnection, id_param: i32) -> Result<usize, Error> {
         use crate::schema::contacts::dsl::*;
 
-        let result = diesel::delete(contacts.find(id_param)).execute(connection)?;
+        connection.transaction(|connection| {
+            diesel::delete(contact_tags::table.filter(contact_tags::contact_id.eq(id_param)))
+                .execute(connection)?;
+            ContactDetails::delete_for(connection, &[id_param])?;
+            ContactMerge::delete_for(connection, &[id_param])?;
+            ContactShare::delete_for(connection, &[id_param])?;
+            Interaction::delete_for(connection, &[id_param])?;
+            Notification::delete_for_contacts(connection, &[id_param])?;
+
+            diesel::delete(contacts.find(id_param)).execute(connection)
+        })
+    }
+
+    /// Delete all the contacts of an owner and get the storage keys of their photos, the files
+    /// are left to the caller to delete once its transaction is committed
+    pub fn delete_for_owner(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+    ) -> Result<HashSet<String>, Error> {
+        let ids = Contact::all_for_owner(connection, owner)?
+            .into_iter()
+            .map(|contact| contact.id)
+            .collect::<Vec<_>>();
+        // The history knows every photo the contacts had, the replaced and deleted ones included
+        let photos = ContactVersion::photos_for_owner(connection, owner)?;
+
+        diesel::delete(contact_tags::table.filter(contact_tags::contact_id.eq_any(&ids)))
+            .execute(connection)?;
+        ContactDetails::delete_for(connection, &ids)?;
+        ContactMerge::delete_for(connection, &ids)?;
+        ContactShare::delete_for(connection, &ids)?;
+        Interaction::delete_for(connection, &ids)?;
+        Notification::delete_for_contacts(connection, &ids)?;
+        ContactVersion::delete_for_owner(connection, owner)?;
+        diesel::delete(contacts::table.filter(contacts::id.eq_any(&ids))).execute(connection)?;
 
-        Ok(result)
+        Ok(photos)
     }
 }
@@ -0,0 +1,182 @@
+use super::contact::Contact;
+use crate::schema::contact_dates;
+use chrono::{Datelike, Duration, NaiveDate};
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Contact), table_name = contact_dates)]
+pub struct ContactDate {
+    pub id: i32,
+    #[serde(skip)]
+    pub contact_id: i32,
+    pub kind: String,
+    pub date: NaiveDate,
+    pub label: String,
+    pub remind_days_before: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, Insertable)]
+#[diesel(table_name = contact_dates)]
+pub struct DatePayload {
+    #[validate(custom = "validate_kind")]
+    pub kind: String,
+    pub date: NaiveDate,
+    #[serde(default)]
+    #[validate(length(max = 255))]
+    pub label: String,
+    #[serde(default)]
+    #[validate(range(min = 0, max = 60))]
+    pub remind_days_before: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = contact_dates)]
+struct NewDate {
+    contact_id: i32,
+    #[diesel(embed)]
+    fields: DatePayload,
+}
+
+/// The kinds of dates of a contact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateKind {
+    Birthday,
+    Anniversary,
+    FollowUp,
+}
+
+impl DateKind {
+    /// Get the kind as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Birthday => "birthday",
+            Self::Anniversary => "anniversary",
+            Self::FollowUp => "follow_up",
+        }
+    }
+
+    /// Parse a kind from its database value
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "birthday" => Some(Self::Birthday),
+            "anniversary" => Some(Self::Anniversary),
+            "follow_up" => Some(Self::FollowUp),
+            _ => None,
+        }
+    }
+
+    /// Get the name of the kind in a sentence
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Birthday => "birthday",
+            Self::Anniversary => "anniversary",
+            Self::FollowUp => "follow-up",
+        }
+    }
+}
+
+/// Check the kind of a date
+fn validate_kind(kind: &str) -> Result<(), ValidationError> {
+    match DateKind::parse(kind) {
+        Some(_) => Ok(()),
+        None => Err(ValidationError::new("kind")),
+    }
+}
+
+/// Check that a contact has at most one birthday and one anniversary
+pub fn validate_dates(dates: &[DatePayload]) -> Result<(), ValidationError> {
+    for kind in [DateKind::Birthday, DateKind::Anniversary] {
+        if dates
+            .iter()
+            .filter(|date| date.kind == kind.as_str())
+            .count()
+            > 1
+        {
+            return Err(ValidationError::new("dates"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the date in a year with the month and day of another one, the 29th of February falling
+/// on the 28th in common years
+fn in_year(date: NaiveDate, year: i32) -> NaiveDate {
+    date.with_year(year)
+        .or_else(|| NaiveDate::from_ymd_opt(year, date.month(), date.day() - 1))
+        .unwrap_or(date)
+}
+
+impl ContactDate {
+    /// Get the kind of the date
+    pub fn kind(&self) -> DateKind {
+        DateKind::parse(&self.kind).unwrap_or(DateKind::FollowUp)
+    }
+
+    /// Get the next occurrence of the date from a day on, none for a past follow-up
+    pub fn next_occurrence(&self, from: NaiveDate) -> Option<NaiveDate> {
+        match self.kind() {
+            DateKind::FollowUp => Some(self.date).filter(|date| *date >= from),
+            DateKind::Birthday | DateKind::Anniversary => {
+                let occurrence = in_year(self.date, from.year());
+                if occurrence >= from {
+                    Some(occurrence)
+                } else {
+                    Some(in_year(self.date, from.year() + 1))
+                }
+            }
+        }
+    }
+
+    /// Get the occurrence to remind on a day, if the day falls between the first day of the
+    /// reminder and a number of days late after the occurrence
+    pub fn due(&self, today: NaiveDate, late_days: i64) -> Option<NaiveDate> {
+        self.next_occurrence(today - Duration::days(late_days))
+            .filter(|occurrence| {
+                *occurrence - Duration::days(i64::from(self.remind_days_before)) <= today
+            })
+    }
+
+    /// Replace the dates of a contact
+    pub fn replace(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+        dates: Vec<DatePayload>,
+    ) -> Result<usize, Error> {
+        diesel::delete(contact_dates::table.filter(contact_dates::contact_id.eq(contact_id_param)))
+            .execute(connection)?;
+
+        diesel::insert_into(contact_dates::table)
+            .values(
+                dates
+                    .into_iter()
+                    .map(|fields| NewDate {
+                        contact_id: contact_id_param,
+                        fields,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(connection)
+    }
+
+    /// Delete the dates of contacts
+    pub fn delete_for(connection: &mut SqliteConnection, ids: &[i32]) -> Result<usize, Error> {
+        use crate::schema::contact_dates::dsl::*;
+
+        diesel::delete(contact_dates.filter(contact_id.eq_any(ids))).execute(connection)
+    }
+}
+
+impl From<&ContactDate> for DatePayload {
+    fn from(date: &ContactDate) -> Self {
+        DatePayload {
+            kind: date.kind.clone(),
+            date: date.date,
+            label: date.label.clone(),
+            remind_days_before: date.remind_days_before,
+        }
+    }
+}
@@ -0,0 +1,621 @@
+use super::{
+    contact::{Contact, NewUpdateContact, Owner},
+    date::{validate_dates, ContactDate, DatePayload},
+    version::{Action, ContactVersion},
+};
+use crate::{
+    contact::phone::{self, validate_phone, Format},
+    field::models::field::CustomField,
+    schema::{contact_addresses, contact_dates, contact_emails, contact_phones},
+};
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use validator::{Validate, ValidationError};
+
+/// The labels of the emails and postal addresses
+const LABELS: [&str; 3] = ["work", "home", "other"];
+
+/// The labels of the phone numbers
+const PHONE_LABELS: [&str; 4] = ["work", "home", "mobile", "other"];
+
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Contact), table_name = contact_emails)]
+pub struct ContactEmail {
+    pub id: i32,
+    #[serde(skip)]
+    pub contact_id: i32,
+    pub label: String,
+    pub value: String,
+    #[serde(rename = "primary")]
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Contact), table_name = contact_phones)]
+pub struct ContactPhone {
+    pub id: i32,
+    #[serde(skip)]
+    pub contact_id: i32,
+    pub label: String,
+    pub value: String,
+    #[serde(rename = "primary")]
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Contact), table_name = contact_addresses)]
+pub struct ContactAddress {
+    pub id: i32,
+    #[serde(skip)]
+    pub contact_id: i32,
+    pub label: String,
+    pub street: String,
+    pub locality: String,
+    pub region: String,
+    pub postal_code: String,
+    pub country: String,
+    #[serde(rename = "primary")]
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, Insertable)]
+#[diesel(table_name = contact_emails)]
+pub struct EmailPayload {
+    #[serde(default = "default_label")]
+    #[validate(custom = "validate_label")]
+    pub label: String,
+    #[validate(email)]
+    pub value: String,
+    #[serde(default)]
+    #[diesel(column_name = is_primary)]
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, Insertable)]
+#[diesel(table_name = contact_phones)]
+pub struct PhonePayload {
+    #[serde(default = "default_label")]
+    #[validate(custom = "validate_phone_label")]
+    pub label: String,
+    #[validate(length(min = 1), custom = "validate_phone")]
+    pub value: String,
+    #[serde(default)]
+    #[diesel(column_name = is_primary)]
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Validate, Insertable)]
+#[diesel(table_name = contact_addresses)]
+pub struct AddressPayload {
+    #[serde(default = "default_label")]
+    #[validate(custom = "validate_label")]
+    pub label: String,
+    #[serde(default)]
+    #[validate(length(max = 255))]
+    pub street: String,
+    #[serde(default)]
+    #[validate(length(max = 255))]
+    pub locality: String,
+    #[serde(default)]
+    #[validate(length(max = 255))]
+    pub region: String,
+    #[serde(default)]
+    #[validate(length(max = 32))]
+    pub postal_code: String,
+    #[serde(default)]
+    #[validate(length(max = 255))]
+    pub country: String,
+    #[serde(default)]
+    #[diesel(column_name = is_primary)]
+    pub primary: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = contact_emails)]
+struct NewEmail {
+    contact_id: i32,
+    #[diesel(embed)]
+    fields: EmailPayload,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = contact_phones)]
+struct NewPhone {
+    contact_id: i32,
+    #[diesel(embed)]
+    fields: PhonePayload,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = contact_addresses)]
+struct NewAddress {
+    contact_id: i32,
+    #[diesel(embed)]
+    fields: AddressPayload,
+}
+
+/// A contact with its emails, phones, postal addresses, dates and custom field values
+#[derive(Debug, Serialize)]
+pub struct ContactDetails {
+    #[serde(flatten)]
+    pub contact: Contact,
+    pub emails: Vec<ContactEmail>,
+    pub phones: Vec<ContactPhone>,
+    pub addresses: Vec<ContactAddress>,
+    pub dates: Vec<ContactDate>,
+    pub fields: BTreeMap<String, Value>,
+}
+
+/// The fields of a contact and the lists of its emails, phones, addresses and dates, a list which
+/// is given replaces the stored one, the custom field values which are given are set and a null
+/// value removes one
+#[derive(Deserialize, Validate)]
+pub struct ContactPayload {
+    #[serde(flatten)]
+    #[validate]
+    pub contact: NewUpdateContact,
+    #[validate]
+    pub emails: Option<Vec<EmailPayload>>,
+    #[validate]
+    pub phones: Option<Vec<PhonePayload>>,
+    #[validate]
+    pub addresses: Option<Vec<AddressPayload>>,
+    #[validate]
+    #[validate(custom = "validate_dates")]
+    pub dates: Option<Vec<DatePayload>>,
+    pub fields: Option<HashMap<String, Value>>,
+}
+
+fn default_label() -> String {
+    "other".to_string()
+}
+
+/// Check the label of an email or an address
+fn validate_label(label: &str) -> Result<(), ValidationError> {
+    if LABELS.contains(&label) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("label"))
+    }
+}
+
+/// Check the label of a phone number
+fn validate_phone_label(label: &str) -> Result<(), ValidationError> {
+    if PHONE_LABELS.contains(&label) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("label"))
+    }
+}
+
+/// Keep a single primary record: the first one flagged, or else the first one
+fn single_primary<T>(records: &mut [T], primary: fn(&mut T) -> &mut bool) {
+    let index = records
+        .iter_mut()
+        .position(|record| *primary(record))
+        .unwrap_or(0);
+
+    for (position, record) in records.iter_mut().enumerate() {
+        *primary(record) = position == index;
+    }
+}
+
+impl ContactDetails {
+    /// Load the emails, phones, addresses, dates and custom field values of contacts
+    pub fn load(
+        connection: &mut SqliteConnection,
+        contacts: Vec<Contact>,
+    ) -> Result<Vec<Self>, Error> {
+        let emails = ContactEmail::belonging_to(&contacts)
+            .order(contact_emails::id)
+            .load::<ContactEmail>(connection)?
+            .grouped_by(&contacts);
+        let phones = ContactPhone::belonging_to(&contacts)
+            .order(contact_phones::id)
+            .load::<ContactPhone>(connection)?
+            .grouped_by(&contacts);
+        let addresses = ContactAddress::belonging_to(&contacts)
+            .order(contact_addresses::id)
+            .load::<ContactAddress>(connection)?
+            .grouped_by(&contacts);
+        let dates = ContactDate::belonging_to(&contacts)
+            .order(contact_dates::id)
+            .load::<ContactDate>(connection)?
+            .grouped_by(&contacts);
+        let mut fields = CustomField::values_for(
+            connection,
+            &contacts
+                .iter()
+                .map(|contact| contact.id)
+                .collect::<Vec<_>>(),
+        )?;
+
+        Ok(contacts
+            .into_iter()
+            .zip(emails)
+            .zip(phones)
+            .zip(addresses)
+            .zip(dates)
+            .map(
+                |((((contact, emails), phones), addresses), dates)| ContactDetails {
+                    fields: fields.remove(&contact.id).unwrap_or_default(),
+                    contact,
+                    emails,
+                    phones,
+                    addresses,
+                    dates,
+                },
+            )
+            .collect())
+    }
+
+    /// Load the emails, phones, addresses, dates and custom field values of a contact
+    pub fn load_one(connection: &mut SqliteConnection, contact: Contact) -> Result<Self, Error> {
+        let mut details = ContactDetails::load(connection, vec![contact])?;
+
+        Ok(details.remove(0))
+    }
+
+    /// Render the phone numbers of the contact in a format
+    pub fn format_phones(&mut self, format: Format) {
+        self.contact.phone = phone::format(&self.contact.phone, format);
+        for number in &mut self.phones {
+            number.value = phone::format(&number.value, format);
+        }
+    }
+
+    /// Set the storage key of the photo of a contact and record the new version
+    pub fn set_photo(
+        connection: &mut SqliteConnection,
+        id: i32,
+        photo: Option<String>,
+        changed_by: i32,
+    ) -> Result<ContactDetails, Error> {
+        connection.transaction(|connection| {
+            let contact = Contact::set_photo(connection, id, photo)?;
+            let details = ContactDetails::load_one(connection, contact)?;
+            ContactVersion::record(connection, &details, Action::Update, changed_by)?;
+
+            Ok(details)
+        })
+    }
+
+    /// Delete the contact, its last state is kept in its history
+    pub fn delete(self, connection: &mut SqliteConnection, changed_by: i32) -> Result<(), Error> {
+        connection.transaction(|connection| {
+            ContactVersion::record(connection, &self, Action::Delete, changed_by)?;
+            Contact::delete(connection, self.contact.id)?;
+
+            Ok(())
+        })
+    }
+
+    /// Delete the emails, phones, addresses, dates and custom field values of contacts
+    pub fn delete_for(connection: &mut SqliteConnection, ids: &[i32]) -> Result<(), Error> {
+        diesel::delete(contact_emails::table.filter(contact_emails::contact_id.eq_any(ids)))
+            .execute(connection)?;
+        diesel::delete(contact_phones::table.filter(contact_phones::contact_id.eq_any(ids)))
+            .execute(connection)?;
+        diesel::delete(contact_addresses::table.filter(contact_addresses::contact_id.eq_any(ids)))
+            .execute(connection)?;
+        ContactDate::delete_for(connection, ids)?;
+        CustomField::delete_values_for(connection, ids)?;
+
+        Ok(())
+    }
+}
+
+impl From<NewUpdateContact> for ContactPayload {
+    fn from(contact: NewUpdateContact) -> Self {
+        ContactPayload {
+            contact,
+            emails: None,
+            phones: None,
+            addresses: None,
+            dates: None,
+            fields: None,
+        }
+    }
+}
+
+impl ContactPayload {
+    /// Check that the payload sets a field or replaces a list
+    pub fn has_changes(&self) -> bool {
+        self.contact.has_changes()
+            || self.emails.is_some()
+            || self.phones.is_some()
+            || self.addresses.is_some()
+            || self.dates.is_some()
+            || self.fields.is_some()
+    }
+
+    /// Check that a new contact has a last name or a first name, the other one is stored empty
+    pub fn has_name(&self) -> bool {
+        self.contact.lastname.is_some() || self.contact.firstname.is_some()
+    }
+
+    /// Write the phone numbers in E.164, keep a single primary record in each list and copy the
+    /// primary email and phone to the fields of the contact
+    pub fn normalize(&mut self) {
+        if let Some(number) = self.contact.phone.as_mut() {
+            *number = phone::normalize(number);
+        }
+        for number in self.phones.iter_mut().flatten() {
+            number.value = phone::normalize(&number.value);
+        }
+
+        if let Some(emails) = self.emails.as_mut() {
+            single_primary(emails, |email| &mut email.primary);
+            self.contact.email = Some(
+                emails
+                    .iter()
+                    .find(|email| email.primary)
+                    .map(|email| email.value.clone())
+                    .unwrap_or_default(),
+            );
+        }
+        if let Some(phones) = self.phones.as_mut() {
+            single_primary(phones, |phone| &mut phone.primary);
+            self.contact.phone = Some(
+                phones
+                    .iter()
+                    .find(|phone| phone.primary)
+                    .map(|phone| phone.value.clone())
+                    .unwrap_or_default(),
+            );
+        }
+        if let Some(addresses) = self.addresses.as_mut() {
+            single_primary(addresses, |address| &mut address.primary);
+        }
+    }
+
+    /// Create a contact with its details and record its first version, from a normalized payload
+    pub fn create(
+        self,
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        changed_by: i32,
+    ) -> Result<ContactDetails, Error> {
+        connection.transaction(|connection| {
+            let ContactPayload {
+                contact,
+                emails,
+                phones,
+                addresses,
+                dates,
+                fields,
+            } = self;
+            let (email, phone) = (contact.email.clone(), contact.phone.clone());
+
+            let contact = Contact::create(connection, owner, contact)?;
+            save_details(
+                connection,
+                contact.id,
+                (email, emails),
+                (phone, phones),
+                addresses,
+            )?;
+            if let Some(dates) = dates {
+                ContactDate::replace(connection, contact.id, dates)?;
+            }
+            if let Some(fields) = fields {
+                CustomField::set_values(connection, &contact, fields)?;
+            }
+
+            let details = ContactDetails::load_one(connection, contact)?;
+            ContactVersion::record(connection, &details, Action::Create, changed_by)?;
+
+            Ok(details)
+        })
+    }
+
+    /// Update a contact, replace the lists of details which are given and record the new version,
+    /// a payload which changes nothing is rolled back and returns the contact as it was
+    pub fn update(
+        self,
+        connection: &mut SqliteConnection,
+        id: i32,
+        changed_by: i32,
+    ) -> Result<ContactDetails, Error> {
+        let mut unchanged = None;
+        let result = connection.transaction(|connection| {
+            let contact = Contact::find(connection, id)?;
+            let before = ContactDetails::load_one(connection, contact)?;
+            let details = self.save(connection, id)?;
+            if content(&details) == content(&before) {
+                unchanged = Some(before);
+                return Err(Error::RollbackTransaction);
+            }
+            ContactVersion::record(connection, &details, Action::Update, changed_by)?;
+
+            Ok(details)
+        });
+
+        match (result, unchanged) {
+            (Err(Error::RollbackTransaction), Some(before)) => Ok(before),
+            (result, _) => result,
+        }
+    }
+
+    /// Update a contact and replace the lists of details which are given, without recording a
+    /// version, from a normalized payload
+    pub fn save(self, connection: &mut SqliteConnection, id: i32) -> Result<ContactDetails, Error> {
+        connection.transaction(|connection| {
+            let ContactPayload {
+                contact,
+                emails,
+                phones,
+                addresses,
+                dates,
+                fields,
+            } = self;
+            let (email, phone) = (contact.email.clone(), contact.phone.clone());
+
+            let contact = if contact.has_changes() {
+                Contact::update(connection, id, contact)?
+            } else {
+                Contact::touch(connection, id)?
+            };
+            save_details(
+                connection,
+                contact.id,
+                (email, emails),
+                (phone, phones),
+                addresses,
+            )?;
+            if let Some(dates) = dates {
+                ContactDate::replace(connection, contact.id, dates)?;
+            }
+            if let Some(fields) = fields {
+                CustomField::set_values(connection, &contact, fields)?;
+            }
+
+            ContactDetails::load_one(connection, contact)
+        })
+    }
+}
+
+/// The content of a contact as JSON, without the ids and the version which a saving changes
+fn content(details: &ContactDetails) -> Value {
+    let mut value = serde_json::to_value(details).unwrap_or_default();
+    if let Some(contact) = value.as_object_mut() {
+        contact.remove("id");
+        contact.remove("version");
+        for list in ["emails", "phones", "addresses", "dates"] {
+            if let Some(Value::Array(items)) = contact.get_mut(list) {
+                for item in items.iter_mut().filter_map(Value::as_object_mut) {
+                    item.remove("id");
+                }
+            }
+        }
+    }
+
+    value
+}
+
+/// Replace the lists of details which are given, otherwise keep the primary email and phone in
+/// line with the fields of the contact
+fn save_details(
+    connection: &mut SqliteConnection,
+    contact_id_param: i32,
+    (email, emails): (Option<String>, Option<Vec<EmailPayload>>),
+    (phone, phones): (Option<String>, Option<Vec<PhonePayload>>),
+    addresses: Option<Vec<AddressPayload>>,
+) -> Result<(), Error> {
+    match (emails, email) {
+        (Some(emails), _) => {
+            diesel::delete(
+                contact_emails::table.filter(contact_emails::contact_id.eq(contact_id_param)),
+            )
+            .execute(connection)?;
+            diesel::insert_into(contact_emails::table)
+                .values(
+                    emails
+                        .into_iter()
+                        .map(|fields| NewEmail {
+                            contact_id: contact_id_param,
+                            fields,
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .execute(connection)?;
+        }
+        (None, Some(email)) => {
+            use crate::schema::contact_emails::dsl::*;
+
+            let primary = contact_emails
+                .filter(contact_id.eq(contact_id_param))
+                .filter(is_primary.eq(true));
+
+            if email.is_empty() {
+                diesel::delete(primary).execute(connection)?;
+            } else if diesel::update(primary)
+                .set(value.eq(&email))
+                .execute(connection)?
+                == 0
+            {
+                diesel::insert_into(contact_emails)
+                    .values(&NewEmail {
+                        contact_id: contact_id_param,
+                        fields: EmailPayload {
+                            label: default_label(),
+                            value: email,
+                            primary: true,
+                        },
+                    })
+                    .execute(connection)?;
+            }
+        }
+        (None, None) => {}
+    }
+
+    match (phones, phone) {
+        (Some(phones), _) => {
+            diesel::delete(
+                contact_phones::table.filter(contact_phones::contact_id.eq(contact_id_param)),
+            )
+            .execute(connection)?;
+            diesel::insert_into(contact_phones::table)
+                .values(
+                    phones
+                        .into_iter()
+                        .map(|fields| NewPhone {
+                            contact_id: contact_id_param,
+                            fields,
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .execute(connection)?;
+        }
+        (None, Some(phone)) => {
+            use crate::schema::contact_phones::dsl::*;
+
+            let primary = contact_phones
+                .filter(contact_id.eq(contact_id_param))
+                .filter(is_primary.eq(true));
+
+            if phone.is_empty() {
+                diesel::delete(primary).execute(connection)?;
+            } else if diesel::update(primary)
+                .set(value.eq(&phone))
+                .execute(connection)?
+                == 0
+            {
+                diesel::insert_into(contact_phones)
+                    .values(&NewPhone {
+                        contact_id: contact_id_param,
+                        fields: PhonePayload {
+                            label: default_label(),
+                            value: phone,
+                            primary: true,
+                        },
+                    })
+                    .execute(connection)?;
+            }
+        }
+        (None, None) => {}
+    }
+
+    if let Some(addresses) = addresses {
+        diesel::delete(
+            contact_addresses::table.filter(contact_addresses::contact_id.eq(contact_id_param)),
+        )
+        .execute(connection)?;
+        diesel::insert_into(contact_addresses::table)
+            .values(
+                addresses
+                    .into_iter()
+                    .map(|fields| NewAddress {
+                        contact_id: contact_id_param,
+                        fields,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(connection)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use super::contact::Contact;
+use crate::schema::{contacts, interactions};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Identifiable, Associations)]
+#[diesel(belongs_to(Contact))]
+pub struct Interaction {
+    pub id: i32,
+    pub contact_id: i32,
+    pub kind: String,
+    pub body: String,
+    pub occurred_at: NaiveDateTime,
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = interactions)]
+struct NewInteraction {
+    contact_id: i32,
+    kind: String,
+    body: String,
+    occurred_at: NaiveDateTime,
+    created_by: i32,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct InteractionPayload {
+    #[validate(custom = "validate_kind")]
+    pub kind: String,
+    #[validate(length(min = 1, max = 10000))]
+    pub body: String,
+    pub occurred_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, Validate, AsChangeset)]
+#[diesel(table_name = interactions)]
+pub struct InteractionUpdate {
+    #[validate(custom = "validate_kind")]
+    pub kind: Option<String>,
+    #[validate(length(min = 1, max = 10000))]
+    pub body: Option<String>,
+    pub occurred_at: Option<NaiveDateTime>,
+}
+
+/// The types of entries of the timeline of a contact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractionKind {
+    Call,
+    Meeting,
+    Note,
+}
+
+impl InteractionKind {
+    /// Get the kind as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Call => "call",
+            Self::Meeting => "meeting",
+            Self::Note => "note",
+        }
+    }
+
+    /// Parse a kind from its database value
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "call" => Some(Self::Call),
+            "meeting" => Some(Self::Meeting),
+            "note" => Some(Self::Note),
+            _ => None,
+        }
+    }
+}
+
+/// Check the kind of an entry
+fn validate_kind(kind: &str) -> Result<(), ValidationError> {
+    match InteractionKind::parse(kind) {
+        Some(_) => Ok(()),
+        None => Err(ValidationError::new("kind")),
+    }
+}
+
+impl Interaction {
+    /// Get the timeline of a contact, the latest entry first
+    pub fn all_for_contact(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+    ) -> Result<Vec<Self>, Error> {
+        use crate::schema::interactions::dsl::*;
+
+        interactions
+            .filter(contact_id.eq(contact_id_param))
+            .order((occurred_at.desc(), id.desc()))
+            .load::<Interaction>(connection)
+    }
+
+    /// Find an entry of the timeline of a contact
+    pub fn find_for_contact(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+        id_param: i32,
+    ) -> Result<Self, Error> {
+        use crate::schema::interactions::dsl::*;
+
+        interactions
+            .filter(id.eq(id_param))
+            .filter(contact_id.eq(contact_id_param))
+            .first::<Interaction>(connection)
+    }
+
+    /// Add an entry to the timeline of a contact, it occurs now unless a date is given
+    pub fn create(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+        created_by_param: i32,
+        payload: InteractionPayload,
+    ) -> Result<Self, Error> {
+        use crate::schema::interactions::dsl::*;
+
+        let now = chrono::Utc::now().naive_utc();
+
+        connection.transaction(|connection| {
+            diesel::insert_into(interactions)
+                .values(&NewInteraction {
+                    contact_id: contact_id_param,
+                    kind: payload.kind,
+                    body: payload.body,
+                    occurred_at: payload.occurred_at.unwrap_or(now),
+                    created_by: created_by_param,
+                    created_at: now,
+                    updated_at: now,
+                })
+                .execute(connection)?;
+            Interaction::refresh_last_contacted(connection, contact_id_param)?;
+
+            interactions
+                .order(id.desc())
+                .first::<Interaction>(connection)
+        })
+    }
+
+    /// Update an entry of the timeline
+    pub fn update(
+        &self,
+        connection: &mut SqliteConnection,
+        payload: InteractionUpdate,
+    ) -> Result<Self, Error> {
+        use crate::schema::interactions::dsl::*;
+
+        connection.transaction(|connection| {
+            diesel::update(interactions.find(self.id))
+                .set((&payload, updated_at.eq(chrono::Utc::now().naive_utc())))
+                .execute(connection)?;
+            Interaction::refresh_last_contacted(connection, self.contact_id)?;
+
+            interactions.find(self.id).first::<Interaction>(connection)
+        })
+    }
+
+    /// Delete an entry of the timeline
+    pub fn delete(&self, connection: &mut SqliteConnection) -> Result<usize, Error> {
+        connection.transaction(|connection| {
+            let deleted = diesel::delete(interactions::table.find(self.id)).execute(connection)?;
+            Interaction::refresh_last_contacted(connection, self.contact_id)?;
+
+            Ok(deleted)
+        })
+    }
+
+    /// Set the date a contact was last reached from the calls and meetings of its timeline
+    pub fn refresh_last_contacted(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+    ) -> Result<usize, Error> {
+        let last = interactions::table
+            .filter(interactions::contact_id.eq(contact_id_param))
+            .filter(interactions::kind.eq_any([
+                InteractionKind::Call.as_str(),
+                InteractionKind::Meeting.as_str(),
+            ]))
+            .select(diesel::dsl::max(interactions::occurred_at))
+            .first::<Option<NaiveDateTime>>(connection)?;
+
+        diesel::update(contacts::table.find(contact_id_param))
+            .set((
+                contacts::last_contacted_at.eq(last),
+                contacts::version.eq(contacts::version + 1),
+            ))
+            .execute(connection)
+    }
+
+    /// Move the timeline of a contact to another one
+    pub fn transfer(connection: &mut SqliteConnection, from: i32, to: i32) -> Result<usize, Error> {
+        let moved = diesel::update(interactions::table.filter(interactions::contact_id.eq(from)))
+            .set(interactions::contact_id.eq(to))
+            .execute(connection)?;
+        Interaction::refresh_last_contacted(connection, to)?;
+
+        Ok(moved)
+    }
+
+    /// Delete the timelines of contacts
+    pub fn delete_for(connection: &mut SqliteConnection, ids: &[i32]) -> Result<usize, Error> {
+        use crate::schema::interactions::dsl::*;
+
+        diesel::delete(interactions.filter(contact_id.eq_any(ids))).execute(connection)
+    }
+}
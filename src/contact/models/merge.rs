@@ -0,0 +1,236 @@
+use super::{
+    contact::NewUpdateContact,
+    date::{DateKind, DatePayload},
+    details::{
+        AddressPayload, ContactAddress, ContactDetails, ContactPayload, EmailPayload, PhonePayload,
+    },
+    interaction::Interaction,
+    share::ContactShare,
+};
+use crate::schema::{contact_merges, contact_tags};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct ContactMerge {
+    pub id: i32,
+    pub winner_id: i32,
+    pub loser_id: i32,
+    pub merged_by: i32,
+    #[serde(serialize_with = "serialize_snapshot")]
+    pub snapshot: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = contact_merges)]
+struct NewContactMerge {
+    winner_id: i32,
+    loser_id: i32,
+    merged_by: i32,
+    snapshot: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergePayload {
+    pub duplicate_id: i32,
+}
+
+/// Write the stored JSON snapshot as a JSON value
+fn serialize_snapshot<S: Serializer>(snapshot: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    let value = serde_json::from_str::<serde_json::Value>(snapshot).unwrap_or_default();
+
+    value.serialize(serializer)
+}
+
+/// Keep the value of the winner, or take the one of the loser when it is empty
+fn pick(winner: &str, loser: &str) -> Option<String> {
+    if winner.trim().is_empty() {
+        Some(loser.to_string())
+    } else {
+        Some(winner.to_string())
+    }
+}
+
+impl ContactMerge {
+    /// Find the merges into a contact, the latest first
+    pub fn all_for_contact(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+    ) -> Result<Vec<Self>, Error> {
+        use crate::schema::contact_merges::dsl::*;
+
+        contact_merges
+            .filter(winner_id.eq(contact_id_param))
+            .order(id.desc())
+            .load::<ContactMerge>(connection)
+    }
+
+    /// Merge a duplicate into the winner: the empty fields of the winner are filled, the emails,
+    /// phones, addresses, dates, custom field values, tags and shares it lacks are added, its
+    /// timeline is moved, then the duplicate is deleted
+    pub fn merge(
+        connection: &mut SqliteConnection,
+        winner: ContactDetails,
+        loser: ContactDetails,
+        merged_by_param: i32,
+    ) -> Result<ContactDetails, Error> {
+        let snapshot =
+            serde_json::to_string(&loser).map_err(|err| Error::SerializationError(err.into()))?;
+
+        let mut emails = winner
+            .emails
+            .iter()
+            .map(|email| EmailPayload {
+                label: email.label.clone(),
+                value: email.value.clone(),
+                primary: email.is_primary,
+            })
+            .collect::<Vec<_>>();
+        for email in &loser.emails {
+            if !emails
+                .iter()
+                .any(|known| known.value.eq_ignore_ascii_case(&email.value))
+            {
+                emails.push(EmailPayload {
+                    label: email.label.clone(),
+                    value: email.value.clone(),
+                    primary: false,
+                });
+            }
+        }
+
+        let mut phones = winner
+            .phones
+            .iter()
+            .map(|phone| PhonePayload {
+                label: phone.label.clone(),
+                value: phone.value.clone(),
+                primary: phone.is_primary,
+            })
+            .collect::<Vec<_>>();
+        for phone in &loser.phones {
+            if !phones.iter().any(|known| known.value == phone.value) {
+                phones.push(PhonePayload {
+                    label: phone.label.clone(),
+                    value: phone.value.clone(),
+                    primary: false,
+                });
+            }
+        }
+
+        let to_payload = |address: &ContactAddress, primary: bool| AddressPayload {
+            label: address.label.clone(),
+            street: address.street.clone(),
+            locality: address.locality.clone(),
+            region: address.region.clone(),
+            postal_code: address.postal_code.clone(),
+            country: address.country.clone(),
+            primary,
+        };
+        let mut addresses = winner
+            .addresses
+            .iter()
+            .map(|address| to_payload(address, address.is_primary))
+            .collect::<Vec<_>>();
+        for address in &loser.addresses {
+            if !addresses.iter().any(|known| {
+                known.street.eq_ignore_ascii_case(&address.street)
+                    && known.locality.eq_ignore_ascii_case(&address.locality)
+                    && known.postal_code.eq_ignore_ascii_case(&address.postal_code)
+                    && known.country.eq_ignore_ascii_case(&address.country)
+            }) {
+                addresses.push(to_payload(address, false));
+            }
+        }
+
+        let mut dates = winner
+            .dates
+            .iter()
+            .map(DatePayload::from)
+            .collect::<Vec<_>>();
+        for date in &loser.dates {
+            // A contact keeps a single birthday and anniversary, the ones of the winner
+            let known = dates.iter().any(|known| match date.kind() {
+                DateKind::FollowUp => known.date == date.date && known.label == date.label,
+                _ => known.kind == date.kind,
+            });
+            if !known {
+                dates.push(DatePayload::from(date));
+            }
+        }
+
+        let mut fields = loser.fields.clone().into_iter().collect::<HashMap<_, _>>();
+        fields.extend(winner.fields.clone());
+
+        let mut payload = ContactPayload {
+            contact: NewUpdateContact {
+                lastname: pick(&winner.contact.lastname, &loser.contact.lastname),
+                firstname: pick(&winner.contact.firstname, &loser.contact.firstname),
+                ..NewUpdateContact::default()
+            },
+            emails: Some(emails),
+            phones: Some(phones),
+            addresses: Some(addresses),
+            dates: Some(dates),
+            fields: Some(fields),
+        };
+        payload.normalize();
+
+        let (winner_id_param, loser_id_param) = (winner.contact.id, loser.contact.id);
+
+        connection.transaction(|connection| {
+            let tag_ids = contact_tags::table
+                .filter(contact_tags::contact_id.eq(loser_id_param))
+                .select(contact_tags::tag_id)
+                .load::<i32>(connection)?;
+            diesel::insert_or_ignore_into(contact_tags::table)
+                .values(
+                    tag_ids
+                        .into_iter()
+                        .map(|tag_id| {
+                            (
+                                contact_tags::contact_id.eq(winner_id_param),
+                                contact_tags::tag_id.eq(tag_id),
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .execute(connection)?;
+            ContactShare::transfer(connection, loser_id_param, winner_id_param)?;
+            Interaction::transfer(connection, loser_id_param, winner_id_param)?;
+
+            // The earlier merges into the duplicate now belong to the history of the winner
+            diesel::update(
+                contact_merges::table.filter(contact_merges::winner_id.eq(loser_id_param)),
+            )
+            .set(contact_merges::winner_id.eq(winner_id_param))
+            .execute(connection)?;
+
+            diesel::insert_into(contact_merges::table)
+                .values(&NewContactMerge {
+                    winner_id: winner_id_param,
+                    loser_id: loser_id_param,
+                    merged_by: merged_by_param,
+                    snapshot,
+                    created_at: chrono::Utc::now().naive_utc(),
+                })
+                .execute(connection)?;
+
+            loser.delete(connection, merged_by_param)?;
+
+            payload.update(connection, winner_id_param, merged_by_param)
+        })
+    }
+
+    /// Delete the merge history of contacts
+    pub fn delete_for(connection: &mut SqliteConnection, ids: &[i32]) -> Result<usize, Error> {
+        use crate::schema::contact_merges::dsl::*;
+
+        diesel::delete(contact_merges.filter(winner_id.eq_any(ids))).execute(connection)
+    }
+}
@@ -1 +1,8 @@
 pub mod contact;
+pub mod date;
+pub mod details;
+pub mod interaction;
+pub mod merge;
+pub mod search;
+pub mod share;
+pub mod version;
@@ -0,0 +1,121 @@
+use super::contact::Contact;
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sql_types::{Double, Integer, Text};
+use serde::Serialize;
+
+/// Start and end of a match in the snippets built by SQLite, control characters that a contact
+/// field does not hold
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+/// A contact matching a search, with its rank (lower is better) and a highlighted snippet, an HTML
+/// text where the matches are wrapped in `<mark>`
+#[derive(Debug, Serialize, QueryableByName)]
+pub struct SearchResult {
+    #[diesel(embed)]
+    pub contact: Contact,
+    #[diesel(sql_type = Double)]
+    pub rank: f64,
+    #[diesel(sql_type = Text)]
+    pub snippet: String,
+}
+
+/// Build a full-text query where every term must match as a prefix
+pub fn match_expression(query: &str) -> Option<String> {
+    let terms = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Escape the text of a snippet as HTML and mark its matches
+fn highlight(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+
+    for c in snippet.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+
+    html
+}
+
+/// Search the contacts a user can access: its own, those of its organizations and those shared
+/// with it
+pub fn search(
+    connection: &mut SqliteConnection,
+    user_id: i32,
+    query: &str,
+    limit: i32,
+) -> Result<Vec<SearchResult>, Error> {
+    let Some(expression) = match_expression(query) else {
+        return Ok(Vec::new());
+    };
+
+    let results = diesel::sql_query(
+        "SELECT contacts.*, bm25(contacts_fts) AS rank, \
+            snippet(contacts_fts, -1, char(2), char(3), '…', 8) AS snippet \
+        FROM contacts_fts \
+        INNER JOIN contacts ON contacts.id = contacts_fts.rowid \
+        WHERE contacts_fts MATCH ? \
+            AND (contacts.user_id = ? \
+                OR contacts.organization_id IN \
+                    (SELECT organization_id FROM memberships WHERE user_id = ?) \
+                OR contacts.id IN (SELECT contact_id FROM contact_shares WHERE user_id = ?)) \
+        ORDER BY rank \
+        LIMIT ?",
+    )
+    .bind::<Text, _>(expression)
+    .bind::<Integer, _>(user_id)
+    .bind::<Integer, _>(user_id)
+    .bind::<Integer, _>(user_id)
+    .bind::<Integer, _>(limit)
+    .load::<SearchResult>(connection)?;
+
+    Ok(results
+        .into_iter()
+        .map(|result| SearchResult {
+            snippet: highlight(&result.snippet),
+            ..result
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_the_snippet_and_marks_the_matches() {
+        let snippet = format!("<b>Tom & \"Jerry\"</b> {}Smith{}", MATCH_START, MATCH_END);
+
+        assert_eq!(
+            highlight(&snippet),
+            "&lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt; <mark>Smith</mark>"
+        );
+    }
+
+    #[test]
+    fn quotes_every_search_term() {
+        assert_eq!(
+            match_expression("ali \"smi").as_deref(),
+            Some("\"ali\"* \"\"\"smi\"*")
+        );
+        assert_eq!(match_expression("  "), None);
+    }
+}
@@ -0,0 +1,204 @@
+use super::{contact::Contact, details::ContactDetails};
+use crate::schema::{contact_shares, contacts};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable)]
+#[diesel(table_name = contact_shares)]
+pub struct ContactShare {
+    pub contact_id: i32,
+    pub user_id: i32,
+    pub permission: String,
+    pub shared_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SharePayload {
+    pub user_id: i32,
+    pub permission: String,
+}
+
+/// A contact shared with the user, with the permission granted
+#[derive(Debug, Serialize)]
+pub struct SharedContact {
+    #[serde(flatten)]
+    pub contact: ContactDetails,
+    pub permission: String,
+    pub shared_by: i32,
+}
+
+/// The access granted on a shared contact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Permission {
+    Read,
+    Write,
+}
+
+impl Permission {
+    /// Get the permission as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+
+    /// Parse a permission from its database value
+    pub fn parse(permission: &str) -> Option<Self> {
+        match permission {
+            "read" => Some(Self::Read),
+            "write" => Some(Self::Write),
+            _ => None,
+        }
+    }
+}
+
+impl SharedContact {
+    /// Get the contacts shared with a user
+    pub fn all(connection: &mut SqliteConnection, user_id: i32) -> Result<Vec<Self>, Error> {
+        let (contacts, shares): (Vec<_>, Vec<_>) = ContactShare::shared_with(connection, user_id)?
+            .into_iter()
+            .unzip();
+
+        Ok(ContactDetails::load(connection, contacts)?
+            .into_iter()
+            .zip(shares)
+            .map(|(contact, share)| SharedContact {
+                contact,
+                permission: share.permission,
+                shared_by: share.shared_by,
+            })
+            .collect())
+    }
+}
+
+impl ContactShare {
+    /// Get the permission of the share
+    pub fn permission(&self) -> Permission {
+        Permission::parse(&self.permission).unwrap_or(Permission::Read)
+    }
+
+    /// Get the shares of a contact
+    pub fn all_for_contact(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+    ) -> Result<Vec<Self>, Error> {
+        use crate::schema::contact_shares::dsl::*;
+
+        contact_shares
+            .filter(contact_id.eq(contact_id_param))
+            .order(created_at.asc())
+            .load::<ContactShare>(connection)
+    }
+
+    /// Get the contacts shared with a user, with their share
+    pub fn shared_with(
+        connection: &mut SqliteConnection,
+        user_id_param: i32,
+    ) -> Result<Vec<(Contact, Self)>, Error> {
+        contacts::table
+            .inner_join(contact_shares::table)
+            .filter(contact_shares::user_id.eq(user_id_param))
+            .order(contacts::id.asc())
+            .load::<(Contact, ContactShare)>(connection)
+    }
+
+    /// Get the permission of a user on a contact, if it is shared with them
+    pub fn permission_of(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+        user_id_param: i32,
+    ) -> Result<Option<Permission>, Error> {
+        use crate::schema::contact_shares::dsl::*;
+
+        let share = contact_shares
+            .find((contact_id_param, user_id_param))
+            .first::<ContactShare>(connection)
+            .optional()?;
+
+        Ok(share.map(|share| share.permission()))
+    }
+
+    /// Share a contact with a user, or change the permission of an existing share
+    pub fn share(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+        user_id_param: i32,
+        permission_param: Permission,
+        shared_by_param: i32,
+    ) -> Result<Self, Error> {
+        use crate::schema::contact_shares::dsl::*;
+
+        connection.transaction(|connection| {
+            diesel::insert_into(contact_shares)
+                .values(&ContactShare {
+                    contact_id: contact_id_param,
+                    user_id: user_id_param,
+                    permission: permission_param.as_str().to_string(),
+                    shared_by: shared_by_param,
+                    created_at: chrono::Utc::now().naive_utc(),
+                })
+                .on_conflict((contact_id, user_id))
+                .do_update()
+                .set(permission.eq(permission_param.as_str()))
+                .execute(connection)?;
+
+            contact_shares
+                .find((contact_id_param, user_id_param))
+                .first::<ContactShare>(connection)
+        })
+    }
+
+    /// Stop sharing a contact with a user
+    pub fn unshare(
+        connection: &mut SqliteConnection,
+        contact_id_param: i32,
+        user_id_param: i32,
+    ) -> Result<usize, Error> {
+        use crate::schema::contact_shares::dsl::*;
+
+        let deleted = diesel::delete(contact_shares.find((contact_id_param, user_id_param)))
+            .execute(connection)?;
+
+        if deleted == 0 {
+            return Err(Error::NotFound);
+        }
+
+        Ok(deleted)
+    }
+
+    /// Move the shares of a contact to another one, keeping the existing shares of the latter
+    pub fn transfer(connection: &mut SqliteConnection, from: i32, to: i32) -> Result<usize, Error> {
+        let shares = ContactShare::all_for_contact(connection, from)?
+            .into_iter()
+            .map(|share| ContactShare {
+                contact_id: to,
+                ..share
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_or_ignore_into(contact_shares::table)
+            .values(&shares)
+            .execute(connection)
+    }
+
+    /// Delete the shares of contacts
+    pub fn delete_for(connection: &mut SqliteConnection, ids: &[i32]) -> Result<usize, Error> {
+        use crate::schema::contact_shares::dsl::*;
+
+        diesel::delete(contact_shares.filter(contact_id.eq_any(ids))).execute(connection)
+    }
+
+    /// Delete the shares with users
+    pub fn delete_for_users(
+        connection: &mut SqliteConnection,
+        ids: &[i32],
+    ) -> Result<usize, Error> {
+        use crate::schema::contact_shares::dsl::*;
+
+        diesel::delete(contact_shares.filter(user_id.eq_any(ids))).execute(connection)
+    }
+}
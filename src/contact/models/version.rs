@@ -0,0 +1,450 @@
+use super::{
+    contact::{Contact, NewUpdateContact, Owner},
+    date::DatePayload,
+    details::{AddressPayload, ContactDetails, ContactPayload, EmailPayload, PhonePayload},
+    interaction::Interaction,
+    share::ContactShare,
+};
+use crate::{
+    field::models::field::CustomField,
+    reminder::models::notification::Notification,
+    schema::{
+        contact_shares, contact_tags, contact_versions, contacts, interactions, notifications, tags,
+    },
+    utils::{image::image_key, storage::STORAGE},
+};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct ContactVersion {
+    pub id: i32,
+    pub contact_id: i32,
+    pub version: i32,
+    pub action: String,
+    #[serde(skip)]
+    pub user_id: Option<i32>,
+    #[serde(skip)]
+    pub organization_id: Option<i32>,
+    pub changed_by: i32,
+    #[serde(serialize_with = "serialize_snapshot")]
+    pub snapshot: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = contact_versions)]
+struct NewContactVersion {
+    contact_id: i32,
+    version: i32,
+    action: String,
+    user_id: Option<i32>,
+    organization_id: Option<i32>,
+    changed_by: i32,
+    snapshot: String,
+    created_at: NaiveDateTime,
+}
+
+/// A contact re-inserted with its former id
+#[derive(Insertable)]
+#[diesel(table_name = contacts)]
+struct RestoredContact {
+    id: i32,
+    user_id: Option<i32>,
+    organization_id: Option<i32>,
+    photo: Option<String>,
+    version: i32,
+    #[diesel(embed)]
+    fields: NewUpdateContact,
+}
+
+/// The state of a contact as written in a snapshot
+#[derive(Deserialize)]
+struct Snapshot {
+    #[serde(flatten)]
+    contact: NewUpdateContact,
+    #[serde(default)]
+    photo: Option<String>,
+    #[serde(default)]
+    version: i32,
+    emails: Vec<EmailPayload>,
+    phones: Vec<PhonePayload>,
+    addresses: Vec<AddressPayload>,
+    #[serde(default)]
+    dates: Vec<DatePayload>,
+    #[serde(default)]
+    fields: HashMap<String, Value>,
+    #[serde(default)]
+    relations: Relations,
+}
+
+/// The rows linked to a contact which its deletion removes, kept in the snapshot of the deletion
+/// so that a restoration brings them back
+#[derive(Default, Serialize, Deserialize)]
+struct Relations {
+    tags: Vec<i32>,
+    interactions: Vec<Interaction>,
+    shares: Vec<ContactShare>,
+    notifications: Vec<Notification>,
+}
+
+impl Relations {
+    /// Load the rows linked to a contact
+    fn load(connection: &mut SqliteConnection, contact_id: i32) -> Result<Self, Error> {
+        Ok(Relations {
+            tags: contact_tags::table
+                .filter(contact_tags::contact_id.eq(contact_id))
+                .select(contact_tags::tag_id)
+                .load::<i32>(connection)?,
+            interactions: interactions::table
+                .filter(interactions::contact_id.eq(contact_id))
+                .load::<Interaction>(connection)?,
+            shares: contact_shares::table
+                .filter(contact_shares::contact_id.eq(contact_id))
+                .load::<ContactShare>(connection)?,
+            notifications: notifications::table
+                .filter(notifications::contact_id.eq(contact_id))
+                .load::<Notification>(connection)?,
+        })
+    }
+
+    /// Insert the rows again for the restored contact, the tags deleted since then are left out
+    fn restore(self, connection: &mut SqliteConnection, contact_id: i32) -> Result<(), Error> {
+        let tag_ids = tags::table
+            .filter(tags::id.eq_any(&self.tags))
+            .select(tags::id)
+            .load::<i32>(connection)?;
+        diesel::insert_or_ignore_into(contact_tags::table)
+            .values(
+                tag_ids
+                    .into_iter()
+                    .map(|tag_id| {
+                        (
+                            contact_tags::contact_id.eq(contact_id),
+                            contact_tags::tag_id.eq(tag_id),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(connection)?;
+        diesel::insert_or_ignore_into(interactions::table)
+            .values(&self.interactions)
+            .execute(connection)?;
+        diesel::insert_or_ignore_into(contact_shares::table)
+            .values(&self.shares)
+            .execute(connection)?;
+        diesel::insert_or_ignore_into(notifications::table)
+            .values(&self.notifications)
+            .execute(connection)?;
+
+        Ok(())
+    }
+}
+
+/// The changes recorded in the history of a contact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Create,
+    Update,
+    Delete,
+    Restore,
+}
+
+impl Action {
+    /// Get the action as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Restore => "restore",
+        }
+    }
+}
+
+/// Write the stored JSON snapshot as a JSON value, without the relations kept for a restoration
+fn serialize_snapshot<S: Serializer>(snapshot: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut value = serde_json::from_str::<serde_json::Value>(snapshot).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        object.remove("relations");
+    }
+
+    value.serialize(serializer)
+}
+
+impl ContactVersion {
+    /// Get the owner of the contact at the time of the version
+    pub fn owner(&self) -> Owner {
+        match (self.user_id, self.organization_id) {
+            (_, Some(organization_id)) => Owner::Organization(organization_id),
+            (Some(user_id), None) => Owner::User(user_id),
+            (None, None) => unreachable!("a version always has an owner"),
+        }
+    }
+
+    /// Record the state of a contact after a change, or before its deletion
+    pub fn record(
+        connection: &mut SqliteConnection,
+        details: &ContactDetails,
+        action_param: Action,
+        changed_by_param: i32,
+    ) -> Result<Self, Error> {
+        use crate::schema::contact_versions::dsl::*;
+
+        let mut snapshot_param =
+            serde_json::to_value(details).map_err(|err| Error::SerializationError(err.into()))?;
+
+        connection.transaction(|connection| {
+            if action_param == Action::Delete {
+                let relations = Relations::load(connection, details.contact.id)?;
+                snapshot_param["relations"] = serde_json::to_value(relations)
+                    .map_err(|err| Error::SerializationError(err.into()))?;
+            }
+
+            let last = contact_versions
+                .filter(contact_id.eq(details.contact.id))
+                .select(diesel::dsl::max(version))
+                .first::<Option<i32>>(connection)?;
+
+            diesel::insert_into(contact_versions)
+                .values(&NewContactVersion {
+                    contact_id: details.contact.id,
+                    version: last.unwrap_or(0) + 1,
+                    action: action_param.as_str().to_string(),
+                    user_id: details.contact.user_id,
+                    organization_id: details.contact.organization_id,
+                    changed_by: changed_by_param,
+                    snapshot: snapshot_param.to_string(),
+                    created_at: chrono::Utc::now().naive_utc(),
+                })
+                .execute(connection)?;
+
+            contact_versions
+                .order(id.desc())
+                .first::<ContactVersion>(connection)
+        })
+    }
+
+    /// Get the history of a contact of an owner, the latest version first
+    pub fn all_for_contact(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        contact_id_param: i32,
+    ) -> Result<Vec<Self>, Error> {
+        let query = contact_versions::table
+            .filter(contact_versions::contact_id.eq(contact_id_param))
+            .order(contact_versions::version.desc())
+            .into_boxed();
+
+        let query = match owner {
+            Owner::User(user) => query.filter(contact_versions::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(contact_versions::organization_id.eq(organization))
+            }
+        };
+
+        query.load::<ContactVersion>(connection)
+    }
+
+    /// Find a version of a contact of an owner
+    pub fn find_owned(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        contact_id_param: i32,
+        version_param: i32,
+    ) -> Result<Self, Error> {
+        use crate::schema::contact_versions::dsl::*;
+
+        let found = contact_versions
+            .filter(contact_id.eq(contact_id_param))
+            .filter(version.eq(version_param))
+            .first::<ContactVersion>(connection)?;
+
+        if found.owner() != owner {
+            return Err(Error::NotFound);
+        }
+
+        Ok(found)
+    }
+
+    /// Get the last version of the deleted contacts of an owner
+    pub fn all_deleted(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+    ) -> Result<Vec<Self>, Error> {
+        let query = contact_versions::table
+            .filter(contact_versions::action.eq(Action::Delete.as_str()))
+            .filter(diesel::dsl::not(
+                contact_versions::contact_id.eq_any(contacts::table.select(contacts::id)),
+            ))
+            .order(contact_versions::id.desc())
+            .into_boxed();
+
+        let query = match owner {
+            Owner::User(user) => query.filter(contact_versions::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(contact_versions::organization_id.eq(organization))
+            }
+        };
+
+        let mut seen = HashSet::new();
+
+        Ok(query
+            .load::<ContactVersion>(connection)?
+            .into_iter()
+            .filter(|deleted| seen.insert(deleted.contact_id))
+            .collect())
+    }
+
+    /// Bring a contact back to the state of this version, with its photo if it is still stored,
+    /// the contact is inserted again with its former id when it has been deleted
+    pub fn restore(
+        &self,
+        connection: &mut SqliteConnection,
+        changed_by_param: i32,
+    ) -> Result<ContactDetails, Error> {
+        let snapshot = serde_json::from_str::<Snapshot>(&self.snapshot)
+            .map_err(|err| Error::DeserializationError(err.into()))?;
+        let photo = snapshot
+            .photo
+            .filter(|photo| STORAGE.get(&image_key(photo)).is_ok());
+
+        connection.transaction(|connection| {
+            if Contact::find(connection, self.contact_id)
+                .optional()?
+                .is_none()
+            {
+                // The versions go on from the deleted contact, so that its former ETags no longer
+                // match
+                let last = contact_versions::table
+                    .filter(contact_versions::contact_id.eq(self.contact_id))
+                    .order(contact_versions::version.desc())
+                    .select(contact_versions::snapshot)
+                    .first::<String>(connection)?;
+                let last = serde_json::from_str::<Snapshot>(&last).ok();
+                let version = last
+                    .as_ref()
+                    .map_or(snapshot.version, |last| last.version.max(snapshot.version));
+
+                diesel::insert_into(contacts::table)
+                    .values(&RestoredContact {
+                        id: self.contact_id,
+                        user_id: self.user_id,
+                        organization_id: self.organization_id,
+                        photo,
+                        version,
+                        fields: snapshot.contact.clone(),
+                    })
+                    .execute(connection)?;
+
+                // The tags, timeline, shares and reminders removed with the contact come back
+                if let Some(last) = last {
+                    last.relations.restore(connection, self.contact_id)?;
+                }
+            } else {
+                diesel::update(contacts::table.find(self.contact_id))
+                    .set(contacts::photo.eq(photo))
+                    .execute(connection)?;
+            }
+            CustomField::delete_values_for(connection, &[self.contact_id])?;
+
+            let details = ContactPayload {
+                contact: snapshot.contact,
+                emails: Some(snapshot.emails),
+                phones: Some(snapshot.phones),
+                addresses: Some(snapshot.addresses),
+                dates: Some(snapshot.dates),
+                fields: Some(snapshot.fields),
+            }
+            .save(connection, self.contact_id)?;
+
+            ContactVersion::record(connection, &details, Action::Restore, changed_by_param)?;
+
+            Ok(details)
+        })
+    }
+
+    /// Get the id of the last version recorded for the contacts of an owner
+    pub fn last_id(connection: &mut SqliteConnection, owner: Owner) -> Result<i32, Error> {
+        let query = contact_versions::table
+            .select(diesel::dsl::max(contact_versions::id))
+            .into_boxed();
+
+        let query = match owner {
+            Owner::User(user) => query.filter(contact_versions::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(contact_versions::organization_id.eq(organization))
+            }
+        };
+
+        Ok(query.first::<Option<i32>>(connection)?.unwrap_or(0))
+    }
+
+    /// Get the contacts of an owner which changed after a version, identified by its id
+    pub fn changed_since(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        after: i32,
+    ) -> Result<Vec<i32>, Error> {
+        let query = contact_versions::table
+            .filter(contact_versions::id.gt(after))
+            .select(contact_versions::contact_id)
+            .distinct()
+            .into_boxed();
+
+        let query = match owner {
+            Owner::User(user) => query.filter(contact_versions::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(contact_versions::organization_id.eq(organization))
+            }
+        };
+
+        query.load::<i32>(connection)
+    }
+
+    /// Get the storage keys of the photos the contacts of an owner ever had
+    pub fn photos_for_owner(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+    ) -> Result<HashSet<String>, Error> {
+        let query = contact_versions::table
+            .select(contact_versions::snapshot)
+            .into_boxed();
+
+        let query = match owner {
+            Owner::User(user) => query.filter(contact_versions::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(contact_versions::organization_id.eq(organization))
+            }
+        };
+
+        Ok(query
+            .load::<String>(connection)?
+            .iter()
+            .filter_map(|snapshot| serde_json::from_str::<Snapshot>(snapshot).ok())
+            .filter_map(|snapshot| snapshot.photo)
+            .collect())
+    }
+
+    /// Delete the history of the contacts of an owner
+    pub fn delete_for_owner(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+    ) -> Result<usize, Error> {
+        use crate::schema::contact_versions::dsl::*;
+
+        match owner {
+            Owner::User(user) => {
+                diesel::delete(contact_versions.filter(user_id.eq(user))).execute(connection)
+            }
+            Owner::Organization(organization) => {
+                diesel::delete(contact_versions.filter(organization_id.eq(organization)))
+                    .execute(connection)
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    contact::models::{
+        contact::Contact,
+        details::ContactDetails,
+        version::{Action, ContactVersion},
+    },
+    schema::{contact_phones, contact_versions, contacts},
+};
+use diesel::prelude::*;
+use diesel::result::Error;
+use once_cell::sync::Lazy;
+use phonenumber::{country, Mode};
+use std::{collections::BTreeSet, env::var};
+use validator::ValidationError;
+
+/// The country of the numbers written without an international prefix, none when
+/// `PHONE_DEFAULT_REGION` is not set and the numbers must then start with their country code
+static DEFAULT_REGION: Lazy<Option<country::Id>> = Lazy::new(|| {
+    let region = var("PHONE_DEFAULT_REGION").ok()?;
+
+    match region.trim().to_ascii_uppercase().parse() {
+        Ok(region) => Some(region),
+        Err(_) => {
+            tracing::warn!("unknown PHONE_DEFAULT_REGION {}", region);
+            None
+        }
+    }
+});
+
+/// The formats in which the phone numbers are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    E164,
+    International,
+    National,
+}
+
+impl Format {
+    /// Parse a format from its name
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "e164" => Some(Self::E164),
+            "international" => Some(Self::International),
+            "national" => Some(Self::National),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a phone number and check that it is valid, a number without international prefix is
+/// read in the region and can not be parsed without one
+fn parse_in(region: Option<country::Id>, phone: &str) -> Option<phonenumber::PhoneNumber> {
+    phonenumber::parse(region, phone)
+        .ok()
+        .filter(|number| number.is_valid())
+}
+
+/// Parse a phone number in the default region and check that it is valid
+fn parse(phone: &str) -> Option<phonenumber::PhoneNumber> {
+    parse_in(*DEFAULT_REGION, phone)
+}
+
+/// Get a phone number in E.164, or as it is when it is not a valid number
+pub fn normalize(phone: &str) -> String {
+    match parse(phone) {
+        Some(number) => number.format().mode(Mode::E164).to_string(),
+        None => phone.trim().to_string(),
+    }
+}
+
+/// Render a phone number, the numbers stored before the normalization are left as they are
+pub fn format(phone: &str, format: Format) -> String {
+    let mode = match format {
+        Format::E164 => Mode::E164,
+        Format::International => Mode::International,
+        Format::National => Mode::National,
+    };
+
+    match parse(phone) {
+        Some(number) => number.format().mode(mode).to_string(),
+        None => phone.to_string(),
+    }
+}
+
+/// Check that a phone number can be stored in E.164, a number without international prefix is
+/// only accepted when it is valid in the region
+fn is_plausible(phone: &str, region: Option<country::Id>) -> bool {
+    parse_in(region, phone).is_some()
+}
+
+/// Check that a phone number is valid, an empty one meaning no phone number
+pub fn validate_phone(phone: &str) -> Result<(), ValidationError> {
+    if phone.is_empty() || is_plausible(phone, *DEFAULT_REGION) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("phone"))
+    }
+}
+
+/// Write the numbers stored before the normalization in E.164, the contacts which changed get a
+/// new version so that their cards are fetched again, return the number of these contacts
+pub fn backfill(connection: &mut SqliteConnection) -> Result<usize, Error> {
+    // The write lock is taken first as the numbers are read before being written
+    connection.immediate_transaction(|connection| {
+        let mut changed = BTreeSet::new();
+
+        let stored = contacts::table
+            .select((contacts::id, contacts::phone))
+            .load::<(i32, String)>(connection)?;
+        for (id, phone) in stored {
+            let normalized = normalize(&phone);
+            if normalized != phone {
+                diesel::update(contacts::table.find(id))
+                    .set(contacts::phone.eq(normalized))
+                    .execute(connection)?;
+                changed.insert(id);
+            }
+        }
+
+        let stored = contact_phones::table
+            .select((
+                contact_phones::id,
+                contact_phones::contact_id,
+                contact_phones::value,
+            ))
+            .load::<(i32, i32, String)>(connection)?;
+        for (id, contact_id, phone) in stored {
+            let normalized = normalize(&phone);
+            if normalized != phone {
+                diesel::update(contact_phones::table.find(id))
+                    .set(contact_phones::value.eq(normalized))
+                    .execute(connection)?;
+                changed.insert(contact_id);
+            }
+        }
+
+        let changed = changed.into_iter().collect::<Vec<_>>();
+        diesel::update(contacts::table.filter(contacts::id.eq_any(&changed)))
+            .set(contacts::version.eq(contacts::version + 1))
+            .execute(connection)?;
+
+        // The change is recorded for the owner, or the last author of a contact of an organization
+        let changed_contacts = contacts::table
+            .filter(contacts::id.eq_any(&changed))
+            .load::<Contact>(connection)?;
+        for details in ContactDetails::load(connection, changed_contacts)? {
+            let author = match details.contact.user_id {
+                Some(user_id) => Some(user_id),
+                None => contact_versions::table
+                    .filter(contact_versions::contact_id.eq(details.contact.id))
+                    .order(contact_versions::version.desc())
+                    .select(contact_versions::changed_by)
+                    .first::<i32>(connection)
+                    .optional()?,
+            };
+            if let Some(author) = author {
+                ContactVersion::record(connection, &details, Action::Update, author)?;
+            }
+        }
+
+        Ok(changed.len())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_the_international_numbers_in_e164() {
+        assert_eq!(normalize(" +33 6 12 34 56 78 "), "+33612345678");
+        assert!(validate_phone("+33 6 12 34 56 78").is_ok());
+    }
+
+    #[test]
+    fn reads_the_national_numbers_in_the_region() {
+        assert!(is_plausible("06 12 34 56 78", Some(country::Id::FR)));
+        assert!(is_plausible("(201) 555-0123", Some(country::Id::US)));
+        assert!(is_plausible("+33 6 12 34 56 78", Some(country::Id::US)));
+    }
+
+    #[test]
+    fn rejects_the_national_numbers_without_region() {
+        assert!(!is_plausible("06 12 34 56 78", None));
+        assert!(!is_plausible("(201) 555-0123", None));
+        assert!(is_plausible("+33 6 12 34 56 78", None));
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_phone_number() {
+        assert!(validate_phone("call me").is_err());
+        assert!(validate_phone("12").is_err());
+        assert!(validate_phone("+1 234").is_err());
+        assert!(validate_phone("").is_ok());
+    }
+}
@@ -0,0 +1,52 @@
+use super::models::{contact::Contact, details::ContactDetails};
+use crate::utils::{
+    error::ApiError,
+    image::{image_key, thumbnail_key, ProcessedImage},
+    storage::STORAGE,
+};
+use diesel::SqliteConnection;
+
+/// Store an image as the photo of a contact and record the new version, the same image as the
+/// stored one leaves the contact unchanged
+pub fn store(
+    connection: &mut SqliteConnection,
+    contact: Contact,
+    processed: &ProcessedImage,
+    changed_by: i32,
+) -> Result<ContactDetails, ApiError> {
+    let unchanged = contact
+        .photo
+        .as_deref()
+        .and_then(|photo| STORAGE.get(&image_key(photo)).ok())
+        .is_some_and(|image| image == processed.image);
+    if unchanged {
+        return ContactDetails::load_one(connection, contact).map_err(ApiError::from);
+    }
+
+    let photo = format!(
+        "photos/{}-{}",
+        contact.id,
+        chrono::Utc::now().timestamp_millis()
+    );
+    let stored = STORAGE
+        .put(&image_key(&photo), &processed.image)
+        .and_then(|_| STORAGE.put(&thumbnail_key(&photo), &processed.thumbnail))
+        .map_err(|_| ApiError::InternalServerError)
+        .and_then(|_| {
+            ContactDetails::set_photo(connection, contact.id, Some(photo.clone()), changed_by)
+                .map_err(ApiError::from)
+        });
+
+    // The previous photo stays for the versions referencing it, it is deleted with the history
+    if stored.is_err() {
+        delete(&photo);
+    }
+
+    stored
+}
+
+/// Delete the stored files of a photo, a missing file is ignored
+pub fn delete(photo: &str) {
+    let _ = STORAGE.delete(&image_key(photo));
+    let _ = STORAGE.delete(&thumbnail_key(photo));
+}
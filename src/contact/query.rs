@@ -0,0 +1,846 @@
+use super::models::{
+    contact::{Contact, Owner},
+    details::ContactDetails,
+};
+use crate::schema::{contact_tags, contacts};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use diesel::{
+    prelude::*,
+    result::Error,
+    sql_types::{Bool, Nullable, Text},
+    sqlite::Sqlite,
+};
+use serde_json::{json, Value as Json};
+
+/// Number of contacts in a page when a cursor is given without a limit
+pub const DEFAULT_LIMIT: i64 = 50;
+
+/// Largest number of contacts in a page
+pub const MAX_LIMIT: i64 = 500;
+
+/// The keys of a contact which can be selected with `?fields=`
+const FIELDS: [&str; 15] = [
+    "id",
+    "user_id",
+    "organization_id",
+    "lastname",
+    "firstname",
+    "email",
+    "phone",
+    "last_contacted_at",
+    "photo",
+    "version",
+    "emails",
+    "phones",
+    "addresses",
+    "dates",
+    "fields",
+];
+
+/// Format of the timestamps in filters and cursors
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+type Condition = Box<dyn BoxableExpression<contacts::table, Sqlite, SqlType = Nullable<Bool>>>;
+type TextColumn = Box<dyn BoxableExpression<contacts::table, Sqlite, SqlType = Text>>;
+
+/// A column of the contacts which can be filtered and sorted on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Id,
+    Lastname,
+    Firstname,
+    Email,
+    Phone,
+    LastContactedAt,
+}
+
+/// A value compared to a column
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i32),
+    Text(String),
+    Timestamp(NaiveDateTime),
+    /// A whole day, only in filters on timestamps
+    Day(NaiveDate),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Contains,
+    StartsWith,
+    In,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+/// A condition on a column, written `column:operator:value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub column: Column,
+    pub operator: Operator,
+    pub values: Vec<Value>,
+}
+
+/// A sort key, written `column`, `column:asc` or `column:desc`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub column: Column,
+    pub descending: bool,
+}
+
+/// A listing of the contacts of an owner
+#[derive(Debug, Default)]
+pub struct ContactQuery {
+    pub filters: Vec<Filter>,
+    pub sort: Vec<Sort>,
+    /// Only the contacts with this tag
+    pub tag: Option<i32>,
+    /// Only the contacts among these
+    pub ids: Option<Vec<i32>>,
+    /// The values of the sort keys of the last contact of the previous page
+    pub after: Option<Vec<Value>>,
+    pub limit: Option<i64>,
+}
+
+/// A page of contacts, with the cursor of the next one when there are more
+pub struct Page {
+    pub contacts: Vec<Contact>,
+    pub next_cursor: Option<String>,
+}
+
+/// The keys of a contact kept in the response, `id` is always kept
+#[derive(Debug)]
+pub struct Fields(Vec<String>);
+
+impl Column {
+    /// Parse a column from its name
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Self::Id),
+            "lastname" => Some(Self::Lastname),
+            "firstname" => Some(Self::Firstname),
+            "email" => Some(Self::Email),
+            "phone" => Some(Self::Phone),
+            "last_contacted_at" => Some(Self::LastContactedAt),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Lastname => "lastname",
+            Self::Firstname => "firstname",
+            Self::Email => "email",
+            Self::Phone => "phone",
+            Self::LastContactedAt => "last_contacted_at",
+        }
+    }
+
+    /// Get the expression of a text column
+    fn text(&self) -> Option<TextColumn> {
+        match self {
+            Self::Lastname => Some(Box::new(contacts::lastname)),
+            Self::Firstname => Some(Box::new(contacts::firstname)),
+            Self::Email => Some(Box::new(contacts::email)),
+            Self::Phone => Some(Box::new(contacts::phone)),
+            Self::Id | Self::LastContactedAt => None,
+        }
+    }
+
+    /// Parse a value of a filter on the column, a timestamp can be a whole day or `null`
+    fn value(&self, raw: &str) -> Option<Value> {
+        match self {
+            Self::Id => raw.parse().ok().map(Value::Int),
+            Self::LastContactedAt if raw == "null" => Some(Value::Null),
+            Self::LastContactedAt => NaiveDateTime::parse_from_str(raw, TIMESTAMP_FORMAT)
+                .map(Value::Timestamp)
+                .or_else(|_| NaiveDate::parse_from_str(raw, "%Y-%m-%d").map(Value::Day))
+                .ok(),
+            _ => Some(Value::Text(raw.to_string())),
+        }
+    }
+
+    /// Get the value of the column for a contact
+    fn value_of(&self, contact: &Contact) -> Value {
+        match self {
+            Self::Id => Value::Int(contact.id),
+            Self::Lastname => Value::Text(contact.lastname.clone()),
+            Self::Firstname => Value::Text(contact.firstname.clone()),
+            Self::Email => Value::Text(contact.email.clone()),
+            Self::Phone => Value::Text(contact.phone.clone()),
+            Self::LastContactedAt => contact
+                .last_contacted_at
+                .map_or(Value::Null, Value::Timestamp),
+        }
+    }
+
+    /// Read a value of the column written in a cursor
+    fn read_cursor_value(&self, value: &Json) -> Option<Value> {
+        match (self, value) {
+            (Self::Id, _) => value
+                .as_i64()
+                .and_then(|id| i32::try_from(id).ok())
+                .map(Value::Int),
+            (Self::LastContactedAt, Json::Null) => Some(Value::Null),
+            (Self::LastContactedAt, Json::String(timestamp)) => {
+                NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+                    .map(Value::Timestamp)
+                    .ok()
+            }
+            (Self::LastContactedAt, _) => None,
+            (_, Json::String(text)) => Some(Value::Text(text.clone())),
+            _ => None,
+        }
+    }
+
+    /// Check if the operator can be applied on the column
+    fn accepts(&self, operator: Operator) -> bool {
+        match operator {
+            Operator::Eq | Operator::Ne => true,
+            Operator::Contains | Operator::StartsWith => self.text().is_some(),
+            Operator::In => *self != Self::LastContactedAt,
+            Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte => {
+                matches!(self, Self::Id | Self::LastContactedAt)
+            }
+        }
+    }
+
+    /// Compare the column to a value
+    fn equal(&self, value: &Value) -> Condition {
+        Filter {
+            column: *self,
+            operator: Operator::Eq,
+            values: vec![value.clone()],
+        }
+        .condition()
+    }
+
+    /// Select the rows which come after a value in the order of the column, none when the
+    /// value is the last one, the nulls come first in ascending order
+    fn after(&self, value: &Value, descending: bool) -> Option<Condition> {
+        let operator = if descending {
+            Operator::Lt
+        } else {
+            Operator::Gt
+        };
+
+        match (self, value) {
+            (Self::LastContactedAt, Value::Null) if descending => None,
+            (Self::LastContactedAt, Value::Null) => Some(Box::new(
+                contacts::last_contacted_at.is_not_null().nullable(),
+            )),
+            (Self::LastContactedAt, Value::Timestamp(timestamp)) if descending => Some(Box::new(
+                contacts::last_contacted_at
+                    .lt(*timestamp)
+                    .or(contacts::last_contacted_at.is_null()),
+            )),
+            (_, value) => Some(
+                Filter {
+                    column: *self,
+                    operator,
+                    values: vec![value.clone()],
+                }
+                .condition(),
+            ),
+        }
+    }
+}
+
+impl Value {
+    /// Write the value in a cursor
+    fn to_json(&self) -> Json {
+        match self {
+            Self::Null => Json::Null,
+            Self::Int(value) => json!(value),
+            Self::Text(value) => json!(value),
+            Self::Timestamp(value) => json!(value.format(TIMESTAMP_FORMAT).to_string()),
+            Self::Day(value) => json!(value.to_string()),
+        }
+    }
+}
+
+impl Operator {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "eq" => Some(Self::Eq),
+            "ne" => Some(Self::Ne),
+            "contains" => Some(Self::Contains),
+            "starts_with" => Some(Self::StartsWith),
+            "in" => Some(Self::In),
+            "gt" => Some(Self::Gt),
+            "gte" => Some(Self::Gte),
+            "lt" => Some(Self::Lt),
+            "lte" => Some(Self::Lte),
+            _ => None,
+        }
+    }
+}
+
+/// Split an expression on a separator which is not escaped with a backslash
+fn split(input: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (index, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == separator => {
+                parts.push(&input[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+
+    parts
+}
+
+/// Remove the backslashes escaping the separators of a value
+fn unescape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => output.extend(chars.next()),
+            c => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// Escape the wildcards of a `LIKE` pattern
+fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+impl Filter {
+    /// Parse a condition, the values of `in` are separated by `|` and a `,` or `|` in a value is
+    /// escaped with a backslash
+    fn parse(input: &str) -> Option<Self> {
+        let mut parts = input.splitn(3, ':');
+        let column = Column::parse(parts.next()?.trim())?;
+        let operator = Operator::parse(parts.next()?.trim())?;
+        let raw = parts.next()?;
+
+        if !column.accepts(operator) {
+            return None;
+        }
+
+        let values = match operator {
+            Operator::In => split(raw, '|'),
+            _ => vec![raw],
+        }
+        .into_iter()
+        .map(|value| column.value(&unescape(value)))
+        .collect::<Option<Vec<_>>>()?;
+
+        let ranged = matches!(
+            operator,
+            Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte
+        );
+        if ranged && values.contains(&Value::Null) {
+            return None;
+        }
+
+        Some(Filter {
+            column,
+            operator,
+            values,
+        })
+    }
+
+    /// Translate the condition into an expression on the contacts
+    fn condition(&self) -> Condition {
+        match self.column {
+            Column::Id => self.id_condition(),
+            Column::LastContactedAt => self.timestamp_condition(),
+            column => self.text_condition(column.text().expect("a text column")),
+        }
+    }
+
+    fn text_condition(&self, column: TextColumn) -> Condition {
+        let mut texts = self.values.iter().filter_map(|value| match value {
+            Value::Text(text) => Some(text.clone()),
+            _ => None,
+        });
+
+        match self.operator {
+            Operator::In => Box::new(column.eq_any(texts.collect::<Vec<_>>()).nullable()),
+            operator => {
+                let text = texts.next().unwrap_or_default();
+                match operator {
+                    Operator::Ne => Box::new(column.ne(text).nullable()),
+                    Operator::Gt => Box::new(column.gt(text).nullable()),
+                    Operator::Gte => Box::new(column.ge(text).nullable()),
+                    Operator::Lt => Box::new(column.lt(text).nullable()),
+                    Operator::Lte => Box::new(column.le(text).nullable()),
+                    Operator::Contains => Box::new(
+                        column
+                            .like(format!("%{}%", escape_like(&text)))
+                            .escape('\\')
+                            .nullable(),
+                    ),
+                    Operator::StartsWith => Box::new(
+                        column
+                            .like(format!("{}%", escape_like(&text)))
+                            .escape('\\')
+                            .nullable(),
+                    ),
+                    _ => Box::new(column.eq(text).nullable()),
+                }
+            }
+        }
+    }
+
+    fn id_condition(&self) -> Condition {
+        let ids = self
+            .values
+            .iter()
+            .filter_map(|value| match value {
+                Value::Int(id) => Some(*id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let id = ids.first().copied().unwrap_or_default();
+
+        match self.operator {
+            Operator::Ne => Box::new(contacts::id.ne(id).nullable()),
+            Operator::In => Box::new(contacts::id.eq_any(ids).nullable()),
+            Operator::Gt => Box::new(contacts::id.gt(id).nullable()),
+            Operator::Gte => Box::new(contacts::id.ge(id).nullable()),
+            Operator::Lt => Box::new(contacts::id.lt(id).nullable()),
+            Operator::Lte => Box::new(contacts::id.le(id).nullable()),
+            _ => Box::new(contacts::id.eq(id).nullable()),
+        }
+    }
+
+    /// A whole day matches the timestamps from its start to the start of the next day
+    fn timestamp_condition(&self) -> Condition {
+        let column = contacts::last_contacted_at;
+
+        let (start, end) = match self.values.first() {
+            Some(Value::Timestamp(timestamp)) => (*timestamp, None),
+            Some(Value::Day(day)) => {
+                let start = day.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+                (start, Some(start + Duration::days(1)))
+            }
+            _ => {
+                return match self.operator {
+                    Operator::Ne => Box::new(column.is_not_null().nullable()),
+                    _ => Box::new(column.is_null().nullable()),
+                }
+            }
+        };
+
+        match (self.operator, end) {
+            (Operator::Ne, None) => Box::new(column.ne(start)),
+            (Operator::Ne, Some(end)) => Box::new(column.lt(start).or(column.ge(end))),
+            (Operator::Gt, None) => Box::new(column.gt(start)),
+            (Operator::Gt, Some(end)) => Box::new(column.ge(end)),
+            (Operator::Gte, _) => Box::new(column.ge(start)),
+            (Operator::Lt, _) => Box::new(column.lt(start)),
+            (Operator::Lte, None) => Box::new(column.le(start)),
+            (Operator::Lte, Some(end)) => Box::new(column.lt(end)),
+            (_, None) => Box::new(column.eq(start)),
+            (_, Some(end)) => Box::new(column.ge(start).and(column.lt(end))),
+        }
+    }
+}
+
+impl Sort {
+    /// Parse a sort key, the timestamps are sorted the most recent first by default
+    fn parse(input: &str) -> Option<Self> {
+        let (name, direction) = match input.split_once(':') {
+            Some((name, direction)) => (name, Some(direction)),
+            None => (input, None),
+        };
+        let column = Column::parse(name.trim())?;
+
+        let descending = match direction {
+            Some("asc") => false,
+            Some("desc") => true,
+            Some(_) => return None,
+            None => column == Column::LastContactedAt,
+        };
+
+        Some(Sort { column, descending })
+    }
+}
+
+impl ContactQuery {
+    /// Parse the filters, the sort keys and the cursor of a listing, the filters and the sort
+    /// keys are separated by `,`
+    pub fn parse(
+        filter: Option<&str>,
+        sort: Option<&str>,
+        cursor: Option<&str>,
+        limit: Option<i64>,
+    ) -> Option<Self> {
+        let filters = match filter.filter(|filter| !filter.trim().is_empty()) {
+            Some(filter) => split(filter, ',')
+                .into_iter()
+                .map(Filter::parse)
+                .collect::<Option<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let sort = match sort.filter(|sort| !sort.trim().is_empty()) {
+            Some(sort) => sort
+                .split(',')
+                .map(Sort::parse)
+                .collect::<Option<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+
+        let mut query = ContactQuery {
+            filters,
+            sort,
+            limit: limit.map(|limit| limit.clamp(1, MAX_LIMIT)),
+            ..Default::default()
+        };
+        if let Some(cursor) = cursor {
+            query.after = Some(query.decode_cursor(cursor)?);
+            query.limit = query.limit.or(Some(DEFAULT_LIMIT));
+        }
+
+        Some(query)
+    }
+
+    /// Get the sort keys, ending with the id so that the order is total
+    fn keys(&self) -> Vec<Sort> {
+        let mut keys = Vec::new();
+        for key in &self.sort {
+            keys.push(*key);
+            if key.column == Column::Id {
+                return keys;
+            }
+        }
+        keys.push(Sort {
+            column: Column::Id,
+            descending: false,
+        });
+
+        keys
+    }
+
+    /// Write the sort keys, a cursor is only valid with the sort keys it was written for
+    fn signature(&self) -> String {
+        self.keys()
+            .iter()
+            .map(|key| {
+                let direction = if key.descending { "desc" } else { "asc" };
+                format!("{}:{}", key.column.name(), direction)
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Write the cursor of the page after a contact
+    fn encode_cursor(&self, contact: &Contact) -> String {
+        let values = self
+            .keys()
+            .iter()
+            .map(|key| key.column.value_of(contact).to_json())
+            .collect::<Vec<_>>();
+        let cursor = json!({ "sort": self.signature(), "after": values });
+
+        URL_SAFE_NO_PAD.encode(cursor.to_string())
+    }
+
+    /// Read the values of the sort keys written in a cursor
+    fn decode_cursor(&self, cursor: &str) -> Option<Vec<Value>> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let cursor = serde_json::from_slice::<Json>(&bytes).ok()?;
+
+        if cursor.get("sort")?.as_str()? != self.signature() {
+            return None;
+        }
+        let values = cursor.get("after")?.as_array()?;
+        let keys = self.keys();
+        if values.len() != keys.len() {
+            return None;
+        }
+
+        keys.iter()
+            .zip(values)
+            .map(|(key, value)| key.column.read_cursor_value(value))
+            .collect()
+    }
+
+    /// Select the contacts after the last one of the previous page: those with the same values
+    /// for the first sort keys and a value after it for the next one
+    fn after_condition(&self, values: &[Value]) -> Option<Condition> {
+        let keys = self.keys();
+        let mut condition: Option<Condition> = None;
+
+        for (index, key) in keys.iter().enumerate() {
+            let Some(mut term) = key.column.after(&values[index], key.descending) else {
+                continue;
+            };
+            for (previous, value) in keys[..index].iter().zip(values) {
+                term = Box::new(previous.column.equal(value).and(term));
+            }
+            condition = Some(match condition {
+                Some(condition) => Box::new(condition.or(term)),
+                None => term,
+            });
+        }
+
+        condition
+    }
+
+    /// Load a page of the contacts of an owner
+    pub fn run(&self, connection: &mut SqliteConnection, owner: Owner) -> Result<Page, Error> {
+        let mut query = contacts::table.into_boxed();
+
+        query = match owner {
+            Owner::User(user) => query.filter(contacts::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(contacts::organization_id.eq(organization))
+            }
+        };
+        if let Some(tag) = self.tag {
+            query = query.filter(
+                contacts::id.eq_any(
+                    contact_tags::table
+                        .filter(contact_tags::tag_id.eq(tag))
+                        .select(contact_tags::contact_id),
+                ),
+            );
+        }
+        if let Some(ids) = &self.ids {
+            query = query.filter(contacts::id.eq_any(ids.clone()));
+        }
+        for filter in &self.filters {
+            query = query.filter(filter.condition());
+        }
+        if let Some(values) = &self.after {
+            match self.after_condition(values) {
+                Some(condition) => query = query.filter(condition),
+                None => {
+                    return Ok(Page {
+                        contacts: Vec::new(),
+                        next_cursor: None,
+                    })
+                }
+            }
+        }
+        for key in self.keys() {
+            query = match (key.column.text(), key.column, key.descending) {
+                (Some(column), _, false) => query.then_order_by(column.asc()),
+                (Some(column), _, true) => query.then_order_by(column.desc()),
+                (None, Column::LastContactedAt, false) => {
+                    query.then_order_by(contacts::last_contacted_at.asc())
+                }
+                (None, Column::LastContactedAt, true) => {
+                    query.then_order_by(contacts::last_contacted_at.desc())
+                }
+                (None, _, false) => query.then_order_by(contacts::id.asc()),
+                (None, _, true) => query.then_order_by(contacts::id.desc()),
+            };
+        }
+
+        let Some(limit) = self.limit else {
+            return Ok(Page {
+                contacts: query.load::<Contact>(connection)?,
+                next_cursor: None,
+            });
+        };
+
+        // One more contact tells if there is a next page
+        let mut contacts = query.limit(limit + 1).load::<Contact>(connection)?;
+        let next_cursor = if contacts.len() as i64 > limit {
+            contacts.truncate(limit as usize);
+            contacts.last().map(|contact| self.encode_cursor(contact))
+        } else {
+            None
+        };
+
+        Ok(Page {
+            contacts,
+            next_cursor,
+        })
+    }
+}
+
+impl Fields {
+    /// Parse the keys separated by `,`
+    pub fn parse(input: &str) -> Option<Self> {
+        let fields = input
+            .split(',')
+            .map(|field| field.trim().to_string())
+            .collect::<Vec<_>>();
+
+        if fields.iter().any(|field| !FIELDS.contains(&field.as_str())) {
+            return None;
+        }
+
+        Some(Fields(fields))
+    }
+
+    /// Keep only the selected keys of a contact
+    pub fn select(&self, contact: &ContactDetails) -> Json {
+        let Ok(Json::Object(mut object)) = serde_json::to_value(contact) else {
+            return Json::Null;
+        };
+        object.retain(|key, _| key == "id" || self.0.contains(key));
+
+        Json::Object(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(lastname: &str, last_contacted_at: Option<NaiveDateTime>) -> Contact {
+        Contact {
+            id: 7,
+            user_id: Some(1),
+            organization_id: None,
+            lastname: lastname.to_string(),
+            firstname: "Alice".to_string(),
+            email: String::new(),
+            phone: String::new(),
+            last_contacted_at,
+            photo: None,
+            version: 1,
+        }
+    }
+
+    fn query(sort: &str, cursor: Option<&str>) -> Option<ContactQuery> {
+        ContactQuery::parse(None, Some(sort), cursor, None)
+    }
+
+    #[test]
+    fn parses_the_filters() {
+        assert_eq!(
+            Filter::parse("lastname:starts_with:Do"),
+            Some(Filter {
+                column: Column::Lastname,
+                operator: Operator::StartsWith,
+                values: vec![Value::Text("Do".to_string())],
+            })
+        );
+        assert_eq!(
+            Filter::parse("id:in:1|2").map(|filter| filter.values),
+            Some(vec![Value::Int(1), Value::Int(2)])
+        );
+        assert_eq!(
+            Filter::parse("last_contacted_at:eq:2023-01-02").map(|filter| filter.values),
+            Some(vec![Value::Day(
+                NaiveDate::from_ymd_opt(2023, 1, 2).unwrap()
+            )])
+        );
+        assert_eq!(
+            Filter::parse("lastname:eq:a:b").map(|filter| filter.values),
+            Some(vec![Value::Text("a:b".to_string())])
+        );
+    }
+
+    #[test]
+    fn rejects_the_invalid_filters() {
+        assert!(Filter::parse("nickname:eq:Al").is_none());
+        assert!(Filter::parse("lastname:like:Al").is_none());
+        assert!(Filter::parse("lastname:gt:Al").is_none());
+        assert!(Filter::parse("last_contacted_at:contains:2023").is_none());
+        assert!(Filter::parse("last_contacted_at:gt:null").is_none());
+        assert!(Filter::parse("id:eq:one").is_none());
+        assert!(Filter::parse("lastname").is_none());
+    }
+
+    #[test]
+    fn splits_on_the_unescaped_separators() {
+        assert_eq!(split(r"a\,b,c,", ','), vec![r"a\,b", "c", ""]);
+        assert_eq!(split(r"x\|y|z", '|'), vec![r"x\|y", "z"]);
+        assert_eq!(unescape(r"a\,b\|c\\d"), r"a,b|c\d");
+
+        let query = ContactQuery::parse(
+            Some(r"lastname:in:O\,Brien|Smith,id:gt:3"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(query.filters.len(), 2);
+        assert_eq!(
+            query.filters[0].values,
+            vec![
+                Value::Text("O,Brien".to_string()),
+                Value::Text("Smith".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_the_like_wildcards() {
+        assert_eq!(escape_like(r"50%_a\b"), r"50\%\_a\\b");
+    }
+
+    #[test]
+    fn reads_back_a_cursor_on_several_columns() {
+        let contacted = NaiveDate::from_ymd_opt(2023, 3, 4)
+            .unwrap()
+            .and_hms_milli_opt(10, 20, 30, 400)
+            .unwrap();
+        let listing = query("last_contacted_at:desc,lastname", None).unwrap();
+
+        let cursor = listing.encode_cursor(&contact("Smith", Some(contacted)));
+        let next = query("last_contacted_at:desc,lastname", Some(&cursor)).unwrap();
+
+        assert_eq!(
+            next.after,
+            Some(vec![
+                Value::Timestamp(contacted),
+                Value::Text("Smith".to_string()),
+                Value::Int(7)
+            ])
+        );
+        assert_eq!(next.limit, Some(DEFAULT_LIMIT));
+    }
+
+    #[test]
+    fn reads_back_a_cursor_without_contact_date() {
+        let listing = query("last_contacted_at,lastname:desc", None).unwrap();
+
+        let cursor = listing.encode_cursor(&contact("O'Brien", None));
+        let next = query("last_contacted_at,lastname:desc", Some(&cursor)).unwrap();
+
+        assert_eq!(
+            next.after,
+            Some(vec![
+                Value::Null,
+                Value::Text("O'Brien".to_string()),
+                Value::Int(7)
+            ])
+        );
+        // The nulls come first in ascending order, so the contacted ones come after them
+        assert!(Column::LastContactedAt.after(&Value::Null, false).is_some());
+        assert!(Column::LastContactedAt.after(&Value::Null, true).is_none());
+    }
+
+    #[test]
+    fn rejects_a_cursor_of_other_sort_keys() {
+        let cursor = query("lastname", None)
+            .unwrap()
+            .encode_cursor(&contact("Smith", None));
+
+        assert!(query("firstname", Some(&cursor)).is_none());
+        assert!(query("lastname:desc", Some(&cursor)).is_none());
+        assert!(query("lastname", Some("not a cursor")).is_none());
+    }
+}
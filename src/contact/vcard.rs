@@ -0,0 +1,696 @@
+use super::models::{
+    contact::NewUpdateContact,
+    date::{DateKind, DatePayload},
+    details::{AddressPayload, ContactDetails, ContactPayload, EmailPayload, PhonePayload},
+};
+use crate::utils::{image::image_key, storage::STORAGE};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::NaiveDate;
+
+/// Longest line of a vCard, in octets, before it is folded
+const MAX_LINE_LENGTH: usize = 75;
+
+/// The supported vCard versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    V3,
+    V4,
+}
+
+impl Version {
+    /// Parse a version from its name (`3.0` or `4.0`)
+    pub fn parse(version: &str) -> Option<Self> {
+        match version {
+            "3.0" | "3" => Some(Self::V3),
+            "4.0" | "4" => Some(Self::V4),
+            _ => None,
+        }
+    }
+
+    /// Get the version as written in the cards
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::V3 => "3.0",
+            Self::V4 => "4.0",
+        }
+    }
+}
+
+/// A card converted into a contact, with the decoded image of its inline photo
+pub struct ParsedCard {
+    pub contact: ContactPayload,
+    pub photo: Option<Vec<u8>>,
+}
+
+/// A property of a card: `GROUP.NAME;PARAM=VALUE:value`
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    /// Get the lowercase types of the property, including the bare parameters of vCard 2.1
+    fn types(&self) -> Vec<String> {
+        self.params
+            .iter()
+            .filter(|(name, _)| name == "TYPE")
+            .flat_map(|(_, value)| value.split(','))
+            .chain(
+                self.params
+                    .iter()
+                    .filter(|(_, value)| value.is_empty())
+                    .map(|(name, _)| name.as_str()),
+            )
+            .map(|kind| kind.trim_matches('"').to_ascii_lowercase())
+            .collect()
+    }
+
+    /// Get the label of the property from its types
+    fn label(&self, labels: &[&str]) -> String {
+        self.types()
+            .iter()
+            .map(|kind| match kind.as_str() {
+                "cell" => "mobile",
+                kind => kind,
+            })
+            .find(|kind| labels.contains(kind))
+            .unwrap_or("other")
+            .to_string()
+    }
+
+    /// Check if the property is the preferred one, `TYPE=pref` in 3.0 and `PREF=1` in 4.0
+    fn is_preferred(&self) -> bool {
+        self.types().iter().any(|kind| kind == "pref")
+            || self.params.iter().any(|(name, _)| name == "PREF")
+    }
+}
+
+/// Escape a text value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Unescape a text value
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+/// Split a structured value on the unescaped separators
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut escaped = false;
+
+    for c in value.chars() {
+        if escaped {
+            current.push('\\');
+            current.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(unescape(&current));
+            current.clear();
+        } else {
+            current.push(c);
+        }
+    }
+    if escaped {
+        current.push('\\');
+    }
+    parts.push(unescape(&current));
+
+    parts
+}
+
+/// Fold a content line so that no line is longer than 75 octets
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+
+    folded
+}
+
+/// Write the stored photo of a contact inline, a missing file is left out
+fn photo_property(photo: &str, version: Version) -> Option<String> {
+    let bytes = STORAGE.get(&image_key(photo)).ok()?;
+    let encoded = STANDARD.encode(bytes);
+
+    Some(match version {
+        Version::V3 => format!("PHOTO;ENCODING=b;TYPE=PNG:{}", encoded),
+        Version::V4 => format!("PHOTO:data:image/png;base64,{}", encoded),
+    })
+}
+
+/// Write the type parameters of a property from its label
+fn type_params(label: &str, primary: bool, version: Version, default: Option<&str>) -> String {
+    let kind = match label {
+        "work" | "home" => Some(label),
+        "mobile" => Some("cell"),
+        _ => default,
+    };
+
+    match version {
+        Version::V3 => {
+            let kinds = kind
+                .into_iter()
+                .chain(primary.then_some("pref"))
+                .collect::<Vec<_>>();
+            if kinds.is_empty() {
+                String::new()
+            } else {
+                format!(";TYPE={}", kinds.join(","))
+            }
+        }
+        Version::V4 => {
+            let mut params = kind
+                .map(|kind| format!(";TYPE={}", kind))
+                .unwrap_or_default();
+            if primary {
+                params.push_str(";PREF=1");
+            }
+            params
+        }
+    }
+}
+
+/// Write the TEL property of a phone number
+fn phone_property(phone: &str, params: &str, version: Version) -> String {
+    let is_global = phone.starts_with('+') && phone[1..].chars().all(|c| c.is_ascii_digit());
+
+    match version {
+        Version::V3 => format!("TEL{}:{}", params, escape(phone)),
+        Version::V4 if is_global => format!("TEL{};VALUE=uri:tel:{}", params, phone),
+        Version::V4 => format!("TEL{};VALUE=text:{}", params, escape(phone)),
+    }
+}
+
+/// Write a date property, only the 4.0 version knows the anniversaries
+fn date_property(kind: DateKind, date: NaiveDate, version: Version) -> Option<String> {
+    match (kind, version) {
+        (DateKind::Birthday, Version::V3) => Some(format!("BDAY:{}", date.format("%Y-%m-%d"))),
+        (DateKind::Birthday, Version::V4) => Some(format!("BDAY:{}", date.format("%Y%m%d"))),
+        (DateKind::Anniversary, Version::V3) => {
+            Some(format!("X-ANNIVERSARY:{}", date.format("%Y-%m-%d")))
+        }
+        (DateKind::Anniversary, Version::V4) => {
+            Some(format!("ANNIVERSARY:{}", date.format("%Y%m%d")))
+        }
+        (DateKind::FollowUp, _) => None,
+    }
+}
+
+/// Parse a complete date, written with or without dashes and possibly followed by a time
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let date = value.split('T').next().unwrap_or_default().trim();
+
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(date, "%Y%m%d"))
+        .ok()
+}
+
+/// Write a contact as a vCard
+pub fn to_vcard(details: &ContactDetails, version: Version) -> String {
+    to_vcard_with_uid(
+        details,
+        version,
+        &format!("urn:fer:contact:{}", details.contact.id),
+    )
+}
+
+/// Write a contact as a vCard with another UID, such as the one given by a CardDAV client
+pub fn to_vcard_with_uid(details: &ContactDetails, version: Version, uid: &str) -> String {
+    let contact = &details.contact;
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        format!("VERSION:{}", version.as_str()),
+        format!("UID:{}", uid),
+        format!(
+            "FN:{}",
+            escape(&format!("{} {}", contact.firstname, contact.lastname))
+        ),
+        format!(
+            "N:{};{};;;",
+            escape(&contact.lastname),
+            escape(&contact.firstname)
+        ),
+    ];
+
+    for email in &details.emails {
+        lines.push(format!(
+            "EMAIL{}:{}",
+            type_params(&email.label, email.is_primary, version, None),
+            escape(&email.value)
+        ));
+    }
+    for phone in &details.phones {
+        let params = type_params(&phone.label, phone.is_primary, version, Some("voice"));
+        lines.push(phone_property(&phone.value, &params, version));
+    }
+    for address in &details.addresses {
+        lines.push(format!(
+            "ADR{}:;;{};{};{};{};{}",
+            type_params(&address.label, address.is_primary, version, None),
+            escape(&address.street),
+            escape(&address.locality),
+            escape(&address.region),
+            escape(&address.postal_code),
+            escape(&address.country)
+        ));
+    }
+    for date in &details.dates {
+        lines.extend(date_property(date.kind(), date.date, version));
+    }
+    if let Some(photo) = contact
+        .photo
+        .as_deref()
+        .and_then(|photo| photo_property(photo, version))
+    {
+        lines.push(photo);
+    }
+    lines.push("END:VCARD".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// Write contacts as a multi-card vCard file
+pub fn to_vcards(contacts: &[ContactDetails], version: Version) -> String {
+    contacts
+        .iter()
+        .map(|contact| to_vcard(contact, version))
+        .collect()
+}
+
+/// Unfold the content lines of a file
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in input.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.chars().next(), lines.last_mut()) {
+            (Some(' ') | Some('\t'), Some(last)) => last.push_str(&line[1..]),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Parse a content line, dropping the group of the property
+fn parse_line(line: &str) -> Option<Property> {
+    // The value starts at the first colon which is not inside a quoted parameter
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(index, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(index),
+        _ => None,
+    })?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.rsplit('.').next()?.to_ascii_uppercase();
+    let params = parts
+        .map(|param| match param.split_once('=') {
+            Some((name, value)) => (name.to_ascii_uppercase(), value.to_string()),
+            None => (param.to_ascii_uppercase(), String::new()),
+        })
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+/// Decode an inline photo, in base64 in vCard 2.1 and 3.0 or as a data URI in vCard 4.0, a photo
+/// given by a link is left out
+fn photo(property: &Property) -> Option<Vec<u8>> {
+    let base64 = property.params.iter().any(|(name, value)| {
+        name == "BASE64"
+            || (name == "ENCODING" && matches!(value.to_ascii_lowercase().as_str(), "b" | "base64"))
+    });
+    let encoded = if base64 {
+        property.value.as_str()
+    } else {
+        let (media, data) = property.value.strip_prefix("data:")?.split_once(',')?;
+        if !media.ends_with(";base64") {
+            return None;
+        }
+        data
+    };
+
+    STANDARD
+        .decode(encoded.split_whitespace().collect::<String>())
+        .ok()
+}
+
+/// Convert the properties of a card into a contact and its photo
+fn to_contact(properties: &[Property]) -> Result<ParsedCard, String> {
+    let find = |name: &str| {
+        properties
+            .iter()
+            .find(|property| property.name == name)
+            .map(|property| property.value.as_str())
+    };
+
+    let structured_name = find("N").map(|n| {
+        let parts = split_unescaped(n, ';');
+        (
+            parts.first().cloned().unwrap_or_default(),
+            parts.get(1).cloned().unwrap_or_default(),
+        )
+    });
+    let formatted_name = find("FN").map(|fn_value| {
+        let full_name = unescape(fn_value).trim().to_string();
+        match full_name.rsplit_once(' ') {
+            Some((firstname, lastname)) => (lastname.to_string(), firstname.to_string()),
+            None => (full_name, String::new()),
+        }
+    });
+
+    // A single word is kept as the last name, and an empty structured name falls back to the
+    // formatted one
+    let optional = |name: String| Some(name.trim().to_string()).filter(|name| !name.is_empty());
+    let (lastname, firstname) = [structured_name, formatted_name]
+        .into_iter()
+        .flatten()
+        .map(|(lastname, firstname)| (optional(lastname), optional(firstname)))
+        .find(|(lastname, firstname)| lastname.is_some() || firstname.is_some())
+        .ok_or_else(|| "the card has no name".to_string())?;
+
+    let all = |name: &'static str| {
+        properties
+            .iter()
+            .filter(move |property| property.name == name)
+    };
+
+    let emails = all("EMAIL")
+        .map(|property| EmailPayload {
+            label: property.label(&["work", "home"]),
+            value: unescape(&property.value).trim().to_string(),
+            primary: property.is_preferred(),
+        })
+        .filter(|email| !email.value.is_empty())
+        .collect();
+    let phones = all("TEL")
+        .map(|property| {
+            let tel = property
+                .value
+                .strip_prefix("tel:")
+                .unwrap_or(&property.value);
+            PhonePayload {
+                label: property.label(&["work", "home", "mobile"]),
+                value: unescape(tel).trim().to_string(),
+                primary: property.is_preferred(),
+            }
+        })
+        .filter(|phone| !phone.value.is_empty())
+        .collect();
+    let addresses = all("ADR")
+        .map(|property| {
+            let parts = split_unescaped(&property.value, ';');
+            let part = |index: usize| parts.get(index).map(|part| part.trim().to_string());
+            AddressPayload {
+                label: property.label(&["work", "home"]),
+                street: part(2).unwrap_or_default(),
+                locality: part(3).unwrap_or_default(),
+                region: part(4).unwrap_or_default(),
+                postal_code: part(5).unwrap_or_default(),
+                country: part(6).unwrap_or_default(),
+                primary: property.is_preferred(),
+            }
+        })
+        .filter(|address| {
+            [
+                &address.street,
+                &address.locality,
+                &address.region,
+                &address.postal_code,
+                &address.country,
+            ]
+            .iter()
+            .any(|part| !part.is_empty())
+        })
+        .collect();
+    let dates = [
+        ("BDAY", DateKind::Birthday),
+        ("ANNIVERSARY", DateKind::Anniversary),
+        ("X-ANNIVERSARY", DateKind::Anniversary),
+    ]
+    .into_iter()
+    .filter_map(|(name, kind)| {
+        let date = parse_date(find(name)?)?;
+        Some(DatePayload {
+            kind: kind.as_str().to_string(),
+            date,
+            label: String::new(),
+            remind_days_before: 0,
+        })
+    })
+    .fold(Vec::<DatePayload>::new(), |mut dates, date| {
+        if !dates.iter().any(|known| known.kind == date.kind) {
+            dates.push(date);
+        }
+        dates
+    });
+
+    Ok(ParsedCard {
+        contact: ContactPayload {
+            contact: NewUpdateContact {
+                lastname,
+                firstname,
+                ..NewUpdateContact::default()
+            },
+            emails: Some(emails),
+            phones: Some(phones),
+            addresses: Some(addresses),
+            dates: Some(dates),
+            fields: None,
+        },
+        photo: properties
+            .iter()
+            .find(|property| property.name == "PHOTO")
+            .and_then(photo),
+    })
+}
+
+/// Get the names and unescaped values of the properties of a card, without their groups
+pub fn properties(card: &str) -> Vec<(String, String)> {
+    unfold(card)
+        .iter()
+        .filter_map(|line| parse_line(line))
+        .map(|property| (property.name, unescape(&property.value)))
+        .collect()
+}
+
+/// Parse the cards of a vCard file, each card being converted or failing on its own
+pub fn parse(input: &str) -> Vec<Result<ParsedCard, String>> {
+    let mut cards = Vec::new();
+    let mut current: Option<Vec<Property>> = None;
+
+    for line in unfold(input) {
+        let Some(property) = parse_line(&line) else {
+            if current.is_some() {
+                cards.push(Err(format!("invalid line: {}", line)));
+                current = None;
+            }
+            continue;
+        };
+
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", _) if property.value.eq_ignore_ascii_case("VCARD") => {
+                if current.is_some() {
+                    cards.push(Err("the card is not terminated".to_string()));
+                }
+                current = Some(Vec::new());
+            }
+            ("END", Some(properties)) if property.value.eq_ignore_ascii_case("VCARD") => {
+                cards.push(to_contact(properties));
+                current = None;
+            }
+            (_, Some(properties)) => properties.push(property),
+            (_, None) => {}
+        }
+    }
+
+    if current.is_some() {
+        cards.push(Err("the card is not terminated".to_string()));
+    }
+
+    cards
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    /// Parse a file holding a single card
+    fn parse_card(lines: &[&str]) -> Result<ParsedCard, String> {
+        let input = format!("BEGIN:VCARD\r\n{}\r\nEND:VCARD\r\n", lines.join("\r\n"));
+        let mut cards = parse(&input);
+        assert_eq!(cards.len(), 1);
+        cards.remove(0)
+    }
+
+    /// Parse a file holding a single card and get its contact
+    fn parse_one(lines: &[&str]) -> Result<ContactPayload, String> {
+        parse_card(lines).map(|card| card.contact)
+    }
+
+    /// Get the last name and first name of a parsed card
+    fn names(payload: &ContactPayload) -> (Option<&str>, Option<&str>) {
+        (
+            payload.contact.lastname.as_deref(),
+            payload.contact.firstname.as_deref(),
+        )
+    }
+
+    #[test]
+    fn reads_the_structured_name() {
+        let card = parse_one(&["VERSION:3.0", "N:Smith;Alice;;;", "FN:Someone Else"]).unwrap();
+
+        assert_eq!(names(&card), (Some("Smith"), Some("Alice")));
+    }
+
+    #[test]
+    fn splits_the_formatted_name_on_its_last_space() {
+        let card = parse_one(&["VERSION:4.0", "FN:Mary Ann Smith"]).unwrap();
+
+        assert_eq!(names(&card), (Some("Smith"), Some("Mary Ann")));
+    }
+
+    #[test]
+    fn keeps_a_single_word_formatted_name_as_the_last_name() {
+        let mut card = parse_one(&["VERSION:4.0", "FN:Madonna"]).unwrap();
+
+        assert_eq!(names(&card), (Some("Madonna"), None));
+        card.normalize();
+        assert!(card.validate().is_ok());
+    }
+
+    #[test]
+    fn falls_back_to_the_formatted_name_when_the_structured_one_is_empty() {
+        let card = parse_one(&["VERSION:3.0", "N:;;;;", "FN:Alice Smith"]).unwrap();
+
+        assert_eq!(names(&card), (Some("Smith"), Some("Alice")));
+    }
+
+    #[test]
+    fn rejects_a_card_without_name() {
+        assert!(parse_one(&["VERSION:3.0", "EMAIL:alice@example.com"]).is_err());
+    }
+
+    #[test]
+    fn accepts_a_card_without_email() {
+        let mut card = parse_one(&["VERSION:3.0", "N:Smith;Alice;;;", "TEL:+33612345678"]).unwrap();
+
+        card.normalize();
+        assert_eq!(card.contact.email.as_deref(), Some(""));
+        assert!(card.validate().is_ok());
+    }
+
+    #[test]
+    fn unescapes_the_structured_name() {
+        let card = parse_one(&["VERSION:3.0", r"N:O\;Brien;Anne\, Marie;;;"]).unwrap();
+
+        assert_eq!(names(&card), (Some("O;Brien"), Some("Anne, Marie")));
+    }
+
+    #[test]
+    fn unfolds_the_continued_lines() {
+        let card = parse_one(&[
+            "VERSION:3.0",
+            "N:Smith;Alice;;;",
+            "EMAIL;TYPE=work:alice.smi",
+            " th@example.com",
+            "NOTE:one",
+            "\ttwo",
+        ])
+        .unwrap();
+
+        let emails = card.emails.unwrap();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].value, "alice.smith@example.com");
+        assert_eq!(emails[0].label, "work");
+    }
+
+    #[test]
+    fn folds_and_escapes_the_written_lines() {
+        let value = format!("{};{}", "a".repeat(100), "line\nbreak");
+        let line = format!("NOTE:{}", escape(&value));
+
+        let folded = fold(&line);
+        assert!(folded
+            .split("\r\n")
+            .all(|part| part.len() <= MAX_LINE_LENGTH));
+
+        let unfolded = unfold(&folded);
+        assert_eq!(unfolded, vec![line.clone()]);
+        let property = parse_line(&unfolded[0]).unwrap();
+        assert_eq!(unescape(&property.value), value);
+    }
+
+    #[test]
+    fn decodes_the_inline_photos() {
+        let v3 = parse_card(&[
+            "VERSION:3.0",
+            "N:Smith;Alice;;;",
+            "PHOTO;ENCODING=b;TYPE=PNG:aGVs",
+            " bG8=",
+        ])
+        .unwrap();
+        let v4 = parse_card(&[
+            "VERSION:4.0",
+            "FN:Alice Smith",
+            "PHOTO:data:image/png;base64,aGVsbG8=",
+        ])
+        .unwrap();
+        let link = parse_card(&[
+            "VERSION:4.0",
+            "FN:Alice Smith",
+            "PHOTO:https://example.com/a.png",
+        ])
+        .unwrap();
+
+        assert_eq!(v3.photo.as_deref(), Some(&b"hello"[..]));
+        assert_eq!(v4.photo.as_deref(), Some(&b"hello"[..]));
+        assert!(link.photo.is_none());
+    }
+}
@@ -0,0 +1,134 @@
+use super::models::export::Export;
+use crate::{
+    auth::models::{audit::AuditEvent, auth::Auth},
+    contact::{
+        self,
+        models::{
+            contact::{Contact, Owner},
+            details::ContactDetails,
+        },
+    },
+    field::models::field::CustomField,
+    user::models::user::User,
+    utils::{db::establish_connection, storage::STORAGE},
+};
+use diesel::SqliteConnection;
+use serde::Serialize;
+use std::{
+    env::var,
+    error::Error,
+    io::{Cursor, Write},
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+type ArchiveResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Serialize)]
+struct AuthRecord {
+    failed_attempts: i32,
+    blocked: bool,
+}
+
+/// Number of hours an archive can be downloaded
+fn ttl_hours() -> i64 {
+    var("EXPORT_TTL_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .unwrap_or(24)
+}
+
+/// Serialize rows as CSV
+fn to_csv<T: Serialize>(rows: &[T]) -> ArchiveResult<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+/// Add a dataset to the archive as both `<name>.json` and `<name>.csv`
+fn add_dataset<T: Serialize>(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    rows: &[T],
+) -> ArchiveResult<()> {
+    add_json(zip, name, rows)?;
+    add_csv(zip, name, rows)
+}
+
+/// Add a dataset to the archive as `<name>.json`
+fn add_json<T: Serialize>(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    rows: &[T],
+) -> ArchiveResult<()> {
+    zip.start_file(format!("{}.json", name), SimpleFileOptions::default())?;
+    zip.write_all(&serde_json::to_vec_pretty(rows)?)?;
+
+    Ok(())
+}
+
+/// Add a dataset to the archive as `<name>.csv`
+fn add_csv<T: Serialize>(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    rows: &[T],
+) -> ArchiveResult<()> {
+    zip.start_file(format!("{}.csv", name), SimpleFileOptions::default())?;
+    zip.write_all(&to_csv(rows)?)?;
+
+    Ok(())
+}
+
+/// Build a zip archive with all the data of a user
+pub fn build(connection: &mut SqliteConnection, user_id: i32) -> ArchiveResult<Vec<u8>> {
+    let user = User::find(connection, user_id)?;
+    let contacts = Contact::all(connection, user_id)?;
+    let contacts = ContactDetails::load(connection, contacts)?;
+    let fields = CustomField::all(connection, Owner::User(user_id))?;
+    let auth = Auth::find_by_user_id(connection, user_id)?;
+    let exports = Export::all(connection, user_id)?;
+    let events = AuditEvent::all(connection, user_id)?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+    add_dataset(&mut zip, "profile", &[user])?;
+    // The CSV file stays flat, with the primary email and phone of each contact and a column
+    // per custom field
+    zip.start_file("contacts.csv", SimpleFileOptions::default())?;
+    zip.write_all(&contact::csv::to_csv(&contacts, &fields)?)?;
+    add_json(&mut zip, "contacts", &contacts)?;
+    add_dataset(
+        &mut zip,
+        "auth",
+        &[AuthRecord {
+            failed_attempts: auth.error,
+            blocked: auth.is_blocked(),
+        }],
+    )?;
+    add_dataset(&mut zip, "exports", &exports)?;
+    add_dataset(&mut zip, "audit", &events)?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Build the archive of an export and store it
+pub fn run(export_id: i32, user_id: i32) {
+    let connection = &mut establish_connection();
+
+    let file = format!("exports/{}.zip", export_id);
+    let result = build(connection, user_id).and_then(|archive| {
+        STORAGE.put(&file, &archive)?;
+
+        let expires_at = chrono::Utc::now().naive_utc() + chrono::Duration::hours(ttl_hours());
+        Export::complete(connection, export_id, file.clone(), expires_at)?;
+
+        Ok(())
+    });
+
+    if let Err(err) = result {
+        tracing::error!("failed to export the data of user {}: {}", user_id, err);
+        let _ = STORAGE.delete(&file);
+        let _ = Export::fail(connection, export_id);
+    }
+}
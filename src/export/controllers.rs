@@ -0,0 +1,156 @@
+use super::{archive, models::export::Export};
+use crate::{
+    auth::models::{
+        audit::{AuditAction, AuditEvent},
+        claims::Claims,
+        link::LinkClaims,
+    },
+    route,
+    user::models::user::User,
+    utils::{db::establish_connection, error::ApiError, storage::STORAGE},
+};
+use axum::{
+    extract::{Path, Query},
+    http::header,
+    response::IntoResponse,
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Purpose of the signed download links
+const DOWNLOAD_PURPOSE: &str = "export";
+
+#[derive(Debug, Deserialize)]
+struct DownloadQuery {
+    token: String,
+}
+
+/// Describe an export, with its download link when the archive is available
+fn describe(export: &Export) -> Result<Value, ApiError> {
+    let mut value = json!(export);
+
+    if let (true, Some(expires_at)) = (export.is_available(), export.expires_at) {
+        let token = LinkClaims::new(
+            export.id,
+            DOWNLOAD_PURPOSE,
+            expires_at.and_utc().timestamp(),
+        )
+        .encode()?;
+        value["download_url"] = json!(route(format!("/exports/download?token={}", token)));
+    }
+
+    Ok(value)
+}
+
+/// Request an export of all the data of a user, built in the background
+async fn create(claims: Claims, Path(id): Path<i32>) -> Result<Json<Value>, ApiError> {
+    if !claims.is_admin() && !claims.is_user(id) {
+        return Err(ApiError::InvalidToken);
+    }
+
+    let connection = &mut establish_connection();
+
+    User::find(connection, id).map_err(ApiError::from)?;
+    let export = Export::create(connection, id).map_err(ApiError::from)?;
+    AuditEvent::record(
+        connection,
+        id,
+        Some(claims.id()),
+        AuditAction::ExportRequest,
+    )
+    .map_err(ApiError::from)?;
+
+    let (export_id, user_id) = (export.id, export.user_id);
+    tokio::task::spawn_blocking(move || archive::run(export_id, user_id));
+
+    Ok(Json(describe(&export)?))
+}
+
+/// Get the status of an export
+async fn find(
+    claims: Claims,
+    Path((id, export_id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    if !claims.is_admin() && !claims.is_user(id) {
+        return Err(ApiError::InvalidToken);
+    }
+
+    let connection = &mut establish_connection();
+
+    let export = Export::find(connection, export_id).map_err(ApiError::from)?;
+    if export.user_id != id {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(describe(&export)?))
+}
+
+/// Get all the exports of a user
+async fn get_all(claims: Claims, Path(id): Path<i32>) -> Result<Json<Value>, ApiError> {
+    if !claims.is_admin() && !claims.is_user(id) {
+        return Err(ApiError::InvalidToken);
+    }
+
+    let connection = &mut establish_connection();
+
+    let exports = Export::all(connection, id)
+        .map_err(ApiError::from)?
+        .iter()
+        .map(describe)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(json!({ "exports": exports })))
+}
+
+/// Download an archive with a signed link
+async fn download(Query(query): Query<DownloadQuery>) -> Result<impl IntoResponse, ApiError> {
+    let claims = LinkClaims::decode(&query.token, DOWNLOAD_PURPOSE)?;
+
+    let connection = &mut establish_connection();
+
+    let export = Export::find(connection, claims.sub).map_err(ApiError::from)?;
+    let file = match (&export.file, export.is_available()) {
+        (Some(file), true) => file,
+        _ => return Err(ApiError::NotFound),
+    };
+
+    let bytes = STORAGE.get(file).map_err(|_| ApiError::NotFound)?;
+    // The link is not tied to a session, whoever holds it is unknown
+    AuditEvent::record(
+        connection,
+        export.user_id,
+        None,
+        AuditAction::ExportDownload,
+    )
+    .map_err(ApiError::from)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"export-{}.zip\"", export.id),
+            ),
+        ],
+        bytes,
+    ))
+}
+
+/// Export routes
+pub fn controller(router: &Router) -> Router {
+    router
+        .clone()
+        .route(
+            route("/user/:id/exports".to_string()).as_str(),
+            axum::routing::get(get_all).post(create),
+        )
+        .route(
+            route("/user/:id/exports/:export_id".to_string()).as_str(),
+            axum::routing::get(find),
+        )
+        .route(
+            route("/exports/download".to_string()).as_str(),
+            axum::routing::get(download),
+        )
+}
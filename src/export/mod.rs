@@ -0,0 +1,3 @@
+pub mod archive;
+pub mod controllers;
+pub mod models;
@@ -0,0 +1,117 @@
+use crate::schema::exports;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct Export {
+    pub id: i32,
+    pub user_id: i32,
+    pub status: String,
+    #[serde(skip_serializing)]
+    pub file: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = exports)]
+struct NewExport {
+    user_id: i32,
+    created_at: NaiveDateTime,
+}
+
+impl Export {
+    /// Check if the archive can be downloaded
+    pub fn is_available(&self) -> bool {
+        self.status == "done"
+            && self
+                .expires_at
+                .is_some_and(|expires_at| expires_at > chrono::Utc::now().naive_utc())
+    }
+
+    /// Find all the exports of a user
+    pub fn all(connection: &mut SqliteConnection, user_id_param: i32) -> Result<Vec<Self>, Error> {
+        use crate::schema::exports::dsl::*;
+
+        exports
+            .filter(user_id.eq(user_id_param))
+            .order(id.desc())
+            .load::<Export>(connection)
+    }
+
+    /// Find an export by id
+    pub fn find(connection: &mut SqliteConnection, id_param: i32) -> Result<Self, Error> {
+        use crate::schema::exports::dsl::*;
+
+        exports.find(id_param).first::<Export>(connection)
+    }
+
+    /// Create a pending export for a user
+    pub fn create(connection: &mut SqliteConnection, user_id_param: i32) -> Result<Self, Error> {
+        use crate::schema::exports::dsl::*;
+
+        let new_export = NewExport {
+            user_id: user_id_param,
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+        connection.transaction(|connection| {
+            diesel::insert_into(exports)
+                .values(&new_export)
+                .execute(connection)?;
+
+            exports.order(id.desc()).first::<Export>(connection)
+        })
+    }
+
+    /// Mark an export as done, its archive being available until the expiration date
+    pub fn complete(
+        connection: &mut SqliteConnection,
+        id_param: i32,
+        file_param: String,
+        expires_at_param: NaiveDateTime,
+    ) -> Result<usize, Error> {
+        use crate::schema::exports::dsl::*;
+
+        diesel::update(exports.find(id_param))
+            .set((
+                status.eq("done"),
+                file.eq(file_param),
+                expires_at.eq(expires_at_param),
+            ))
+            .execute(connection)
+    }
+
+    /// Mark an export as failed
+    pub fn fail(connection: &mut SqliteConnection, id_param: i32) -> Result<usize, Error> {
+        use crate::schema::exports::dsl::*;
+
+        diesel::update(exports.find(id_param))
+            .set(status.eq("failed"))
+            .execute(connection)
+    }
+
+    /// Find the exports whose archive expired before the given date
+    pub fn expired(
+        connection: &mut SqliteConnection,
+        before: NaiveDateTime,
+    ) -> Result<Vec<Self>, Error> {
+        use crate::schema::exports::dsl::*;
+
+        exports
+            .filter(expires_at.lt(before))
+            .filter(file.is_not_null())
+            .load::<Export>(connection)
+    }
+
+    /// Forget the archive of an expired export
+    pub fn clear_file(connection: &mut SqliteConnection, id_param: i32) -> Result<usize, Error> {
+        use crate::schema::exports::dsl::*;
+
+        diesel::update(exports.find(id_param))
+            .set(file.eq(None::<String>))
+            .execute(connection)
+    }
+}
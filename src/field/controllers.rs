@@ -0,0 +1,167 @@
+use super::models::field::{valid_options, CustomField, FieldKind, FieldPayload, FieldUpdate};
+use crate::{
+    auth::models::claims::Claims,
+    contact::{controllers::authorize_owner, models::contact::Owner},
+    route,
+    utils::{db::establish_connection, error::ApiError},
+};
+use axum::{extract::Path, Json, Router};
+use serde_json::{json, Value};
+use validator::Validate;
+
+/// Get the custom fields of an owner
+fn list(claims: &Claims, owner: Owner) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let fields = CustomField::all(connection, owner).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "fields": fields })))
+}
+
+/// Create a custom field for an owner
+fn store(
+    claims: &Claims,
+    owner: Owner,
+    payload: FieldPayload,
+) -> Result<Json<CustomField>, ApiError> {
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
+    let kind = FieldKind::parse(&payload.kind).ok_or(ApiError::NotValid)?;
+    if kind == FieldKind::Enum && !payload.options.as_deref().is_some_and(valid_options) {
+        return Err(ApiError::NotValid);
+    }
+
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let field = CustomField::create(connection, owner, kind, payload).map_err(ApiError::from)?;
+
+    Ok(Json(field))
+}
+
+/// Rename a custom field of an owner or change its choices
+fn edit(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    payload: FieldUpdate,
+) -> Result<Json<CustomField>, ApiError> {
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+    let field = CustomField::find_owned(connection, owner, id).map_err(ApiError::from)?;
+
+    if let Some(options) = payload.options.as_deref() {
+        if field.kind() != FieldKind::Enum || !valid_options(options) {
+            return Err(ApiError::NotValid);
+        }
+    }
+
+    let field = field.update(connection, payload).map_err(ApiError::from)?;
+
+    Ok(Json(field))
+}
+
+/// Delete a custom field of an owner with its values
+fn remove(claims: &Claims, owner: Owner, id: i32) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+    CustomField::find_owned(connection, owner, id).map_err(ApiError::from)?;
+
+    CustomField::delete(connection, id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "message": "Field deleted" })))
+}
+
+/// Get the custom fields of a user
+pub async fn get_all(claims: Claims, Path(user_id): Path<i32>) -> Result<Json<Value>, ApiError> {
+    list(&claims, Owner::User(user_id))
+}
+
+/// Create a custom field for a user
+pub async fn create(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<FieldPayload>,
+) -> Result<Json<CustomField>, ApiError> {
+    store(&claims, Owner::User(user_id), payload)
+}
+
+/// Update a custom field of a user
+pub async fn update(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<FieldUpdate>,
+) -> Result<Json<CustomField>, ApiError> {
+    edit(&claims, Owner::User(user_id), id, payload)
+}
+
+/// Delete a custom field of a user
+pub async fn delete(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    remove(&claims, Owner::User(user_id), id)
+}
+
+/// Get the custom fields of an organization
+pub async fn get_all_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+) -> Result<Json<Value>, ApiError> {
+    list(&claims, Owner::Organization(organization_id))
+}
+
+/// Create a custom field for an organization
+pub async fn create_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+    Json(payload): Json<FieldPayload>,
+) -> Result<Json<CustomField>, ApiError> {
+    store(&claims, Owner::Organization(organization_id), payload)
+}
+
+/// Update a custom field of an organization
+pub async fn update_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<FieldUpdate>,
+) -> Result<Json<CustomField>, ApiError> {
+    edit(&claims, Owner::Organization(organization_id), id, payload)
+}
+
+/// Delete a custom field of an organization
+pub async fn delete_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    remove(&claims, Owner::Organization(organization_id), id)
+}
+
+/// Custom field routes
+pub fn controller(router: &Router) -> Router {
+    router
+        .clone()
+        .route(
+            route("/users/:id/fields".to_string()).as_str(),
+            axum::routing::get(get_all).post(create),
+        )
+        .route(
+            route("/users/:id/fields/:field_id".to_string()).as_str(),
+            axum::routing::put(update).delete(delete),
+        )
+        .route(
+            route("/organizations/:id/fields".to_string()).as_str(),
+            axum::routing::get(get_all_for_organization).post(create_for_organization),
+        )
+        .route(
+            route("/organizations/:id/fields/:field_id".to_string()).as_str(),
+            axum::routing::put(update_for_organization).delete(delete_for_organization),
+        )
+}
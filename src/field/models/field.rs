@@ -0,0 +1,429 @@
+use crate::{
+    contact::models::contact::{Contact, Owner},
+    schema::{contact_field_values, custom_fields},
+};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use validator::Validate;
+
+/// Longest accepted text value
+const MAX_TEXT_LENGTH: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+pub struct CustomField {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub organization_id: Option<i32>,
+    pub name: String,
+    pub kind: String,
+    #[serde(serialize_with = "serialize_options")]
+    pub options: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = custom_fields)]
+struct NewCustomField {
+    user_id: Option<i32>,
+    organization_id: Option<i32>,
+    name: String,
+    kind: String,
+    options: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = contact_field_values)]
+struct FieldValue {
+    contact_id: i32,
+    field_id: i32,
+    value: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct FieldPayload {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    pub kind: String,
+    pub options: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct FieldUpdate {
+    #[validate(length(min = 1, max = 64))]
+    pub name: Option<String>,
+    pub options: Option<Vec<String>>,
+}
+
+/// The type of the values of a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    Number,
+    Date,
+    Enum,
+    Url,
+}
+
+impl FieldKind {
+    /// Get the kind as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Number => "number",
+            Self::Date => "date",
+            Self::Enum => "enum",
+            Self::Url => "url",
+        }
+    }
+
+    /// Parse a kind from its database value
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "text" => Some(Self::Text),
+            "number" => Some(Self::Number),
+            "date" => Some(Self::Date),
+            "enum" => Some(Self::Enum),
+            "url" => Some(Self::Url),
+            _ => None,
+        }
+    }
+}
+
+/// Write the stored JSON choices as a JSON array
+fn serialize_options<S: Serializer>(
+    options: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let value = options
+        .as_deref()
+        .and_then(|options| serde_json::from_str::<Value>(options).ok());
+
+    value.serialize(serializer)
+}
+
+/// Check the choices of an enum field: at least one, none empty nor repeated
+pub fn valid_options(options: &[String]) -> bool {
+    let mut seen = std::collections::HashSet::new();
+
+    !options.is_empty()
+        && options
+            .iter()
+            .all(|option| !option.trim().is_empty() && seen.insert(option.trim()))
+}
+
+impl CustomField {
+    /// Get the kind of the field
+    pub fn kind(&self) -> FieldKind {
+        FieldKind::parse(&self.kind).unwrap_or(FieldKind::Text)
+    }
+
+    /// Get the choices of an enum field
+    pub fn options(&self) -> Vec<String> {
+        self.options
+            .as_deref()
+            .and_then(|options| serde_json::from_str(options).ok())
+            .unwrap_or_default()
+    }
+
+    /// Check a value and convert it to its stored text, none when it is not valid
+    pub fn to_stored(&self, value: &Value) -> Option<String> {
+        match (self.kind(), value) {
+            (FieldKind::Number, Value::Number(number)) => Some(number.to_string()),
+            (FieldKind::Number, Value::String(number)) => number
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .map(|number| number.to_string()),
+            (_, Value::String(text)) => {
+                let text = text.trim();
+                let valid = match self.kind() {
+                    FieldKind::Text => text.chars().count() <= MAX_TEXT_LENGTH,
+                    FieldKind::Date => NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok(),
+                    FieldKind::Enum => self.options().iter().any(|option| option == text),
+                    FieldKind::Url => {
+                        (text.starts_with("https://") || text.starts_with("http://"))
+                            && validator::validate_url(text)
+                    }
+                    FieldKind::Number => false,
+                };
+                valid.then(|| text.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Convert a stored text to its JSON value
+    pub fn to_json(&self, stored: &str) -> Value {
+        match self.kind() {
+            FieldKind::Number => serde_json::from_str::<serde_json::Number>(stored)
+                .map(Value::Number)
+                .unwrap_or_else(|_| Value::String(stored.to_string())),
+            _ => Value::String(stored.to_string()),
+        }
+    }
+
+    /// Get the fields of an owner
+    pub fn all(connection: &mut SqliteConnection, owner: Owner) -> Result<Vec<Self>, Error> {
+        let query = custom_fields::table
+            .order(custom_fields::name.asc())
+            .into_boxed();
+
+        let query = match owner {
+            Owner::User(user) => query.filter(custom_fields::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(custom_fields::organization_id.eq(organization))
+            }
+        };
+
+        query.load::<CustomField>(connection)
+    }
+
+    /// Find a field by id, only if it belongs to the owner
+    pub fn find_owned(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        id: i32,
+    ) -> Result<Self, Error> {
+        let query = custom_fields::table
+            .filter(custom_fields::id.eq(id))
+            .into_boxed();
+
+        let query = match owner {
+            Owner::User(user) => query.filter(custom_fields::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(custom_fields::organization_id.eq(organization))
+            }
+        };
+
+        query.first::<CustomField>(connection)
+    }
+
+    /// Find a field of an owner by its name, ignoring the case
+    pub fn find_by_name(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        name: &str,
+    ) -> Result<Self, Error> {
+        CustomField::all(connection, owner)?
+            .into_iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or(Error::NotFound)
+    }
+
+    /// Create a field for an owner
+    pub fn create(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        kind: FieldKind,
+        payload: FieldPayload,
+    ) -> Result<Self, Error> {
+        let (user_id, organization_id) = match owner {
+            Owner::User(user) => (Some(user), None),
+            Owner::Organization(organization) => (None, Some(organization)),
+        };
+        let options = match kind {
+            FieldKind::Enum => payload
+                .options
+                .map(|options| serde_json::to_string(&options))
+                .transpose()
+                .map_err(|err| Error::SerializationError(err.into()))?,
+            _ => None,
+        };
+
+        connection.transaction(|connection| {
+            diesel::insert_into(custom_fields::table)
+                .values(&NewCustomField {
+                    user_id,
+                    organization_id,
+                    name: payload.name.trim().to_string(),
+                    kind: kind.as_str().to_string(),
+                    options,
+                })
+                .execute(connection)?;
+
+            custom_fields::table
+                .order(custom_fields::id.desc())
+                .first::<CustomField>(connection)
+        })
+    }
+
+    /// Rename a field or change the choices of an enum field, the values which are no longer a
+    /// choice are removed
+    pub fn update(
+        &self,
+        connection: &mut SqliteConnection,
+        payload: FieldUpdate,
+    ) -> Result<Self, Error> {
+        connection.transaction(|connection| {
+            if let Some(name) = payload.name {
+                diesel::update(custom_fields::table.find(self.id))
+                    .set(custom_fields::name.eq(name.trim()))
+                    .execute(connection)?;
+            }
+
+            if let Some(options) = payload.options.filter(|_| self.kind() == FieldKind::Enum) {
+                let stored = serde_json::to_string(&options)
+                    .map_err(|err| Error::SerializationError(err.into()))?;
+                diesel::update(custom_fields::table.find(self.id))
+                    .set(custom_fields::options.eq(stored))
+                    .execute(connection)?;
+                diesel::delete(
+                    contact_field_values::table
+                        .filter(contact_field_values::field_id.eq(self.id))
+                        .filter(contact_field_values::value.ne_all(options)),
+                )
+                .execute(connection)?;
+            }
+
+            custom_fields::table
+                .find(self.id)
+                .first::<CustomField>(connection)
+        })
+    }
+
+    /// Delete a field with its values
+    pub fn delete(connection: &mut SqliteConnection, id: i32) -> Result<usize, Error> {
+        connection.transaction(|connection| {
+            diesel::delete(
+                contact_field_values::table.filter(contact_field_values::field_id.eq(id)),
+            )
+            .execute(connection)?;
+            diesel::delete(custom_fields::table.find(id)).execute(connection)
+        })
+    }
+
+    /// Delete all the fields of an owner
+    pub fn delete_for_owner(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+    ) -> Result<usize, Error> {
+        let ids = CustomField::all(connection, owner)?
+            .into_iter()
+            .map(|field| field.id)
+            .collect::<Vec<_>>();
+
+        diesel::delete(
+            contact_field_values::table.filter(contact_field_values::field_id.eq_any(&ids)),
+        )
+        .execute(connection)?;
+        diesel::delete(custom_fields::table.filter(custom_fields::id.eq_any(&ids)))
+            .execute(connection)
+    }
+
+    /// Get the names of the values which are not defined for the owner or not valid
+    pub fn invalid_values(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        values: &HashMap<String, Value>,
+    ) -> Result<Vec<String>, Error> {
+        let fields = CustomField::all(connection, owner)?;
+
+        Ok(values
+            .iter()
+            .filter(|(name, value)| {
+                let field = fields
+                    .iter()
+                    .find(|field| field.name.eq_ignore_ascii_case(name.trim()));
+                match field {
+                    Some(_) if value.is_null() => false,
+                    Some(field) => field.to_stored(value).is_none(),
+                    None => true,
+                }
+            })
+            .map(|(name, _)| name.clone())
+            .collect())
+    }
+
+    /// Set the values of a contact by field name, a null value removes it, the unknown or
+    /// invalid values are ignored
+    pub fn set_values(
+        connection: &mut SqliteConnection,
+        contact: &Contact,
+        values: HashMap<String, Value>,
+    ) -> Result<(), Error> {
+        let fields = CustomField::all(connection, contact.owner())?;
+
+        for (name, value) in values {
+            let Some(field) = fields
+                .iter()
+                .find(|field| field.name.eq_ignore_ascii_case(name.trim()))
+            else {
+                continue;
+            };
+            let current = contact_field_values::table.find((contact.id, field.id));
+
+            if value.is_null() {
+                diesel::delete(current).execute(connection)?;
+            } else if let Some(stored) = field.to_stored(&value) {
+                diesel::replace_into(contact_field_values::table)
+                    .values(&FieldValue {
+                        contact_id: contact.id,
+                        field_id: field.id,
+                        value: stored,
+                    })
+                    .execute(connection)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the values of contacts by field name
+    pub fn values_for(
+        connection: &mut SqliteConnection,
+        contact_ids: &[i32],
+    ) -> Result<HashMap<i32, BTreeMap<String, Value>>, Error> {
+        let rows = contact_field_values::table
+            .inner_join(custom_fields::table)
+            .filter(contact_field_values::contact_id.eq_any(contact_ids))
+            .select((
+                contact_field_values::contact_id,
+                contact_field_values::value,
+                custom_fields::all_columns,
+            ))
+            .load::<(i32, String, CustomField)>(connection)?;
+
+        let mut values: HashMap<i32, BTreeMap<String, Value>> = HashMap::new();
+        for (contact_id, value, field) in rows {
+            values
+                .entry(contact_id)
+                .or_default()
+                .insert(field.name.clone(), field.to_json(&value));
+        }
+
+        Ok(values)
+    }
+
+    /// Get the contacts having a value of the field
+    pub fn contact_ids_with(
+        &self,
+        connection: &mut SqliteConnection,
+        value: &str,
+    ) -> Result<Vec<i32>, Error> {
+        let Some(stored) = self.to_stored(&Value::String(value.to_string())) else {
+            return Ok(Vec::new());
+        };
+
+        contact_field_values::table
+            .filter(contact_field_values::field_id.eq(self.id))
+            .filter(contact_field_values::value.eq(stored))
+            .select(contact_field_values::contact_id)
+            .load::<i32>(connection)
+    }
+
+    /// Delete the values of contacts
+    pub fn delete_values_for(
+        connection: &mut SqliteConnection,
+        contact_ids: &[i32],
+    ) -> Result<usize, Error> {
+        diesel::delete(
+            contact_field_values::table
+                .filter(contact_field_values::contact_id.eq_any(contact_ids)),
+        )
+        .execute(connection)
+    }
+}
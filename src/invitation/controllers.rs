@@ -0,0 +1,220 @@
+use super::models::invitation::{AcceptInvitation, Invitation, InvitationPayload};
+use crate::{
+    auth::models::{
+        audit::{AuditAction, AuditEvent},
+        claims::Claims,
+        link::LinkClaims,
+    },
+    route,
+    user::models::user::{Role, User},
+    utils::{db::establish_connection, error::ApiError, mailer::MAILER},
+};
+use axum::{
+    extract::{Path, Query},
+    Json, Router,
+};
+use diesel::SqliteConnection;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env::var;
+use validator::Validate;
+
+/// Purpose of the signed invitation links
+const INVITATION_PURPOSE: &str = "invitation";
+
+#[derive(Debug, Deserialize)]
+struct InvitationQuery {
+    token: String,
+}
+
+/// Date until which a new or resent invitation can be accepted
+fn expiration() -> chrono::NaiveDateTime {
+    let days = var("INVITATION_TTL_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(7);
+
+    chrono::Utc::now().naive_utc() + chrono::Duration::days(days)
+}
+
+/// Email a signed link to accept the invitation
+async fn send(invitation: &Invitation) -> Result<(), ApiError> {
+    let token = LinkClaims::new(
+        invitation.id,
+        INVITATION_PURPOSE,
+        invitation.expires_at.and_utc().timestamp(),
+    )
+    .encode()?;
+
+    let base_url = var("APP_URL").unwrap_or_else(|_| "http://127.0.0.1:8080".to_string());
+    let to = invitation.email.clone();
+    let body = format!(
+        "You have been invited to join fer.\n\nAccept the invitation before {}:\n{}{}\n",
+        invitation.expires_at.format("%Y-%m-%d %H:%M UTC"),
+        base_url,
+        route(format!("/invitations/accept?token={}", token))
+    );
+
+    tokio::task::spawn_blocking(move || MAILER.send(&to, "Your invitation to fer", &body))
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .map_err(|err| {
+            tracing::error!(
+                "failed to send the invitation to {}: {}",
+                invitation.email,
+                err
+            );
+            ApiError::InternalServerError
+        })
+}
+
+/// Invite someone with a role
+async fn create(
+    claims: Claims,
+    Json(payload): Json<InvitationPayload>,
+) -> Result<Json<Invitation>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+    let role = Role::parse(&payload.role).ok_or(ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    if User::find_by_email(connection, payload.email.clone()).is_ok() {
+        return Err(ApiError::Conflict);
+    }
+
+    let invitation =
+        Invitation::create(connection, &payload.email, role, claims.id(), expiration())
+            .map_err(ApiError::from)?;
+
+    // An invitation nobody received can not be accepted, it is not kept
+    if let Err(err) = send(&invitation).await {
+        Invitation::delete(connection, invitation.id).map_err(ApiError::from)?;
+        return Err(err);
+    }
+
+    Ok(Json(invitation))
+}
+
+/// Get all invitations
+async fn get_all(claims: Claims) -> Result<Json<Value>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    let connection = &mut establish_connection();
+
+    let invitations = Invitation::all(connection).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "invitations": invitations })))
+}
+
+/// Send the invitation again with a new expiration date
+async fn resend(claims: Claims, Path(id): Path<i32>) -> Result<Json<Invitation>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    let connection = &mut establish_connection();
+
+    let invitation = Invitation::find(connection, id).map_err(ApiError::from)?;
+    if invitation.accepted_at.is_some() || invitation.revoked_at.is_some() {
+        return Err(ApiError::Conflict);
+    }
+
+    let previous = invitation.expires_at;
+    let invitation = Invitation::renew(connection, id, expiration()).map_err(ApiError::from)?;
+
+    // The links sent before stay valid when the new one could not be sent
+    if let Err(err) = send(&invitation).await {
+        Invitation::renew(connection, id, previous).map_err(ApiError::from)?;
+        return Err(err);
+    }
+
+    Ok(Json(invitation))
+}
+
+/// Revoke an invitation
+async fn revoke(claims: Claims, Path(id): Path<i32>) -> Result<Json<Invitation>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    let connection = &mut establish_connection();
+
+    let invitation = Invitation::find(connection, id).map_err(ApiError::from)?;
+    if invitation.accepted_at.is_some() {
+        return Err(ApiError::Conflict);
+    }
+
+    let invitation = Invitation::revoke(connection, id).map_err(ApiError::from)?;
+
+    Ok(Json(invitation))
+}
+
+/// Find the pending invitation of a link
+fn find_pending(connection: &mut SqliteConnection, token: &str) -> Result<Invitation, ApiError> {
+    let claims = LinkClaims::decode(token, INVITATION_PURPOSE)?;
+
+    let invitation = Invitation::find(connection, claims.sub).map_err(ApiError::from)?;
+
+    // A resent invitation has a new expiration date, the older links are rejected
+    if !invitation.is_pending() || invitation.expires_at.and_utc().timestamp() != claims.exp {
+        return Err(ApiError::InvalidToken);
+    }
+
+    Ok(invitation)
+}
+
+/// Get the invitation of the emailed link, so that a client can ask for the name and password
+async fn show(Query(query): Query<InvitationQuery>) -> Result<Json<Invitation>, ApiError> {
+    let connection = &mut establish_connection();
+
+    let invitation = find_pending(connection, &query.token)?;
+
+    Ok(Json(invitation))
+}
+
+/// Accept an invitation and create the account
+async fn accept(Json(payload): Json<AcceptInvitation>) -> Result<Json<User>, ApiError> {
+    if payload.password.is_empty() {
+        return Err(ApiError::MissingCredentials);
+    }
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    let invitation = find_pending(connection, &payload.token)?;
+
+    let user = Invitation::accept(connection, invitation, payload.name, payload.password)
+        .map_err(ApiError::from)?;
+    AuditEvent::record(connection, user.id, Some(user.id), AuditAction::Register)
+        .map_err(ApiError::from)?;
+
+    Ok(Json(user))
+}
+
+/// Invitation routes
+pub fn controller(router: &Router) -> Router {
+    router
+        .clone()
+        .route(
+            route("/admin/invitations".to_string()).as_str(),
+            axum::routing::get(get_all).post(create),
+        )
+        .route(
+            route("/admin/invitations/:id".to_string()).as_str(),
+            axum::routing::delete(revoke),
+        )
+        .route(
+            route("/admin/invitations/:id/resend".to_string()).as_str(),
+            axum::routing::post(resend),
+        )
+        .route(
+            route("/invitations/accept".to_string()).as_str(),
+            axum::routing::get(show).post(accept),
+        )
+}
@@ -0,0 +1,156 @@
+use crate::{
+    schema::invitations,
+    user::models::user::{normalize_email, Register, Role, User},
+};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct Invitation {
+    pub id: i32,
+    pub email: String,
+    pub role: String,
+    pub invited_by: i32,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub accepted_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = invitations)]
+struct NewInvitation {
+    email: String,
+    role: String,
+    invited_by: i32,
+    created_at: NaiveDateTime,
+    expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct InvitationPayload {
+    #[validate(email)]
+    pub email: String,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AcceptInvitation {
+    pub token: String,
+    #[validate(length(min = 4))]
+    pub name: String,
+    pub password: String,
+}
+
+impl Invitation {
+    /// Check if the invitation can still be accepted
+    pub fn is_pending(&self) -> bool {
+        self.accepted_at.is_none()
+            && self.revoked_at.is_none()
+            && self.expires_at > chrono::Utc::now().naive_utc()
+    }
+
+    /// Get all invitations
+    pub fn all(connection: &mut SqliteConnection) -> Result<Vec<Self>, Error> {
+        use crate::schema::invitations::dsl::*;
+
+        invitations.order(id.desc()).load::<Invitation>(connection)
+    }
+
+    /// Find an invitation by id
+    pub fn find(connection: &mut SqliteConnection, id_param: i32) -> Result<Self, Error> {
+        use crate::schema::invitations::dsl::*;
+
+        invitations.find(id_param).first::<Invitation>(connection)
+    }
+
+    /// Create a new invitation
+    pub fn create(
+        connection: &mut SqliteConnection,
+        email_param: &str,
+        role_param: Role,
+        invited_by_param: i32,
+        expires_at_param: NaiveDateTime,
+    ) -> Result<Self, Error> {
+        use crate::schema::invitations::dsl::*;
+
+        let new_invitation = NewInvitation {
+            email: normalize_email(email_param),
+            role: role_param.as_str().to_string(),
+            invited_by: invited_by_param,
+            created_at: chrono::Utc::now().naive_utc(),
+            expires_at: expires_at_param,
+        };
+
+        connection.transaction(|connection| {
+            diesel::insert_into(invitations)
+                .values(&new_invitation)
+                .execute(connection)?;
+
+            invitations.order(id.desc()).first::<Invitation>(connection)
+        })
+    }
+
+    /// Push back the expiration of an invitation, the links sent before are no longer valid
+    pub fn renew(
+        connection: &mut SqliteConnection,
+        id_param: i32,
+        expires_at_param: NaiveDateTime,
+    ) -> Result<Self, Error> {
+        use crate::schema::invitations::dsl::*;
+
+        diesel::update(invitations.find(id_param))
+            .set(expires_at.eq(expires_at_param))
+            .execute(connection)?;
+
+        Invitation::find(connection, id_param)
+    }
+
+    /// Revoke an invitation
+    pub fn revoke(connection: &mut SqliteConnection, id_param: i32) -> Result<Self, Error> {
+        use crate::schema::invitations::dsl::*;
+
+        diesel::update(invitations.find(id_param))
+            .set(revoked_at.eq(chrono::Utc::now().naive_utc()))
+            .execute(connection)?;
+
+        Invitation::find(connection, id_param)
+    }
+
+    /// Delete an invitation
+    pub fn delete(connection: &mut SqliteConnection, id_param: i32) -> Result<usize, Error> {
+        use crate::schema::invitations::dsl::*;
+
+        diesel::delete(invitations.find(id_param)).execute(connection)
+    }
+
+    /// Accept an invitation, creating the invited user with the role of the invitation
+    pub fn accept(
+        connection: &mut SqliteConnection,
+        invitation: Invitation,
+        name: String,
+        password: String,
+    ) -> Result<User, Error> {
+        use crate::schema::invitations::dsl::*;
+
+        let role_param = Role::parse(&invitation.role).unwrap_or(Role::User);
+        let register = Register {
+            name,
+            email: invitation.email,
+            password,
+        };
+
+        connection.transaction(|connection| {
+            let user = User::create(connection, register, role_param)?;
+
+            diesel::update(invitations.find(invitation.id))
+                .set(accepted_at.eq(chrono::Utc::now().naive_utc()))
+                .execute(connection)?;
+
+            Ok(user)
+        })
+    }
+}
@@ -4,9 +4,17 @@ use std::net::SocketAddr;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
+pub mod admin;
 pub mod auth;
+pub mod carddav;
 pub mod contact;
+pub mod export;
+pub mod field;
+pub mod invitation;
+pub mod organization;
+pub mod reminder;
 pub mod schema;
+pub mod tag;
 pub mod user;
 pub mod utils;
 
@@ -18,17 +26,38 @@ async fn main() {
         .finish();
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.get(1).map(String::as_str) == Some("create-admin") {
+        if let Err(err) = admin::bootstrap::from_args(&args[2..]) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    admin::bootstrap::from_env();
+
     let mut app = Router::new().route("/", axum::routing::get(|| async { "Hello, World!" }));
 
     app = user::controllers::controller(&app);
     app = auth::controllers::controller(&app);
+    app = carddav::controllers::controller(&app);
     app = contact::controllers::controller(&app);
+    app = admin::controllers::controller(&app);
+    app = export::controllers::controller(&app);
+    app = field::controllers::controller(&app);
+    app = invitation::controllers::controller(&app);
+    app = organization::controllers::controller(&app);
+    app = reminder::controllers::controller(&app);
+    app = tag::controllers::controller(&app);
     app = app
         .fallback((|| utils::middleware::handler_404()).into_service())
         .layer(middleware::from_fn(
             utils::middleware::print_request_response,
         ));
 
+    utils::jobs::spawn();
+
     let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
     tracing::info!("listening on {}", addr);
 
@@ -0,0 +1,207 @@
+use super::models::{
+    membership::{Membership, MembershipPayload, OrgRole},
+    organization::{Organization, OrganizationPayload},
+};
+use crate::{
+    auth::models::claims::Claims,
+    route,
+    user::models::user::User,
+    utils::{db::establish_connection, error::ApiError},
+};
+use axum::{extract::Path, Json, Router};
+use diesel::SqliteConnection;
+use serde_json::{json, Value};
+use validator::Validate;
+
+/// Get the role of the caller in an organization, the admins act as owners
+pub fn role_of(
+    connection: &mut SqliteConnection,
+    claims: &Claims,
+    organization_id: i32,
+) -> Result<OrgRole, ApiError> {
+    if claims.is_admin() {
+        Organization::find(connection, organization_id).map_err(ApiError::from)?;
+        return Ok(OrgRole::Owner);
+    }
+
+    Membership::role_of(connection, organization_id, claims.id())
+        .map_err(ApiError::from)?
+        .ok_or(ApiError::NotFound)
+}
+
+/// Check that the organization keeps an owner when this membership changes
+fn keeps_an_owner(
+    connection: &mut SqliteConnection,
+    membership: &Membership,
+    new_role: Option<OrgRole>,
+) -> Result<bool, ApiError> {
+    if membership.role() != OrgRole::Owner || new_role == Some(OrgRole::Owner) {
+        return Ok(true);
+    }
+
+    let owners =
+        Membership::count_owners(connection, membership.organization_id).map_err(ApiError::from)?;
+
+    Ok(owners > 1)
+}
+
+/// Get the organizations of the user, or all of them for an admin
+async fn get_all(claims: Claims) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    let organizations = if claims.is_admin() {
+        Organization::all(connection)
+    } else {
+        Organization::all_for_user(connection, claims.id())
+    }
+    .map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "organizations": organizations })))
+}
+
+/// Create an organization owned by the user
+async fn create(
+    claims: Claims,
+    Json(payload): Json<OrganizationPayload>,
+) -> Result<Json<Organization>, ApiError> {
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    let organization =
+        Organization::create(connection, payload, claims.id()).map_err(ApiError::from)?;
+
+    Ok(Json(organization))
+}
+
+/// Get an organization by id
+async fn find(claims: Claims, Path(id): Path<i32>) -> Result<Json<Organization>, ApiError> {
+    let connection = &mut establish_connection();
+
+    role_of(connection, &claims, id)?;
+
+    let organization = Organization::find(connection, id).map_err(ApiError::from)?;
+
+    Ok(Json(organization))
+}
+
+/// Rename an organization
+async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Json(payload): Json<OrganizationPayload>,
+) -> Result<Json<Organization>, ApiError> {
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    if role_of(connection, &claims, id)? < OrgRole::Admin {
+        return Err(ApiError::Forbidden);
+    }
+
+    let organization = Organization::update(connection, id, payload).map_err(ApiError::from)?;
+
+    Ok(Json(organization))
+}
+
+/// Delete an organization with its contacts
+async fn delete(claims: Claims, Path(id): Path<i32>) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    if role_of(connection, &claims, id)? < OrgRole::Owner {
+        return Err(ApiError::Forbidden);
+    }
+
+    Organization::delete(connection, id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "message": "Organization deleted" })))
+}
+
+/// Get the members of an organization
+async fn get_members(claims: Claims, Path(id): Path<i32>) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    role_of(connection, &claims, id)?;
+
+    let members = Membership::all(connection, id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "members": members })))
+}
+
+/// Add a member to an organization or change its role
+async fn put_member(
+    claims: Claims,
+    Path((id, user_id)): Path<(i32, i32)>,
+    Json(payload): Json<MembershipPayload>,
+) -> Result<Json<Membership>, ApiError> {
+    let role = OrgRole::parse(&payload.role).ok_or(ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    let caller_role = role_of(connection, &claims, id)?;
+    if caller_role < OrgRole::Admin || caller_role < role {
+        return Err(ApiError::Forbidden);
+    }
+
+    User::find(connection, user_id).map_err(ApiError::from)?;
+
+    let current = Membership::find(connection, id, user_id).ok();
+    if let Some(current) = &current {
+        if caller_role < current.role() {
+            return Err(ApiError::Forbidden);
+        }
+        if !keeps_an_owner(connection, current, Some(role))? {
+            return Err(ApiError::Conflict);
+        }
+    }
+
+    let membership = Membership::upsert(connection, id, user_id, role).map_err(ApiError::from)?;
+
+    Ok(Json(membership))
+}
+
+/// Remove a member from an organization, members can also leave by themselves
+async fn delete_member(
+    claims: Claims,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    let caller_role = role_of(connection, &claims, id)?;
+    let membership = Membership::find(connection, id, user_id).map_err(ApiError::from)?;
+
+    if !claims.is_user(user_id) && (caller_role < OrgRole::Admin || caller_role < membership.role())
+    {
+        return Err(ApiError::Forbidden);
+    }
+
+    if !keeps_an_owner(connection, &membership, None)? {
+        return Err(ApiError::Conflict);
+    }
+
+    Membership::delete(connection, id, user_id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "message": "Member removed" })))
+}
+
+/// Organization routes
+pub fn controller(router: &Router) -> Router {
+    router
+        .clone()
+        .route(
+            route("/organizations".to_string()).as_str(),
+            axum::routing::get(get_all).post(create),
+        )
+        .route(
+            route("/organizations/:id".to_string()).as_str(),
+            axum::routing::get(find).put(update).delete(delete),
+        )
+        .route(
+            route("/organizations/:id/members".to_string()).as_str(),
+            axum::routing::get(get_members),
+        )
+        .route(
+            route("/organizations/:id/members/:user_id".to_string()).as_str(),
+            axum::routing::put(put_member).delete(delete_member),
+        )
+}
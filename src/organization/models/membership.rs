@@ -0,0 +1,140 @@
+use crate::schema::memberships;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Queryable, Insertable, AsChangeset)]
+#[diesel(table_name = memberships)]
+pub struct Membership {
+    pub organization_id: i32,
+    pub user_id: i32,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MembershipPayload {
+    pub role: String,
+}
+
+/// The role of a user inside an organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OrgRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl OrgRole {
+    /// Get the role as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::Admin => "admin",
+            Self::Owner => "owner",
+        }
+    }
+
+    /// Parse a role from its database value
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "member" => Some(Self::Member),
+            "admin" => Some(Self::Admin),
+            "owner" => Some(Self::Owner),
+            _ => None,
+        }
+    }
+}
+
+impl Membership {
+    /// Get the role of the member
+    pub fn role(&self) -> OrgRole {
+        OrgRole::parse(&self.role).unwrap_or(OrgRole::Member)
+    }
+
+    /// Get the members of an organization
+    pub fn all(
+        connection: &mut SqliteConnection,
+        organization_id_param: i32,
+    ) -> Result<Vec<Self>, Error> {
+        use crate::schema::memberships::dsl::*;
+
+        memberships
+            .filter(organization_id.eq(organization_id_param))
+            .load::<Membership>(connection)
+    }
+
+    /// Find the membership of a user in an organization
+    pub fn find(
+        connection: &mut SqliteConnection,
+        organization_id_param: i32,
+        user_id_param: i32,
+    ) -> Result<Self, Error> {
+        use crate::schema::memberships::dsl::*;
+
+        memberships
+            .find((organization_id_param, user_id_param))
+            .first::<Membership>(connection)
+    }
+
+    /// Get the role of a user in an organization, if the user is a member
+    pub fn role_of(
+        connection: &mut SqliteConnection,
+        organization_id_param: i32,
+        user_id_param: i32,
+    ) -> Result<Option<OrgRole>, Error> {
+        let membership =
+            Membership::find(connection, organization_id_param, user_id_param).optional()?;
+
+        Ok(membership.map(|membership| membership.role()))
+    }
+
+    /// Count the owners of an organization
+    pub fn count_owners(
+        connection: &mut SqliteConnection,
+        organization_id_param: i32,
+    ) -> Result<i64, Error> {
+        use crate::schema::memberships::dsl::*;
+
+        memberships
+            .filter(organization_id.eq(organization_id_param))
+            .filter(role.eq(OrgRole::Owner.as_str()))
+            .count()
+            .get_result(connection)
+    }
+
+    /// Add a member to an organization or change its role
+    pub fn upsert(
+        connection: &mut SqliteConnection,
+        organization_id_param: i32,
+        user_id_param: i32,
+        role_param: OrgRole,
+    ) -> Result<Self, Error> {
+        use crate::schema::memberships::dsl::*;
+
+        let membership = Membership {
+            organization_id: organization_id_param,
+            user_id: user_id_param,
+            role: role_param.as_str().to_string(),
+        };
+
+        diesel::insert_into(memberships)
+            .values(&membership)
+            .on_conflict((organization_id, user_id))
+            .do_update()
+            .set(role.eq(role_param.as_str()))
+            .execute(connection)?;
+
+        Ok(membership)
+    }
+
+    /// Remove a member from an organization
+    pub fn delete(
+        connection: &mut SqliteConnection,
+        organization_id_param: i32,
+        user_id_param: i32,
+    ) -> Result<usize, Error> {
+        use crate::schema::memberships::dsl::*;
+
+        diesel::delete(memberships.find((organization_id_param, user_id_param))).execute(connection)
+    }
+}
@@ -0,0 +1,119 @@
+use super::membership::{Membership, OrgRole};
+use crate::{
+    contact::{
+        models::contact::{Contact, Owner},
+        photo,
+    },
+    field::models::field::CustomField,
+    schema::{memberships, organizations},
+    tag::models::tag::Tag,
+};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct Organization {
+    pub id: i32,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = organizations)]
+struct NewOrganization {
+    name: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct OrganizationPayload {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+}
+
+impl Organization {
+    /// Get all organizations
+    pub fn all(connection: &mut SqliteConnection) -> Result<Vec<Self>, Error> {
+        organizations::table.load::<Organization>(connection)
+    }
+
+    /// Get the organizations a user is a member of
+    pub fn all_for_user(
+        connection: &mut SqliteConnection,
+        user_id: i32,
+    ) -> Result<Vec<Self>, Error> {
+        organizations::table
+            .inner_join(memberships::table)
+            .filter(memberships::user_id.eq(user_id))
+            .select(organizations::all_columns)
+            .load::<Organization>(connection)
+    }
+
+    /// Find an organization by id
+    pub fn find(connection: &mut SqliteConnection, id: i32) -> Result<Self, Error> {
+        organizations::table
+            .find(id)
+            .first::<Organization>(connection)
+    }
+
+    /// Create an organization owned by a user
+    pub fn create(
+        connection: &mut SqliteConnection,
+        payload: OrganizationPayload,
+        owner_id: i32,
+    ) -> Result<Self, Error> {
+        let new_organization = NewOrganization {
+            name: payload.name,
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+        connection.transaction(|connection| {
+            diesel::insert_into(organizations::table)
+                .values(&new_organization)
+                .execute(connection)?;
+
+            let organization = organizations::table
+                .order(organizations::id.desc())
+                .first::<Organization>(connection)?;
+
+            Membership::upsert(connection, organization.id, owner_id, OrgRole::Owner)?;
+
+            Ok(organization)
+        })
+    }
+
+    /// Rename an organization
+    pub fn update(
+        connection: &mut SqliteConnection,
+        id: i32,
+        payload: OrganizationPayload,
+    ) -> Result<Self, Error> {
+        diesel::update(organizations::table.find(id))
+            .set(organizations::name.eq(payload.name))
+            .execute(connection)?;
+
+        Organization::find(connection, id)
+    }
+
+    /// Delete an organization with its memberships, contacts, tags and custom fields
+    pub fn delete(connection: &mut SqliteConnection, id: i32) -> Result<usize, Error> {
+        let (deleted, photos) = connection.transaction(|connection| {
+            let photos = Contact::delete_for_owner(connection, Owner::Organization(id))?;
+            Tag::delete_for_owner(connection, Owner::Organization(id))?;
+            CustomField::delete_for_owner(connection, Owner::Organization(id))?;
+            diesel::delete(memberships::table.filter(memberships::organization_id.eq(id)))
+                .execute(connection)?;
+            let deleted = diesel::delete(organizations::table.find(id)).execute(connection)?;
+
+            Ok::<_, Error>((deleted, photos))
+        })?;
+
+        // The photos are only deleted once the contacts are
+        photos.iter().for_each(|deleted| photo::delete(deleted));
+
+        Ok(deleted)
+    }
+}
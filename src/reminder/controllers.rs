@@ -0,0 +1,79 @@
+use super::models::notification::Notification;
+use crate::{
+    auth::models::claims::Claims,
+    contact::{controllers::authorize_owner, models::contact::Owner},
+    route,
+    utils::{db::establish_connection, error::ApiError},
+};
+use axum::{
+    extract::{Path, Query},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationQuery {
+    unread: Option<bool>,
+}
+
+/// Get the notifications of a user, only the unread ones with `?unread=true`
+pub async fn get_all(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Query(query): Query<NotificationQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, &claims, Owner::User(user_id))?;
+
+    let notifications = Notification::all(connection, user_id, query.unread.unwrap_or(false))
+        .map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "notifications": notifications })))
+}
+
+/// Mark a notification of a user as read
+pub async fn read(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Notification>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, &claims, Owner::User(user_id))?;
+
+    let notification = Notification::find_owned(connection, user_id, id)
+        .and_then(|notification| notification.mark_read(connection))
+        .map_err(ApiError::from)?;
+
+    Ok(Json(notification))
+}
+
+/// Mark all the notifications of a user as read
+pub async fn read_all(claims: Claims, Path(user_id): Path<i32>) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, &claims, Owner::User(user_id))?;
+
+    let count = Notification::mark_all_read(connection, user_id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "count": count })))
+}
+
+/// Notification routes
+pub fn controller(router: &Router) -> Router {
+    router
+        .clone()
+        .route(
+            route("/users/:id/notifications".to_string()).as_str(),
+            axum::routing::get(get_all),
+        )
+        .route(
+            route("/users/:id/notifications/read".to_string()).as_str(),
+            axum::routing::post(read_all),
+        )
+        .route(
+            route("/users/:id/notifications/:notification_id/read".to_string()).as_str(),
+            axum::routing::post(read),
+        )
+}
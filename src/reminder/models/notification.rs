@@ -0,0 +1,139 @@
+use crate::schema::notifications;
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize};
+
+/// A reminder of a date of a contact, kept once read so that it is not generated again
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable)]
+#[diesel(table_name = notifications)]
+pub struct Notification {
+    pub id: i32,
+    pub user_id: i32,
+    pub contact_id: i32,
+    pub kind: String,
+    pub due_on: NaiveDate,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+    pub read_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub label: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = notifications)]
+pub struct NewNotification {
+    pub user_id: i32,
+    pub contact_id: i32,
+    pub kind: String,
+    pub due_on: NaiveDate,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+    pub label: String,
+}
+
+impl Notification {
+    /// Get the notifications of a user, the latest first
+    pub fn all(
+        connection: &mut SqliteConnection,
+        user_id_param: i32,
+        unread_only: bool,
+    ) -> Result<Vec<Self>, Error> {
+        use crate::schema::notifications::dsl::*;
+
+        let mut query = notifications
+            .filter(user_id.eq(user_id_param))
+            .order((created_at.desc(), id.desc()))
+            .into_boxed();
+        if unread_only {
+            query = query.filter(read_at.is_null());
+        }
+
+        query.load::<Notification>(connection)
+    }
+
+    /// Find a notification of a user
+    pub fn find_owned(
+        connection: &mut SqliteConnection,
+        user_id_param: i32,
+        id_param: i32,
+    ) -> Result<Self, Error> {
+        use crate::schema::notifications::dsl::*;
+
+        notifications
+            .filter(id.eq(id_param))
+            .filter(user_id.eq(user_id_param))
+            .first::<Notification>(connection)
+    }
+
+    /// Create a notification, none when the same occurrence has already been notified
+    pub fn create(
+        connection: &mut SqliteConnection,
+        notification: NewNotification,
+    ) -> Result<Option<Self>, Error> {
+        use crate::schema::notifications::dsl::*;
+
+        connection.transaction(|connection| {
+            let inserted = diesel::insert_or_ignore_into(notifications)
+                .values(&notification)
+                .execute(connection)?;
+            if inserted == 0 {
+                return Ok(None);
+            }
+
+            notifications
+                .order(id.desc())
+                .first::<Notification>(connection)
+                .map(Some)
+        })
+    }
+
+    /// Mark a notification as read
+    pub fn mark_read(&self, connection: &mut SqliteConnection) -> Result<Self, Error> {
+        use crate::schema::notifications::dsl::*;
+
+        diesel::update(notifications.find(self.id).filter(read_at.is_null()))
+            .set(read_at.eq(chrono::Utc::now().naive_utc()))
+            .execute(connection)?;
+
+        notifications
+            .find(self.id)
+            .first::<Notification>(connection)
+    }
+
+    /// Mark all the notifications of a user as read
+    pub fn mark_all_read(
+        connection: &mut SqliteConnection,
+        user_id_param: i32,
+    ) -> Result<usize, Error> {
+        use crate::schema::notifications::dsl::*;
+
+        diesel::update(
+            notifications
+                .filter(user_id.eq(user_id_param))
+                .filter(read_at.is_null()),
+        )
+        .set(read_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(connection)
+    }
+
+    /// Delete the notifications about contacts
+    pub fn delete_for_contacts(
+        connection: &mut SqliteConnection,
+        ids: &[i32],
+    ) -> Result<usize, Error> {
+        use crate::schema::notifications::dsl::*;
+
+        diesel::delete(notifications.filter(contact_id.eq_any(ids))).execute(connection)
+    }
+
+    /// Delete the notifications of users
+    pub fn delete_for_users(
+        connection: &mut SqliteConnection,
+        ids: &[i32],
+    ) -> Result<usize, Error> {
+        use crate::schema::notifications::dsl::*;
+
+        diesel::delete(notifications.filter(user_id.eq_any(ids))).execute(connection)
+    }
+}
@@ -0,0 +1,229 @@
+use super::models::notification::{NewNotification, Notification};
+use crate::{
+    contact::models::{
+        contact::Contact,
+        date::{ContactDate, DateKind},
+    },
+    schema::{contact_dates, contacts, users},
+    user::models::user::User,
+    utils::mailer::MAILER,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sql_types::{Bool, Date, Text};
+
+/// Local time of the day at which the reminders are sent when the user has not chosen one
+const DEFAULT_REMINDER_TIME: &str = "09:00";
+
+/// Number of days after an occurrence during which its reminder is still sent, when the
+/// scheduler did not run on that day
+const CATCH_UP_DAYS: i64 = 7;
+
+/// Largest number of days a reminder is sent before its date, as validated on the dates
+const MAX_DAYS_BEFORE: i64 = 60;
+
+/// Get the local day of a user once their reminder time has passed, in the timezone of their
+/// profile or else in UTC
+fn reminder_day(user: &User, now: DateTime<Utc>) -> Option<NaiveDate> {
+    let timezone = user
+        .timezone
+        .as_deref()
+        .and_then(|timezone| timezone.parse::<chrono_tz::Tz>().ok())
+        .unwrap_or(chrono_tz::UTC);
+    let time = user
+        .reminder_time
+        .as_deref()
+        .unwrap_or(DEFAULT_REMINDER_TIME);
+    let time = NaiveTime::parse_from_str(time, "%H:%M").ok()?;
+
+    let local = now.with_timezone(&timezone);
+
+    (local.time() >= time).then(|| local.date_naive())
+}
+
+/// Get the window of the occurrences which can be due on a day in the timezone of any user, as
+/// dates for the follow-ups and as months and days for the birthdays and anniversaries
+fn due_window(today: NaiveDate) -> ((NaiveDate, NaiveDate), (String, String)) {
+    let from = today - Duration::days(CATCH_UP_DAYS + 1);
+    let to = today + Duration::days(MAX_DAYS_BEFORE + 1);
+    let from_day = from.format("%m-%d").to_string();
+    // The 29th of February is reminded on the 28th in common years
+    let to_day = match to.format("%m-%d").to_string() {
+        day if day == "02-28" => "02-29".to_string(),
+        day => day,
+    };
+
+    ((from, to), (from_day, to_day))
+}
+
+/// Write the text of the reminder of a date
+fn message(contact: &Contact, date: &ContactDate, due_on: NaiveDate) -> String {
+    let name = format!("{} {}", contact.firstname, contact.lastname);
+    let kind = date.kind();
+    let mut message = format!(
+        "{}: {} on {}",
+        name.trim(),
+        kind.describe(),
+        due_on.format("%A %-d %B %Y")
+    );
+
+    let years = due_on.year() - date.date.year();
+    match kind {
+        DateKind::Birthday if years > 0 => message.push_str(&format!(" (turns {})", years)),
+        DateKind::Anniversary if years > 0 => message.push_str(&format!(" ({} years)", years)),
+        _ => {}
+    }
+    if !date.label.is_empty() {
+        message.push_str(&format!(" - {}", date.label));
+    }
+
+    message
+}
+
+/// Email a new notification to its user, a failure is only logged as the notification stays
+/// available in the application
+fn deliver(user: &User, contact: &Contact, notification: &Notification) {
+    let kind = DateKind::parse(&notification.kind).unwrap_or(DateKind::FollowUp);
+    let subject = format!(
+        "Reminder: {} of {} {}",
+        kind.describe(),
+        contact.firstname,
+        contact.lastname
+    );
+
+    if let Err(err) = MAILER.send(&user.email, subject.trim(), &notification.message) {
+        tracing::error!(
+            "failed to send reminder {} to {}: {}",
+            notification.id,
+            user.email,
+            err
+        );
+    }
+}
+
+/// Generate the reminders which are due for the contacts of the users whose reminder time has
+/// passed, each occurrence of a date being notified once, and return how many were created.
+/// An occurrence missed while the scheduler was not running is still notified during the
+/// following days. The contacts of the organizations have no reminders.
+pub fn run(connection: &mut SqliteConnection, now: DateTime<Utc>) -> Result<usize, Error> {
+    let ((from, to), (from_day, to_day)) = due_window(now.date_naive());
+    let wraps = from_day > to_day;
+
+    let dates = contact_dates::table
+        .inner_join(contacts::table.inner_join(users::table))
+        .filter(users::deleted_at.is_null())
+        .filter(users::suspended_at.is_null())
+        .filter(
+            sql::<Bool>("((contact_dates.kind = 'follow_up' AND contact_dates.date BETWEEN ")
+                .bind::<Date, _>(from)
+                .sql(" AND ")
+                .bind::<Date, _>(to)
+                .sql(") OR (contact_dates.kind <> 'follow_up' AND (")
+                .sql("strftime('%m-%d', contact_dates.date) >= ")
+                .bind::<Text, _>(from_day)
+                // The window of days wraps at the end of the year
+                .sql(if wraps { " OR " } else { " AND " })
+                .sql("strftime('%m-%d', contact_dates.date) <= ")
+                .bind::<Text, _>(to_day)
+                .sql(")))"),
+        )
+        .select((
+            contact_dates::all_columns,
+            contacts::all_columns,
+            users::all_columns,
+        ))
+        .load::<(ContactDate, Contact, User)>(connection)?;
+
+    let mut created = 0;
+    for (date, contact, user) in dates {
+        let Some(due_on) =
+            reminder_day(&user, now).and_then(|today| date.due(today, CATCH_UP_DAYS))
+        else {
+            continue;
+        };
+
+        let notification = Notification::create(
+            connection,
+            NewNotification {
+                user_id: user.id,
+                contact_id: contact.id,
+                kind: date.kind.clone(),
+                due_on,
+                message: message(&contact, &date, due_on),
+                created_at: now.naive_utc(),
+                label: date.label.clone(),
+            },
+        )?;
+        if let Some(notification) = notification {
+            deliver(&user, &contact, &notification);
+            created += 1;
+        }
+    }
+
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(kind: &str, date: NaiveDate, remind_days_before: i32) -> ContactDate {
+        ContactDate {
+            id: 1,
+            contact_id: 1,
+            kind: kind.to_string(),
+            date,
+            label: String::new(),
+            remind_days_before,
+        }
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn window_wraps_at_the_end_of_the_year() {
+        let (_, (from_day, to_day)) = due_window(day(2023, 12, 20));
+
+        assert_eq!(from_day, "12-12");
+        assert_eq!(to_day, "02-19");
+    }
+
+    #[test]
+    fn window_includes_the_29th_of_february() {
+        let (_, (_, to_day)) = due_window(day(2022, 12, 29));
+
+        assert_eq!(to_day, "02-29");
+    }
+
+    #[test]
+    fn missed_occurrences_are_caught_up() {
+        let birthday = date("birthday", day(1990, 3, 10), 0);
+        let follow_up = date("follow_up", day(2023, 3, 10), 0);
+
+        assert_eq!(
+            birthday.due(day(2023, 3, 12), CATCH_UP_DAYS),
+            Some(day(2023, 3, 10))
+        );
+        assert_eq!(
+            follow_up.due(day(2023, 3, 12), CATCH_UP_DAYS),
+            Some(day(2023, 3, 10))
+        );
+        assert_eq!(follow_up.due(day(2023, 3, 20), CATCH_UP_DAYS), None);
+        assert_eq!(birthday.due(day(2023, 3, 20), CATCH_UP_DAYS), None);
+    }
+
+    #[test]
+    fn reminders_start_days_before() {
+        let anniversary = date("anniversary", day(2010, 6, 15), 5);
+
+        assert_eq!(anniversary.due(day(2023, 6, 9), CATCH_UP_DAYS), None);
+        assert_eq!(
+            anniversary.due(day(2023, 6, 10), CATCH_UP_DAYS),
+            Some(day(2023, 6, 15))
+        );
+    }
+}
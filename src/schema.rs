@@ -1,5 +1,15 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    audit_events (id) {
+        id -> Integer,
+        user_id -> Integer,
+        actor_id -> Nullable<Integer>,
+        action -> Text,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     auths (user_id) {
         user_id -> Integer,
@@ -9,13 +19,208 @@ diesel::table! {
 }
 
 diesel::table! {
-    contacts (id) {
+    carddav_resources (contact_id) {
+        contact_id -> Integer,
+        user_id -> Integer,
+        name -> Text,
+        uid -> Text,
+    }
+}
+
+diesel::table! {
+    contact_addresses (id) {
+        id -> Integer,
+        contact_id -> Integer,
+        label -> Text,
+        street -> Text,
+        locality -> Text,
+        region -> Text,
+        postal_code -> Text,
+        country -> Text,
+        is_primary -> Bool,
+    }
+}
+
+diesel::table! {
+    contact_dates (id) {
+        id -> Integer,
+        contact_id -> Integer,
+        kind -> Text,
+        date -> Date,
+        label -> Text,
+        remind_days_before -> Integer,
+    }
+}
+
+diesel::table! {
+    contact_emails (id) {
+        id -> Integer,
+        contact_id -> Integer,
+        label -> Text,
+        value -> Text,
+        is_primary -> Bool,
+    }
+}
+
+diesel::table! {
+    contact_field_values (contact_id, field_id) {
+        contact_id -> Integer,
+        field_id -> Integer,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    contact_merges (id) {
         id -> Integer,
+        winner_id -> Integer,
+        loser_id -> Integer,
+        merged_by -> Integer,
+        snapshot -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    contact_phones (id) {
+        id -> Integer,
+        contact_id -> Integer,
+        label -> Text,
+        value -> Text,
+        is_primary -> Bool,
+    }
+}
+
+diesel::table! {
+    contact_shares (contact_id, user_id) {
+        contact_id -> Integer,
         user_id -> Integer,
+        permission -> Text,
+        shared_by -> Integer,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    contact_tags (contact_id, tag_id) {
+        contact_id -> Integer,
+        tag_id -> Integer,
+    }
+}
+
+diesel::table! {
+    contact_versions (id) {
+        id -> Integer,
+        contact_id -> Integer,
+        version -> Integer,
+        action -> Text,
+        user_id -> Nullable<Integer>,
+        organization_id -> Nullable<Integer>,
+        changed_by -> Integer,
+        snapshot -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    contacts (id) {
+        id -> Integer,
+        user_id -> Nullable<Integer>,
+        organization_id -> Nullable<Integer>,
         lastname -> Text,
         firstname -> Text,
         email -> Text,
         phone -> Text,
+        last_contacted_at -> Nullable<Timestamp>,
+        photo -> Nullable<Text>,
+        version -> Integer,
+    }
+}
+
+diesel::table! {
+    custom_fields (id) {
+        id -> Integer,
+        user_id -> Nullable<Integer>,
+        organization_id -> Nullable<Integer>,
+        name -> Text,
+        kind -> Text,
+        options -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    exports (id) {
+        id -> Integer,
+        user_id -> Integer,
+        status -> Text,
+        file -> Nullable<Text>,
+        created_at -> Timestamp,
+        expires_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    interactions (id) {
+        id -> Integer,
+        contact_id -> Integer,
+        kind -> Text,
+        body -> Text,
+        occurred_at -> Timestamp,
+        created_by -> Integer,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    invitations (id) {
+        id -> Integer,
+        email -> Text,
+        role -> Text,
+        invited_by -> Integer,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        accepted_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    memberships (organization_id, user_id) {
+        organization_id -> Integer,
+        user_id -> Integer,
+        role -> Text,
+    }
+}
+
+diesel::table! {
+    notifications (id) {
+        id -> Integer,
+        user_id -> Integer,
+        contact_id -> Integer,
+        kind -> Text,
+        due_on -> Date,
+        message -> Text,
+        created_at -> Timestamp,
+        read_at -> Nullable<Timestamp>,
+        label -> Text,
+    }
+}
+
+diesel::table! {
+    organizations (id) {
+        id -> Integer,
+        name -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Integer,
+        user_id -> Nullable<Integer>,
+        organization_id -> Nullable<Integer>,
+        name -> Text,
     }
 }
 
@@ -25,10 +230,67 @@ diesel::table! {
         name -> Text,
         email -> Text,
         role -> Text,
+        deleted_at -> Nullable<Timestamp>,
+        suspended_at -> Nullable<Timestamp>,
+        display_name -> Nullable<Text>,
+        locale -> Nullable<Text>,
+        timezone -> Nullable<Text>,
+        avatar -> Nullable<Text>,
+        reminder_time -> Nullable<Text>,
+        version -> Integer,
     }
 }
 
 diesel::joinable!(auths -> users (user_id));
+diesel::joinable!(carddav_resources -> users (user_id));
+diesel::joinable!(contact_addresses -> contacts (contact_id));
+diesel::joinable!(contact_dates -> contacts (contact_id));
+diesel::joinable!(contact_emails -> contacts (contact_id));
+diesel::joinable!(contact_field_values -> contacts (contact_id));
+diesel::joinable!(contact_field_values -> custom_fields (field_id));
+diesel::joinable!(contact_merges -> contacts (winner_id));
+diesel::joinable!(contact_merges -> users (merged_by));
+diesel::joinable!(contact_phones -> contacts (contact_id));
+diesel::joinable!(contact_shares -> contacts (contact_id));
+diesel::joinable!(contact_tags -> contacts (contact_id));
+diesel::joinable!(contact_tags -> tags (tag_id));
+diesel::joinable!(contact_versions -> users (changed_by));
+diesel::joinable!(contacts -> organizations (organization_id));
 diesel::joinable!(contacts -> users (user_id));
+diesel::joinable!(custom_fields -> organizations (organization_id));
+diesel::joinable!(custom_fields -> users (user_id));
+diesel::joinable!(exports -> users (user_id));
+diesel::joinable!(interactions -> contacts (contact_id));
+diesel::joinable!(interactions -> users (created_by));
+diesel::joinable!(invitations -> users (invited_by));
+diesel::joinable!(memberships -> organizations (organization_id));
+diesel::joinable!(memberships -> users (user_id));
+diesel::joinable!(notifications -> contacts (contact_id));
+diesel::joinable!(notifications -> users (user_id));
+diesel::joinable!(tags -> organizations (organization_id));
+diesel::joinable!(tags -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(auths, contacts, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    audit_events,
+    auths,
+    carddav_resources,
+    contact_addresses,
+    contact_dates,
+    contact_emails,
+    contact_field_values,
+    contact_merges,
+    contact_phones,
+    contact_shares,
+    contact_tags,
+    contact_versions,
+    contacts,
+    custom_fields,
+    exports,
+    interactions,
+    invitations,
+    memberships,
+    notifications,
+    organizations,
+    tags,
+    users,
+);
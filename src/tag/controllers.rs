@@ -0,0 +1,232 @@
+use super::models::tag::{Tag, TagContacts, TagCount, TagPayload};
+use crate::{
+    auth::models::claims::Claims,
+    contact::{controllers::authorize_owner, models::contact::Owner},
+    route,
+    utils::{db::establish_connection, error::ApiError},
+};
+use axum::{extract::Path, Json, Router};
+use serde_json::{json, Value};
+use validator::Validate;
+
+/// Get the tags of an owner with their number of contacts
+fn list(claims: &Claims, owner: Owner) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let tags: Vec<TagCount> = Tag::all(connection, owner).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "tags": tags })))
+}
+
+/// Create a tag for an owner
+fn store(claims: &Claims, owner: Owner, payload: TagPayload) -> Result<Json<Tag>, ApiError> {
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+
+    let tag = Tag::create(connection, owner, payload).map_err(ApiError::from)?;
+
+    Ok(Json(tag))
+}
+
+/// Rename a tag of an owner
+fn edit(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    payload: TagPayload,
+) -> Result<Json<Tag>, ApiError> {
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+    Tag::find_owned(connection, owner, id).map_err(ApiError::from)?;
+
+    let tag = Tag::update(connection, id, payload).map_err(ApiError::from)?;
+
+    Ok(Json(tag))
+}
+
+/// Delete a tag of an owner
+fn remove(claims: &Claims, owner: Owner, id: i32) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+    Tag::find_owned(connection, owner, id).map_err(ApiError::from)?;
+
+    Tag::delete(connection, id).map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "message": "Tag deleted" })))
+}
+
+/// Apply or remove a tag of an owner on many contacts
+fn apply(
+    claims: &Claims,
+    owner: Owner,
+    id: i32,
+    payload: TagContacts,
+    add: bool,
+) -> Result<Json<Value>, ApiError> {
+    let connection = &mut establish_connection();
+
+    authorize_owner(connection, claims, owner)?;
+    let tag = Tag::find_owned(connection, owner, id).map_err(ApiError::from)?;
+
+    let count = if add {
+        tag.tag(connection, &payload.contact_ids)
+    } else {
+        tag.untag(connection, &payload.contact_ids)
+    }
+    .map_err(ApiError::from)?;
+
+    Ok(Json(json!({ "count": count })))
+}
+
+/// Get the tags of a user
+pub async fn get_all(claims: Claims, Path(user_id): Path<i32>) -> Result<Json<Value>, ApiError> {
+    list(&claims, Owner::User(user_id))
+}
+
+/// Create a tag for a user
+pub async fn create(
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<TagPayload>,
+) -> Result<Json<Tag>, ApiError> {
+    store(&claims, Owner::User(user_id), payload)
+}
+
+/// Rename a tag of a user
+pub async fn update(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<TagPayload>,
+) -> Result<Json<Tag>, ApiError> {
+    edit(&claims, Owner::User(user_id), id, payload)
+}
+
+/// Delete a tag of a user
+pub async fn delete(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    remove(&claims, Owner::User(user_id), id)
+}
+
+/// Tag contacts of a user
+pub async fn tag_contacts(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<TagContacts>,
+) -> Result<Json<Value>, ApiError> {
+    apply(&claims, Owner::User(user_id), id, payload, true)
+}
+
+/// Untag contacts of a user
+pub async fn untag_contacts(
+    claims: Claims,
+    Path((user_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<TagContacts>,
+) -> Result<Json<Value>, ApiError> {
+    apply(&claims, Owner::User(user_id), id, payload, false)
+}
+
+/// Get the tags of an organization
+pub async fn get_all_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+) -> Result<Json<Value>, ApiError> {
+    list(&claims, Owner::Organization(organization_id))
+}
+
+/// Create a tag for an organization
+pub async fn create_for_organization(
+    claims: Claims,
+    Path(organization_id): Path<i32>,
+    Json(payload): Json<TagPayload>,
+) -> Result<Json<Tag>, ApiError> {
+    store(&claims, Owner::Organization(organization_id), payload)
+}
+
+/// Rename a tag of an organization
+pub async fn update_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<TagPayload>,
+) -> Result<Json<Tag>, ApiError> {
+    edit(&claims, Owner::Organization(organization_id), id, payload)
+}
+
+/// Delete a tag of an organization
+pub async fn delete_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, ApiError> {
+    remove(&claims, Owner::Organization(organization_id), id)
+}
+
+/// Tag contacts of an organization
+pub async fn tag_contacts_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<TagContacts>,
+) -> Result<Json<Value>, ApiError> {
+    apply(
+        &claims,
+        Owner::Organization(organization_id),
+        id,
+        payload,
+        true,
+    )
+}
+
+/// Untag contacts of an organization
+pub async fn untag_contacts_for_organization(
+    claims: Claims,
+    Path((organization_id, id)): Path<(i32, i32)>,
+    Json(payload): Json<TagContacts>,
+) -> Result<Json<Value>, ApiError> {
+    apply(
+        &claims,
+        Owner::Organization(organization_id),
+        id,
+        payload,
+        false,
+    )
+}
+
+/// Tag routes
+pub fn controller(router: &Router) -> Router {
+    router
+        .clone()
+        .route(
+            route("/users/:id/tags".to_string()).as_str(),
+            axum::routing::get(get_all).post(create),
+        )
+        .route(
+            route("/users/:id/tags/:tag_id".to_string()).as_str(),
+            axum::routing::put(update).delete(delete),
+        )
+        .route(
+            route("/users/:id/tags/:tag_id/contacts".to_string()).as_str(),
+            axum::routing::post(tag_contacts).delete(untag_contacts),
+        )
+        .route(
+            route("/organizations/:id/tags".to_string()).as_str(),
+            axum::routing::get(get_all_for_organization).post(create_for_organization),
+        )
+        .route(
+            route("/organizations/:id/tags/:tag_id".to_string()).as_str(),
+            axum::routing::put(update_for_organization).delete(delete_for_organization),
+        )
+        .route(
+            route("/organizations/:id/tags/:tag_id/contacts".to_string()).as_str(),
+            axum::routing::post(tag_contacts_for_organization)
+                .delete(untag_contacts_for_organization),
+        )
+}
@@ -0,0 +1,229 @@
+use crate::{
+    contact::models::contact::Owner,
+    schema::{contact_tags, contacts, tags},
+};
+use diesel::dsl::count;
+use diesel::prelude::*;
+use diesel::result::Error;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct Tag {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub organization_id: Option<i32>,
+    pub name: String,
+}
+
+/// A tag with the number of contacts it is applied to
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    #[serde(flatten)]
+    pub tag: Tag,
+    pub count: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = tags)]
+struct NewTag {
+    user_id: Option<i32>,
+    organization_id: Option<i32>,
+    name: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = contact_tags)]
+struct ContactTag {
+    contact_id: i32,
+    tag_id: i32,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TagPayload {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagContacts {
+    pub contact_ids: Vec<i32>,
+}
+
+impl Tag {
+    /// Get the tags of an owner with their number of contacts
+    pub fn all(connection: &mut SqliteConnection, owner: Owner) -> Result<Vec<TagCount>, Error> {
+        let query = tags::table
+            .left_join(contact_tags::table)
+            .group_by(tags::id)
+            .select((
+                tags::all_columns,
+                count(contact_tags::contact_id.nullable()),
+            ))
+            .order(tags::name.asc())
+            .into_boxed();
+
+        let query = match owner {
+            Owner::User(user) => query.filter(tags::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(tags::organization_id.eq(organization))
+            }
+        };
+
+        let results = query.load::<(Tag, i64)>(connection)?;
+
+        Ok(results
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect())
+    }
+
+    /// Find a tag by id, only if it belongs to the owner
+    pub fn find_owned(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        id: i32,
+    ) -> Result<Self, Error> {
+        let query = tags::table.filter(tags::id.eq(id)).into_boxed();
+
+        let query = match owner {
+            Owner::User(user) => query.filter(tags::user_id.eq(user)),
+            Owner::Organization(organization) => {
+                query.filter(tags::organization_id.eq(organization))
+            }
+        };
+
+        query.first::<Tag>(connection)
+    }
+
+    /// Create a tag for an owner
+    pub fn create(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+        payload: TagPayload,
+    ) -> Result<Self, Error> {
+        let (user_id, organization_id) = match owner {
+            Owner::User(user) => (Some(user), None),
+            Owner::Organization(organization) => (None, Some(organization)),
+        };
+
+        connection.transaction(|connection| {
+            diesel::insert_into(tags::table)
+                .values(&NewTag {
+                    user_id,
+                    organization_id,
+                    name: payload.name.trim().to_string(),
+                })
+                .execute(connection)?;
+
+            tags::table.order(tags::id.desc()).first::<Tag>(connection)
+        })
+    }
+
+    /// Rename a tag
+    pub fn update(
+        connection: &mut SqliteConnection,
+        id: i32,
+        payload: TagPayload,
+    ) -> Result<Self, Error> {
+        diesel::update(tags::table.find(id))
+            .set(tags::name.eq(payload.name.trim()))
+            .execute(connection)?;
+
+        tags::table.find(id).first::<Tag>(connection)
+    }
+
+    /// Delete a tag, removing it from its contacts
+    pub fn delete(connection: &mut SqliteConnection, id: i32) -> Result<usize, Error> {
+        connection.transaction(|connection| {
+            diesel::delete(contact_tags::table.filter(contact_tags::tag_id.eq(id)))
+                .execute(connection)?;
+            diesel::delete(tags::table.find(id)).execute(connection)
+        })
+    }
+
+    /// Delete all the tags of an owner
+    pub fn delete_for_owner(
+        connection: &mut SqliteConnection,
+        owner: Owner,
+    ) -> Result<usize, Error> {
+        let ids = match owner {
+            Owner::User(user) => tags::table
+                .select(tags::id)
+                .filter(tags::user_id.eq(user))
+                .load::<i32>(connection)?,
+            Owner::Organization(organization) => tags::table
+                .select(tags::id)
+                .filter(tags::organization_id.eq(organization))
+                .load::<i32>(connection)?,
+        };
+
+        diesel::delete(contact_tags::table.filter(contact_tags::tag_id.eq_any(&ids)))
+            .execute(connection)?;
+        diesel::delete(tags::table.filter(tags::id.eq_any(&ids))).execute(connection)
+    }
+
+    /// Apply the tag to contacts of its owner, returns the number of contacts newly tagged
+    pub fn tag(
+        &self,
+        connection: &mut SqliteConnection,
+        contact_ids: &[i32],
+    ) -> Result<usize, Error> {
+        connection.transaction(|connection| {
+            self.check_contacts(connection, contact_ids)?;
+
+            let rows = contact_ids
+                .iter()
+                .map(|contact_id| ContactTag {
+                    contact_id: *contact_id,
+                    tag_id: self.id,
+                })
+                .collect::<Vec<_>>();
+
+            diesel::insert_or_ignore_into(contact_tags::table)
+                .values(&rows)
+                .execute(connection)
+        })
+    }
+
+    /// Remove the tag from contacts, returns the number of contacts untagged
+    pub fn untag(
+        &self,
+        connection: &mut SqliteConnection,
+        contact_ids: &[i32],
+    ) -> Result<usize, Error> {
+        diesel::delete(
+            contact_tags::table
+                .filter(contact_tags::tag_id.eq(self.id))
+                .filter(contact_tags::contact_id.eq_any(contact_ids)),
+        )
+        .execute(connection)
+    }
+
+    /// Check that all the contacts have the same owner as the tag
+    fn check_contacts(
+        &self,
+        connection: &mut SqliteConnection,
+        contact_ids: &[i32],
+    ) -> Result<(), Error> {
+        let owned = contacts::table
+            .filter(contacts::id.eq_any(contact_ids))
+            .filter(
+                contacts::user_id
+                    .eq(self.user_id)
+                    .or(contacts::organization_id.eq(self.organization_id)),
+            )
+            .count()
+            .get_result::<i64>(connection)?;
+
+        let mut unique = contact_ids.to_vec();
+        unique.sort_unstable();
+        unique.dedup();
+
+        if owned as usize != unique.len() {
+            return Err(Error::NotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -1,11 +1,29 @@
-use super::models::user::{Update, User};
+use super::models::user::{Role, Update, User};
 use crate::{
-    auth::models::claims::Claims,
+    auth::models::{
+        audit::{AuditAction, AuditEvent},
+        claims::Claims,
+    },
     route,
-    utils::{db::establish_connection, error::ApiError},
+    utils::{
+        db::establish_connection,
+        error::ApiError,
+        etag::{check_if_match, etag, not_modified},
+        image::{self, image_key, thumbnail_key, MAX_UPLOAD_SIZE},
+        storage::STORAGE,
+        upload::read_fields,
+    },
 };
-use axum::{extract::Path, Json, Router};
+use axum::{
+    extract::{Multipart, Path, Query},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use diesel::Connection;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use validator::Validate;
 
 /// Get all users
 async fn get_all(claims: Claims) -> Result<Json<Value>, ApiError> {
@@ -20,8 +38,12 @@ async fn get_all(claims: Claims) -> Result<Json<Value>, ApiError> {
     Ok(Json(json!({ "users": users })))
 }
 
-/// Get a user by id
-async fn get_one(claims: Claims, Path(id): Path<i32>) -> Result<Json<User>, ApiError> {
+/// Get a user by id, with its version as ETag
+async fn get_one(
+    claims: Claims,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
     if !claims.is_admin() && !claims.is_user(id) {
         return Err(ApiError::InvalidToken);
     }
@@ -30,39 +52,202 @@ async fn get_one(claims: Claims, Path(id): Path<i32>) -> Result<Json<User>, ApiE
 
     let user = User::find(connection, id).map_err(|_| ApiError::NotFound)?;
 
-    Ok(Json(user))
+    let tag = etag(user.version);
+    if let Some(response) = not_modified(&headers, &tag) {
+        return Ok(response);
+    }
+
+    Ok(([(header::ETAG, tag)], Json(user)).into_response())
 }
 
-/// Update a user
+/// Update a user, only if it is still at the version of `If-Match` when given
 async fn update_one(
     claims: Claims,
     Path(id): Path<i32>,
+    headers: HeaderMap,
     Json(payload): Json<Update>,
-) -> Result<Json<User>, ApiError> {
+) -> Result<Response, ApiError> {
     if !claims.is_admin() && !claims.is_user(id) {
         return Err(ApiError::InvalidToken);
     }
 
+    // Roles are managed by the admins only
+    if payload.role.is_some() && !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    if let Some(role) = &payload.role {
+        Role::parse(role).ok_or(ApiError::NotValid)?;
+    }
+
+    payload.validate().map_err(|_| ApiError::NotValid)?;
+
     let connection = &mut establish_connection();
 
-    let user = User::update(connection, id, payload).map_err(|_| ApiError::InternalServerError)?;
+    let user = connection.transaction(|connection| {
+        let current = User::find(connection, id).map_err(|_| ApiError::NotFound)?;
+        check_if_match(&headers, &etag(current.version))?;
 
-    Ok(Json(user))
+        let user = User::update(connection, id, payload).map_err(ApiError::from)?;
+        AuditEvent::record(connection, id, Some(claims.id()), AuditAction::Update)
+            .map_err(ApiError::from)?;
+
+        Ok::<_, ApiError>(user)
+    })?;
+
+    Ok(([(header::ETAG, etag(user.version))], Json(user)).into_response())
 }
 
-/// Delete a user
-async fn delete_one(claims: Claims, Path(id): Path<i32>) -> Result<Json<Value>, ApiError> {
+/// Delete a user, only if it is still at the version of `If-Match` when given
+async fn delete_one(
+    claims: Claims,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, ApiError> {
     if !claims.is_admin() && !claims.is_user(id) {
         return Err(ApiError::InvalidToken);
     }
 
     let connection = &mut establish_connection();
 
-    User::delete(connection, id).map_err(|_| ApiError::InternalServerError)?;
+    connection.immediate_transaction(|connection| {
+        let current = User::find(connection, id).map_err(|_| ApiError::NotFound)?;
+        check_if_match(&headers, &etag(current.version))?;
+        // The last admin must stay, whoever deletes it
+        if User::is_last_admin(connection, id).map_err(ApiError::from)? {
+            return Err(ApiError::Conflict);
+        }
+
+        User::delete(connection, id).map_err(|_| ApiError::NotFound)?;
+        AuditEvent::record(connection, id, Some(claims.id()), AuditAction::Delete)
+            .map_err(ApiError::from)
+    })?;
 
     Ok(Json(json!({ "message": "User deleted" })))
 }
 
+#[derive(Debug, Deserialize)]
+struct AvatarQuery {
+    thumbnail: Option<bool>,
+}
+
+/// Upload the avatar of a user, sent as the `avatar` field of a multipart form
+async fn upload_avatar(
+    claims: Claims,
+    Path(id): Path<i32>,
+    mut multipart: Multipart,
+) -> Result<Json<User>, ApiError> {
+    if !claims.is_admin() && !claims.is_user(id) {
+        return Err(ApiError::InvalidToken);
+    }
+
+    let mut fields = read_fields(&mut multipart, MAX_UPLOAD_SIZE).await?;
+    let bytes = fields.remove("avatar").ok_or(ApiError::NotValid)?;
+
+    let processed = tokio::task::spawn_blocking(move || image::process(&bytes))
+        .await
+        .map_err(|_| ApiError::InternalServerError)??;
+
+    let connection = &mut establish_connection();
+
+    let user = User::find(connection, id).map_err(ApiError::from)?;
+
+    let avatar = format!("avatars/{}-{}", id, chrono::Utc::now().timestamp_millis());
+    let updated = STORAGE
+        .put(&image_key(&avatar), &processed.image)
+        .and_then(|_| STORAGE.put(&thumbnail_key(&avatar), &processed.thumbnail))
+        .map_err(|_| ApiError::InternalServerError)
+        .and_then(|_| {
+            User::set_avatar(connection, id, Some(avatar.clone())).map_err(ApiError::from)
+        });
+
+    // Nothing references the new files when the user could not be updated
+    let updated = match updated {
+        Ok(updated) => updated,
+        Err(err) => {
+            let _ = STORAGE.delete(&image_key(&avatar));
+            let _ = STORAGE.delete(&thumbnail_key(&avatar));
+            return Err(err);
+        }
+    };
+
+    if let Some(previous) = user.avatar {
+        let _ = STORAGE.delete(&image_key(&previous));
+        let _ = STORAGE.delete(&thumbnail_key(&previous));
+    }
+
+    Ok(Json(updated))
+}
+
+/// Get the avatar of a user, or its thumbnail with `?thumbnail=true`
+async fn get_avatar(
+    _claims: Claims,
+    Path(id): Path<i32>,
+    Query(query): Query<AvatarQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let connection = &mut establish_connection();
+
+    let user = User::find(connection, id).map_err(ApiError::from)?;
+    let avatar = user.avatar.ok_or(ApiError::NotFound)?;
+
+    let key = if query.thumbnail.unwrap_or(false) {
+        thumbnail_key(&avatar)
+    } else {
+        image_key(&avatar)
+    };
+    // A new upload gets a new key, so the key identifies the content
+    let tag = format!("\"{}\"", key);
+    if let Some(response) = not_modified(&headers, &tag) {
+        return Ok(response);
+    }
+    let bytes = STORAGE.get(&key).map_err(|_| ApiError::NotFound)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/png".to_string()),
+            (header::CACHE_CONTROL, "private, max-age=86400".to_string()),
+            (header::ETAG, tag),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// Delete the avatar of a user
+async fn delete_avatar(claims: Claims, Path(id): Path<i32>) -> Result<Json<User>, ApiError> {
+    if !claims.is_admin() && !claims.is_user(id) {
+        return Err(ApiError::InvalidToken);
+    }
+
+    let connection = &mut establish_connection();
+
+    let user = User::find(connection, id).map_err(ApiError::from)?;
+    let updated = User::set_avatar(connection, id, None).map_err(ApiError::from)?;
+
+    if let Some(avatar) = user.avatar {
+        let _ = STORAGE.delete(&image_key(&avatar));
+        let _ = STORAGE.delete(&thumbnail_key(&avatar));
+    }
+
+    Ok(Json(updated))
+}
+
+/// Restore a deleted user
+async fn restore_one(claims: Claims, Path(id): Path<i32>) -> Result<Json<User>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::AdminRequired);
+    }
+
+    let connection = &mut establish_connection();
+
+    let user = User::restore(connection, id).map_err(ApiError::from)?;
+    AuditEvent::record(connection, id, Some(claims.id()), AuditAction::Restore)
+        .map_err(ApiError::from)?;
+
+    Ok(Json(user))
+}
+
 /// User routes
 pub fn controller(router: &Router) -> Router {
     router
@@ -83,4 +268,14 @@ pub fn controller(router: &Router) -> Router {
             route("/user/:id".to_string()).as_str(),
             axum::routing::delete(delete_one),
         )
+        .route(
+            route("/user/:id/avatar".to_string()).as_str(),
+            axum::routing::get(get_avatar)
+                .put(upload_avatar)
+                .delete(delete_avatar),
+        )
+        .route(
+            route("/user/:id/restore".to_string()).as_str(),
+            axum::routing::post(restore_one),
+        )
 }
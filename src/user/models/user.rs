@@ -1,11 +1,24 @@
 use crate::{
-    auth::models::auth::NewAuth,
-    schema::{auths, users},
+    auth::models::{audit::AuditEvent, auth::NewAuth},
+    carddav::models::resource::CardResource,
+    contact::models::{
+        contact::{Contact, Owner},
+        share::ContactShare,
+    },
+    field::models::field::CustomField,
+    reminder::models::notification::Notification,
+    schema::{auths, exports, invitations, memberships, users},
+    tag::models::tag::Tag,
+    utils::{
+        image::{image_key, thumbnail_key},
+        storage::STORAGE,
+    },
 };
+use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use diesel::result::Error;
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 #[derive(Queryable, PartialEq, Debug, Deserialize, Serialize)]
 pub struct User {
@@ -13,6 +26,14 @@ pub struct User {
     pub name: String,
     pub email: String,
     pub role: String,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub suspended_at: Option<NaiveDateTime>,
+    pub display_name: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub avatar: Option<String>,
+    pub reminder_time: Option<String>,
+    pub version: i32,
 }
 
 #[derive(Insertable, Deserialize, Validate)]
@@ -25,9 +46,25 @@ pub struct NewUser {
     pub role: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Role {
-    Admin(String),
-    User(String),
+    Admin,
+    User,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct NewAccount {
+    #[validate(length(min = 4))]
+    pub name: String,
+    #[validate(email)]
+    pub email: String,
+    pub password: String,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleUpdate {
+    pub role: String,
 }
 
 #[derive(Debug, Validate, Deserialize)]
@@ -42,17 +79,121 @@ pub struct Register {
 #[derive(Debug, Deserialize, Validate, AsChangeset)]
 #[diesel(table_name = users)]
 pub struct Update {
+    #[validate(length(min = 4))]
     pub name: Option<String>,
+    #[validate(email)]
     pub email: Option<String>,
     pub role: Option<String>,
+    #[validate(length(min = 1, max = 255))]
+    pub display_name: Option<String>,
+    #[validate(custom = "validate_locale")]
+    pub locale: Option<String>,
+    #[validate(custom = "validate_timezone")]
+    pub timezone: Option<String>,
+    #[validate(custom = "validate_reminder_time")]
+    pub reminder_time: Option<String>,
+}
+
+/// Normalize an email so that two spellings of the same address are equal
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Check that a locale looks like a BCP 47 language tag (`fr`, `en-US`, `zh-Hant-TW`)
+fn validate_locale(locale: &str) -> Result<(), ValidationError> {
+    let mut subtags = locale.split('-');
+
+    let language = subtags.next().unwrap_or_default();
+    let valid_language =
+        (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase());
+    let valid_subtags = subtags.all(|subtag| {
+        (2..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+    });
+
+    if valid_language && valid_subtags {
+        Ok(())
+    } else {
+        Err(ValidationError::new("locale"))
+    }
+}
+
+/// Check that a timezone is a known IANA timezone (`Europe/Paris`)
+fn validate_timezone(timezone: &str) -> Result<(), ValidationError> {
+    timezone
+        .parse::<chrono_tz::Tz>()
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("timezone"))
+}
+
+/// Check that a reminder time is a time of the day (`09:30`)
+fn validate_reminder_time(time: &str) -> Result<(), ValidationError> {
+    chrono::NaiveTime::parse_from_str(time, "%H:%M")
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("reminder_time"))
+}
+
+impl Role {
+    /// Get the role as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::User => "user",
+        }
+    }
+
+    /// Parse a role from its database value
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "admin" => Some(Self::Admin),
+            "user" => Some(Self::User),
+            _ => None,
+        }
+    }
 }
 
 impl User {
+    /// Check if the account is suspended
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_at.is_some()
+    }
+
+    /// Check if there is at least one active admin
+    pub fn admin_exists(connection: &mut SqliteConnection) -> Result<bool, Error> {
+        Ok(User::active_admins(connection, None)? > 0)
+    }
+
+    /// Check if a user is the only active admin, who must stay one so that the users can still be
+    /// managed
+    pub fn is_last_admin(connection: &mut SqliteConnection, id_param: i32) -> Result<bool, Error> {
+        let user = User::find(connection, id_param)?;
+        if user.role != Role::Admin.as_str() || user.is_suspended() {
+            return Ok(false);
+        }
+
+        Ok(User::active_admins(connection, Some(id_param))? == 0)
+    }
+
+    /// Count the admins which are neither deleted nor suspended, except the given user
+    fn active_admins(connection: &mut SqliteConnection, except: Option<i32>) -> Result<i64, Error> {
+        use crate::schema::users::dsl::*;
+
+        let mut query = users
+            .filter(role.eq(Role::Admin.as_str()))
+            .filter(deleted_at.is_null())
+            .filter(suspended_at.is_null())
+            .into_boxed();
+        if let Some(except) = except {
+            query = query.filter(id.ne(except));
+        }
+
+        query.count().get_result::<i64>(connection)
+    }
+
     /// Get all users
     pub fn all(connection: &mut SqliteConnection) -> Result<Vec<User>, Error> {
         use crate::schema::users::dsl::*;
 
-        let results = users.load::<User>(connection)?;
+        let results = users.filter(deleted_at.is_null()).load::<User>(connection)?;
 
         Ok(results)
     }
@@ -61,7 +202,10 @@ impl User {
     pub fn find(connection: &mut SqliteConnection, id_param: i32) -> Result<Self, Error> {
         use crate::schema::users::dsl::*;
 
-        let user = users.find(id_param).first::<User>(connection)?;
+        let user = users
+            .find(id_param)
+            .filter(deleted_at.is_null())
+            .first::<User>(connection)?;
 
         Ok(user)
     }
@@ -74,66 +218,206 @@ impl User {
         use crate::schema::users::dsl::*;
 
         users
-            .filter(email.eq(email_param))
+            .filter(email.eq(normalize_email(&email_param)))
+            .filter(deleted_at.is_null())
             .first::<User>(connection)
     }
 
-    /// Create a new user
-    pub fn create(connection: &mut SqliteConnection, param: Register) -> Result<Self, Error> {
-        let already_exists = User::find_by_email(connection, param.email.clone()).is_ok();
-        if already_exists {
-            return Err(Error::RollbackTransaction);
-        }
-
+    /// Create a new user with the given role
+    pub fn create(
+        connection: &mut SqliteConnection,
+        param: Register,
+        role: Role,
+    ) -> Result<Self, Error> {
         let new_user = NewUser {
             name: param.name,
-            email: param.email,
-            role: String::from("user"),
+            email: normalize_email(&param.email),
+            role: role.as_str().to_string(),
         };
 
-        diesel::insert_into(users::table)
-            .values(&new_user)
-            .execute(connection)?;
-
-        let user = users::table
-            .order(users::id.desc())
-            .first::<User>(connection)?;
+        // The unique index on the email rejects the duplicates
+        connection.transaction(|connection| {
+            diesel::insert_into(users::table)
+                .values(&new_user)
+                .execute(connection)?;
 
-        let new_auth = NewAuth::new(user.id, param.password);
+            let user = users::table
+                .order(users::id.desc())
+                .first::<User>(connection)?;
 
-        let res = diesel::insert_into(auths::table)
-            .values(&new_auth)
-            .execute(connection)
-            .map_err(|_| {
-                diesel::delete(users::table.filter(users::id.eq(user.id))).execute(connection)
-            });
+            let new_auth = NewAuth::new(user.id, param.password);
 
-        if res.is_err() {
-            return Err(Error::RollbackTransaction);
-        }
+            diesel::insert_into(auths::table)
+                .values(&new_auth)
+                .execute(connection)?;
 
-        Ok(user)
+            Ok(user)
+        })
     }
 
     /// Update a user
     pub fn update(
         connection: &mut SqliteConnection,
         id_param: i32,
-        param: Update,
+        mut param: Update,
+    ) -> Result<Self, Error> {
+        use crate::schema::users::dsl::*;
+
+        param.email = param.email.as_deref().map(normalize_email);
+
+        diesel::update(users.filter(id.eq(id_param)).filter(deleted_at.is_null()))
+            .set((param, version.eq(version + 1)))
+            .execute(connection)?;
+
+        User::find(connection, id_param)
+    }
+
+    /// Change the role of a user
+    pub fn set_role(
+        connection: &mut SqliteConnection,
+        id_param: i32,
+        role_param: Role,
+    ) -> Result<Self, Error> {
+        use crate::schema::users::dsl::*;
+
+        diesel::update(users.find(id_param).filter(deleted_at.is_null()))
+            .set((role.eq(role_param.as_str()), version.eq(version + 1)))
+            .execute(connection)?;
+
+        User::find(connection, id_param)
+    }
+
+    /// Suspend a user, who can no longer log in
+    pub fn suspend(connection: &mut SqliteConnection, id_param: i32) -> Result<Self, Error> {
+        use crate::schema::users::dsl::*;
+
+        diesel::update(users.find(id_param).filter(suspended_at.is_null()))
+            .set((
+                suspended_at.eq(chrono::Utc::now().naive_utc()),
+                version.eq(version + 1),
+            ))
+            .execute(connection)?;
+
+        User::find(connection, id_param)
+    }
+
+    /// Reactivate a suspended user
+    pub fn reactivate(connection: &mut SqliteConnection, id_param: i32) -> Result<Self, Error> {
+        use crate::schema::users::dsl::*;
+
+        diesel::update(users.find(id_param))
+            .set((
+                suspended_at.eq(None::<NaiveDateTime>),
+                version.eq(version + 1),
+            ))
+            .execute(connection)?;
+
+        User::find(connection, id_param)
+    }
+
+    /// Set the storage key of the avatar of a user
+    pub fn set_avatar(
+        connection: &mut SqliteConnection,
+        id_param: i32,
+        avatar_param: Option<String>,
     ) -> Result<Self, Error> {
         use crate::schema::users::dsl::*;
 
-        diesel::update(users.filter(id.eq(id_param)))
-            .set::<Update>(param)
+        diesel::update(users.find(id_param).filter(deleted_at.is_null()))
+            .set((avatar.eq(avatar_param), version.eq(version + 1)))
             .execute(connection)?;
 
-        users.find(id_param).first(connection)
+        User::find(connection, id_param)
     }
 
-    /// Delete a user
+    /// Soft delete a user, the row is kept until it is purged
     pub fn delete(connection: &mut SqliteConnection, id_param: i32) -> Result<usize, Error> {
         use crate::schema::users::dsl::*;
 
-        diesel::delete(users.find(id_param)).execute(connection)
+        let result = diesel::update(users.find(id_param).filter(deleted_at.is_null()))
+            .set(deleted_at.eq(chrono::Utc::now().naive_utc()))
+            .execute(connection)?;
+
+        if result == 0 {
+            return Err(Error::NotFound);
+        }
+
+        Ok(result)
+    }
+
+    /// Restore a soft deleted user
+    pub fn restore(connection: &mut SqliteConnection, id_param: i32) -> Result<Self, Error> {
+        use crate::schema::users::dsl::*;
+
+        let result = diesel::update(users.find(id_param).filter(deleted_at.is_not_null()))
+            .set(deleted_at.eq(None::<NaiveDateTime>))
+            .execute(connection)?;
+
+        if result == 0 {
+            return Err(Error::NotFound);
+        }
+
+        User::find(connection, id_param)
+    }
+
+    /// Hard delete the users soft deleted before the given date, with their auths and contacts
+    pub fn purge(connection: &mut SqliteConnection, before: NaiveDateTime) -> Result<usize, Error> {
+        // The files are deleted once the transaction is committed, a rollback keeps them
+        let (deleted, files) = connection.transaction(|connection| {
+            let ids = users::table
+                .select(users::id)
+                .filter(users::deleted_at.lt(before))
+                .load::<i32>(connection)?;
+
+            if ids.is_empty() {
+                return Ok((0, Vec::new()));
+            }
+
+            let mut files = Vec::new();
+            for id in &ids {
+                for photo in Contact::delete_for_owner(connection, Owner::User(*id))? {
+                    files.push(image_key(&photo));
+                    files.push(thumbnail_key(&photo));
+                }
+                Tag::delete_for_owner(connection, Owner::User(*id))?;
+                CustomField::delete_for_owner(connection, Owner::User(*id))?;
+            }
+            ContactShare::delete_for_users(connection, &ids)?;
+            Notification::delete_for_users(connection, &ids)?;
+            CardResource::delete_for_users(connection, &ids)?;
+
+            let avatars = users::table
+                .select(users::avatar)
+                .filter(users::id.eq_any(&ids))
+                .load::<Option<String>>(connection)?;
+            for avatar in avatars.into_iter().flatten() {
+                files.push(image_key(&avatar));
+                files.push(thumbnail_key(&avatar));
+            }
+            let exports = exports::table
+                .select(exports::file)
+                .filter(exports::user_id.eq_any(&ids))
+                .load::<Option<String>>(connection)?;
+            files.extend(exports.into_iter().flatten());
+
+            diesel::delete(exports::table.filter(exports::user_id.eq_any(&ids)))
+                .execute(connection)?;
+            diesel::delete(invitations::table.filter(invitations::invited_by.eq_any(&ids)))
+                .execute(connection)?;
+            diesel::delete(memberships::table.filter(memberships::user_id.eq_any(&ids)))
+                .execute(connection)?;
+            diesel::delete(auths::table.filter(auths::user_id.eq_any(&ids))).execute(connection)?;
+            AuditEvent::delete_for_users(connection, &ids)?;
+            let deleted =
+                diesel::delete(users::table.filter(users::id.eq_any(&ids))).execute(connection)?;
+
+            Ok::<_, Error>((deleted, files))
+        })?;
+
+        for file in files {
+            let _ = STORAGE.delete(&file);
+        }
+
+        Ok(deleted)
     }
 }
@@ -1,4 +1,4 @@
-use diesel::{Connection, SqliteConnection};
+use diesel::{connection::SimpleConnection, Connection, SqliteConnection};
 use dotenv::dotenv;
 use std::env::var;
 
@@ -7,6 +7,13 @@ pub fn establish_connection() -> SqliteConnection {
     dotenv().ok();
 
     let database_url = var("DATABASE_URL").expect("DATABASE_URL must be set");
-    SqliteConnection::establish(&database_url)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
+    let mut connection = SqliteConnection::establish(&database_url)
+        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url));
+
+    // The background jobs write at the same time as the requests, so a lock is waited for
+    connection
+        .batch_execute("PRAGMA busy_timeout = 5000;")
+        .unwrap_or_else(|_| panic!("Error configuring {}", database_url));
+
+    connection
 }
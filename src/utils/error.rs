@@ -3,6 +3,7 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use diesel::result::DatabaseErrorKind;
 use serde_json::json;
 
 #[derive(Debug)]
@@ -14,7 +15,25 @@ pub enum ApiError {
     MissingCredentials,
     TokenCreation,
     AdminRequired,
+    Forbidden,
+    AccountSuspended,
     NotValid,
+    Conflict,
+    PreconditionFailed,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+}
+
+impl From<diesel::result::Error> for ApiError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => Self::NotFound,
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                Self::Conflict
+            }
+            _ => Self::InternalServerError,
+        }
+    }
 }
 
 impl IntoResponse for ApiError {
@@ -24,10 +43,17 @@ impl IntoResponse for ApiError {
                 (StatusCode::BAD_REQUEST, "Bad request")
             }
             Self::NotFound => (StatusCode::NOT_FOUND, "Not found"),
+            Self::Conflict => (StatusCode::CONFLICT, "Conflict"),
+            Self::PreconditionFailed => (StatusCode::PRECONDITION_FAILED, "Precondition failed"),
+            Self::PayloadTooLarge => (StatusCode::PAYLOAD_TOO_LARGE, "Payload too large"),
+            Self::UnsupportedMediaType => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported media type")
+            }
             Self::InternalServerError | Self::TokenCreation => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
             }
-            Self::AdminRequired => (StatusCode::FORBIDDEN, "Forbidden"),
+            Self::AdminRequired | Self::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            Self::AccountSuspended => (StatusCode::FORBIDDEN, "Account suspended"),
             Self::WrongCredentials => (StatusCode::UNAUTHORIZED, "Wrong credentials"),
         };
         let body = Json(json!({
@@ -0,0 +1,37 @@
+use super::error::ApiError;
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// Write the ETag of a resource from its version
+pub fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Check if an `If-Match` or `If-None-Match` header matches an ETag
+pub fn matches(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|expected| expected == "*" || expected == etag)
+}
+
+/// Check the `If-Match` header of a change against the current ETag of the resource, a request
+/// without the header is always applied
+pub fn check_if_match(headers: &HeaderMap, etag: &str) -> Result<(), ApiError> {
+    match headers.get(header::IF_MATCH).map(|value| value.to_str()) {
+        None => Ok(()),
+        Some(Ok(expected)) if matches(expected, etag) => Ok(()),
+        Some(_) => Err(ApiError::PreconditionFailed),
+    }
+}
+
+/// Answer `304 Not Modified` when the `If-None-Match` header of a read matches the ETag
+pub fn not_modified(headers: &HeaderMap, etag: &str) -> Option<Response> {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .filter(|expected| matches(expected, etag))
+        .map(|_| (StatusCode::NOT_MODIFIED, [(header::ETAG, etag.to_string())]).into_response())
+}
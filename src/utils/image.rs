@@ -0,0 +1,67 @@
+use crate::utils::error::ApiError;
+use image::{imageops::FilterType, ImageFormat};
+use std::io::Cursor;
+
+/// Largest accepted upload, in bytes
+pub const MAX_UPLOAD_SIZE: usize = 5 * 1024 * 1024;
+
+/// Largest side of a stored image, in pixels
+const MAX_SIDE: u32 = 1024;
+
+/// Side of a thumbnail, in pixels
+const THUMBNAIL_SIDE: u32 = 128;
+
+/// An uploaded image resized and encoded as PNG, with its square thumbnail
+pub struct ProcessedImage {
+    pub image: Vec<u8>,
+    pub thumbnail: Vec<u8>,
+}
+
+/// Storage key of the full size image stored under a name
+pub fn image_key(name: &str) -> String {
+    format!("{}.png", name)
+}
+
+/// Storage key of the thumbnail stored under a name
+pub fn thumbnail_key(name: &str) -> String {
+    format!("{}-thumbnail.png", name)
+}
+
+/// Check that the upload is a PNG, JPEG, GIF or WebP image and resize it
+pub fn process(bytes: &[u8]) -> Result<ProcessedImage, ApiError> {
+    if bytes.len() > MAX_UPLOAD_SIZE {
+        return Err(ApiError::PayloadTooLarge);
+    }
+
+    let format = image::guess_format(bytes).map_err(|_| ApiError::UnsupportedMediaType)?;
+    if !matches!(
+        format,
+        ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP
+    ) {
+        return Err(ApiError::UnsupportedMediaType);
+    }
+
+    let decoded =
+        image::load_from_memory_with_format(bytes, format).map_err(|_| ApiError::NotValid)?;
+
+    let resized = if decoded.width() > MAX_SIDE || decoded.height() > MAX_SIDE {
+        decoded.resize(MAX_SIDE, MAX_SIDE, FilterType::Lanczos3)
+    } else {
+        decoded.clone()
+    };
+    let thumbnail = decoded.resize_to_fill(THUMBNAIL_SIDE, THUMBNAIL_SIDE, FilterType::Lanczos3);
+
+    Ok(ProcessedImage {
+        image: encode_png(&resized)?,
+        thumbnail: encode_png(&thumbnail)?,
+    })
+}
+
+/// Encode an image as PNG
+fn encode_png(image: &image::DynamicImage) -> Result<Vec<u8>, ApiError> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|_| ApiError::InternalServerError)?;
+    Ok(bytes)
+}
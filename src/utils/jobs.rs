@@ -0,0 +1,108 @@
+use crate::{
+    contact::phone,
+    export::models::export::Export,
+    reminder::scheduler,
+    user::models::user::User,
+    utils::{db::establish_connection, storage::STORAGE},
+};
+use std::{env::var, time::Duration};
+
+/// Number of days a deleted user is kept before being purged
+fn retention_days() -> i64 {
+    var("USER_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(30)
+}
+
+/// Hard delete the users whose retention period is over
+fn purge_users() {
+    let connection = &mut establish_connection();
+
+    let before = chrono::Utc::now().naive_utc() - chrono::Duration::days(retention_days());
+
+    match User::purge(connection, before) {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("purged {} deleted users", count),
+        Err(err) => tracing::error!("failed to purge deleted users: {}", err),
+    }
+}
+
+/// Delete the export archives which can no longer be downloaded
+fn clear_exports() {
+    let connection = &mut establish_connection();
+
+    let expired = match Export::expired(connection, chrono::Utc::now().naive_utc()) {
+        Ok(expired) => expired,
+        Err(err) => {
+            tracing::error!("failed to find the expired exports: {}", err);
+            return;
+        }
+    };
+
+    for export in expired {
+        if let Some(file) = &export.file {
+            if let Err(err) = STORAGE.delete(file) {
+                tracing::error!("failed to delete {}: {}", file, err);
+                continue;
+            }
+        }
+        if let Err(err) = Export::clear_file(connection, export.id) {
+            tracing::error!("failed to clear export {}: {}", export.id, err);
+        }
+    }
+}
+
+/// Generate the reminders which are due and email them
+fn send_reminders() {
+    let connection = &mut establish_connection();
+
+    match scheduler::run(connection, chrono::Utc::now()) {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("sent {} reminders", count),
+        Err(err) => tracing::error!("failed to send the reminders: {}", err),
+    }
+}
+
+/// Write the phone numbers stored before their normalization in E.164
+fn normalize_phones() {
+    let connection = &mut establish_connection();
+
+    match phone::backfill(connection) {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("normalized the phone numbers of {} contacts", count),
+        Err(err) => tracing::error!("failed to normalize the phone numbers: {}", err),
+    }
+}
+
+/// Spawn the background jobs
+pub fn spawn() {
+    // The numbers are normalized once at startup, also catching up when the default region is set
+    tokio::task::spawn_blocking(normalize_phones);
+
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+
+        loop {
+            interval.tick().await;
+            if let Err(err) = tokio::task::spawn_blocking(purge_users).await {
+                tracing::error!("purge job panicked: {}", err);
+            }
+            if let Err(err) = tokio::task::spawn_blocking(clear_exports).await {
+                tracing::error!("export cleanup job panicked: {}", err);
+            }
+        }
+    });
+
+    // The reminders are sent at the time chosen by each user, so they are checked every minute
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+
+        loop {
+            interval.tick().await;
+            if let Err(err) = tokio::task::spawn_blocking(send_reminders).await {
+                tracing::error!("reminder job panicked: {}", err);
+            }
+        }
+    });
+}
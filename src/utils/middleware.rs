@@ -1,7 +1,7 @@
 use axum::http::StatusCode;
 use axum::{
     body::{Body, Bytes},
-    http::{Request, Response},
+    http::{header, Request, Response},
     middleware::Next,
     response::IntoResponse,
     Json,
@@ -25,6 +25,21 @@ pub async fn print_request_response(
     let uri = req.uri().to_string();
 
     let res = next.run(req).await;
+
+    // Files, other non JSON bodies and the responses which cannot have a body are sent untouched
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_none_or(|content_type| content_type.as_bytes().starts_with(b"application/json"));
+    let has_body = !matches!(
+        res.status(),
+        StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED
+    );
+    if !is_json || !has_body {
+        tracing::info!("{} {} {}", method, uri, res.status());
+        return Ok(res);
+    }
+
     let (parts, body) = res.into_parts();
     let bytes = buffer_and_print("response", body).await?;
     let res_bytes = bytes.clone();
@@ -39,7 +54,7 @@ pub async fn print_request_response(
         }
         _ => tracing::warn!("{} {} {}", method, uri, res.status()),
     }
-    Ok(format_response(res, res_bytes).await)
+    Ok(format_response(res, res_bytes).await.into_response())
 }
 
 /// Buffer the body and print it to stdout.
@@ -64,16 +79,28 @@ where
 
     Ok(bytes)
 }
-/// Format the response body to be a JSON object
+/// Format the response body to be a JSON object, the headers of the response are kept
 async fn format_response<B>(res: Response<B>, bytes: Bytes) -> impl IntoResponse {
-    // tracing::error!("404 Not Found");
     let json = serde_json::from_slice::<serde_json::Value>(&bytes);
 
-    Json(json!({
+    let mut formatted = Json(json!({
       "status": res.status().as_str(),
       "timestamp": current_date(),
       "body": json.unwrap_or(json!({})),
     }))
+    .into_response();
+    // The responses keep the HTTP status 200 with their status in the body, except a failed
+    // precondition which the clients of `If-Match` must see as such
+    if res.status() == StatusCode::PRECONDITION_FAILED {
+        *formatted.status_mut() = res.status();
+    }
+    for (name, value) in res.headers() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            formatted.headers_mut().append(name, value.clone());
+        }
+    }
+
+    formatted
 }
 
 fn current_date() -> String {
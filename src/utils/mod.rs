@@ -1,3 +1,9 @@
 pub mod db;
 pub mod error;
+pub mod etag;
+pub mod image;
+pub mod jobs;
+pub mod mailer;
 pub mod middleware;
+pub mod storage;
+pub mod upload;
@@ -0,0 +1,71 @@
+use once_cell::sync::Lazy;
+use std::{
+    env::var,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// A place where the uploaded files are kept, addressed by a relative key (`avatars/1.png`)
+pub trait Storage: Send + Sync {
+    /// Write a file, replacing any previous content
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Read a file
+    fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+
+    /// Delete a file, deleting a missing file is not an error
+    fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+/// Store the files in a directory of the local filesystem
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Create a storage rooted in the given directory
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Resolve a key to a path inside the root directory
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        let relative = Path::new(key);
+
+        let is_inside = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if key.is_empty() || !is_inside {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid key"));
+        }
+
+        Ok(self.root.join(relative))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, data)
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(key)?)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(key)?) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// The storage of the server, in the `STORAGE_DIR` directory
+pub static STORAGE: Lazy<Box<dyn Storage>> = Lazy::new(|| {
+    let root = var("STORAGE_DIR").unwrap_or_else(|_| "storage".to_string());
+    Box::new(LocalStorage::new(root))
+});
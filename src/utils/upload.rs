@@ -0,0 +1,33 @@
+use crate::utils::error::ApiError;
+use axum::extract::Multipart;
+use std::collections::HashMap;
+
+/// Read all the fields of a multipart upload, rejecting uploads larger than `max_size` bytes
+pub async fn read_fields(
+    multipart: &mut Multipart,
+    max_size: usize,
+) -> Result<HashMap<String, Vec<u8>>, ApiError> {
+    let mut fields = HashMap::new();
+    let mut size = 0;
+
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| ApiError::NotValid)?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        let mut data = Vec::new();
+
+        while let Some(chunk) = field.chunk().await.map_err(|_| ApiError::NotValid)? {
+            size += chunk.len();
+            if size > max_size {
+                return Err(ApiError::PayloadTooLarge);
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        fields.insert(name, data);
+    }
+
+    Ok(fields)
+}